@@ -35,3 +35,9 @@ sol!(
     Rainlang,
     "../../out/Rainlang.sol/Rainlang.json"
 );
+
+sol!(
+    #![sol(all_derives = true)]
+    RainterpreterParser,
+    "../../out/RainterpreterParser.sol/RainterpreterParser.json"
+);
@@ -1,39 +1,71 @@
+use crate::dispair::DISPaiRCliArgs;
 use crate::execute::Execute;
 use crate::fork::NewForkedEvmCliArgs;
 use crate::output::SupportedOutputEncoding;
-use alloy::primitives::{Address, U256};
+use alloy::hex;
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::sol_types::SolCall;
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::anyhow;
 use clap::Args;
 use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
-use rain_interpreter_eval::trace::RainEvalResult;
-use rain_interpreter_eval::{eval::ForkEvalArgs, fork::Forker};
-use std::path::PathBuf;
+use rain_interpreter_bindings::IParserV2::parse2Call;
+use rain_interpreter_eval::bytecode::SerializedExpression;
+use rain_interpreter_eval::compare::{CompareTarget, compare};
+use rain_interpreter_eval::context::ContextBuilder;
+use rain_interpreter_eval::error::ForkCallError;
+use rain_interpreter_eval::eval::ForkEvalArgs;
+use rain_interpreter_eval::fork::{EnvOverrides, Forker, NewForkedEvm, RpcOptions};
+use rain_interpreter_eval::trace::RainEvalOutcome;
+use std::path::{Path, PathBuf};
 
 /// CLI arguments for evaluating a Rainlang expression.
 #[derive(Args, Clone, Debug)]
 pub struct ForkEvalCliArgs {
-    #[arg(short, long, help = "The Rainlang string to parse")]
-    pub rainlang_string: String,
+    #[arg(
+        short,
+        long,
+        help = "The Rainlang string to parse",
+        required_unless_present = "bytecode"
+    )]
+    pub rainlang_string: Option<String>,
+
+    #[arg(
+        long,
+        help = "Pre-parsed bytecode to evaluate instead of a Rainlang string, as hex or a path to a file produced by `parse`",
+        conflicts_with = "rainlang_string"
+    )]
+    pub bytecode: Option<String>,
 
     #[arg(short, long, help = "The source index")]
     pub source_index: u16,
 
-    #[arg(long, help = "The address of the Rainlang contract")]
-    pub rainlang: Address,
+    #[arg(
+        long,
+        help = "The address of the Rainlang contract",
+        required_unless_present = "deployer"
+    )]
+    pub rainlang: Option<Address>,
 
     #[arg(short, long, help = "The namespace")]
     pub namespace: String,
 
-    // Accept context as a vector of string key-value pairs
+    // Accept context as a vector of comma separated columns
     #[arg(
         short,
         long,
-        help = "The context in key=value format, key is the context column name and value is the context rows as a comma separated list"
+        help = "A context column as a comma separated list of integer or hex values, repeated once per column"
     )]
     pub context: Vec<String>,
 
+    #[arg(
+        long,
+        help = "Path to a JSON or YAML (.yaml/.yml) context file with an optional base column, typed columns and signed contexts",
+        conflicts_with = "context"
+    )]
+    pub context_file: Option<PathBuf>,
+
     #[arg(short, long, help = "Decode errors using the openchain.xyz database")]
     pub decode_errors: bool,
 
@@ -52,6 +84,27 @@ pub struct ForkEvalCliArgs {
         help = "The state overlay vector which applies to the state before evaluation to facilitate 'what if' analysis"
     )]
     pub state_overlay: Option<Vec<U256>>,
+
+    #[arg(long, help = "The caller of eval4, defaults to the zero address")]
+    pub from: Option<Address>,
+
+    #[arg(long, help = "Block timestamp override for this eval")]
+    pub block_timestamp: Option<u64>,
+
+    #[arg(long, help = "Block number override for this eval")]
+    pub block_number: Option<u64>,
+
+    #[arg(long, help = "Chain id override for this eval")]
+    pub chain_id: Option<u64>,
+
+    #[arg(long, help = "Block basefee override for this eval")]
+    pub basefee: Option<u64>,
+
+    #[arg(long, help = "Block and call gas limit override for this eval")]
+    pub gas_limit: Option<u64>,
+
+    #[command(flatten)]
+    pub dispair: DISPaiRCliArgs,
 }
 
 impl TryFrom<ForkEvalCliArgs> for ForkEvalArgs {
@@ -60,30 +113,120 @@ impl TryFrom<ForkEvalCliArgs> for ForkEvalArgs {
     fn try_from(args: ForkEvalCliArgs) -> Result<Self> {
         let namespace = parse_int_or_hex(&args.namespace).context("Invalid namespace format")?;
 
-        let context = args
-            .context
-            .into_iter()
-            .map(|ctx_str| {
-                ctx_str
-                    .split(',')
-                    .map(|v| parse_int_or_hex(v).context("Invalid context value"))
-                    .collect::<Result<Vec<U256>>>()
-            })
-            .collect::<Result<Vec<Vec<U256>>>>()?;
+        let context = match &args.context_file {
+            Some(path) => read_context_file(path)?,
+            None => args
+                .context
+                .into_iter()
+                .map(|ctx_str| {
+                    ctx_str
+                        .split(',')
+                        .map(|v| parse_int_or_hex(v).context("Invalid context value"))
+                        .collect::<Result<Vec<U256>>>()
+                })
+                .collect::<Result<Vec<Vec<U256>>>>()?,
+        };
+
+        let bytecode = args
+            .bytecode
+            .as_deref()
+            .map(read_bytecode)
+            .transpose()
+            .context("Invalid bytecode")?;
+
+        let dispair = args.dispair.into_eval_dispair()?;
 
         Ok(ForkEvalArgs {
-            rainlang_string: args.rainlang_string,
+            rainlang_string: args.rainlang_string.unwrap_or_default(),
             source_index: args.source_index,
-            rainlang: args.rainlang,
+            rainlang: args.rainlang.unwrap_or_default(),
             namespace: FullyQualifiedNamespace::from(namespace),
             context,
             decode_errors: args.decode_errors,
             inputs: args.inputs.unwrap_or_default(),
             state_overlay: args.state_overlay.unwrap_or_default(),
+            bytecode,
+            dispair,
+            from: args.from,
+            env: EnvOverrides {
+                timestamp: args.block_timestamp,
+                number: args.block_number,
+                chain_id: args.chain_id,
+                basefee: args.basefee,
+                gas_limit: args.gas_limit,
+            },
         })
     }
 }
 
+/// Reads serialized bytecode from a hex string or a file containing either
+/// raw bytes or hex. Accepts both the serialized expression and the ABI
+/// encoded `parse2` return that the `parse` command outputs.
+fn read_bytecode(value: &str) -> Result<Bytes> {
+    let data = if value.starts_with("0x") || value.starts_with("0X") {
+        hex::decode(value)?
+    } else {
+        let contents = std::fs::read(value)
+            .with_context(|| format!("Failed to read bytecode file {value}"))?;
+        match std::str::from_utf8(&contents) {
+            Ok(text) if text.trim().starts_with("0x") => hex::decode(text.trim())?,
+            _ => contents,
+        }
+    };
+
+    // Output of `parse` is the ABI encoded return of `parse2`.
+    if let Some(bytecode) = parse2Call::abi_decode_returns(&data)
+        .ok()
+        .filter(|bytecode| SerializedExpression::deserialize(bytecode).is_ok())
+    {
+        return Ok(bytecode);
+    }
+
+    SerializedExpression::deserialize(&data)?;
+    Ok(data.into())
+}
+
+/// Reads a context file in the format of [`ContextBuilder`] and builds the
+/// context matrix from it. Files ending in `.yaml` or `.yml` are read as
+/// YAML, any other as JSON.
+fn read_context_file(path: &Path) -> Result<Vec<Vec<U256>>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read context file {}", path.display()))?;
+    let is_yaml = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+    let builder: ContextBuilder = if is_yaml {
+        serde_yaml::from_str(&contents).map_err(anyhow::Error::from)
+    } else {
+        serde_json::from_str(&contents).map_err(anyhow::Error::from)
+    }
+    .with_context(|| format!("Invalid context file {}", path.display()))?;
+    builder.build().context("Invalid context value")
+}
+
+/// Parses a `--compare` target of the form `<fork_url>,<rainlang>[,<block>]`.
+fn parse_compare_target(value: &str) -> Result<CompareTarget> {
+    let parts: Vec<&str> = value.split(',').collect();
+    let (fork_url, rainlang, fork_block_number) = match parts.as_slice() {
+        [fork_url, rainlang] => (fork_url, rainlang, None),
+        [fork_url, rainlang, block] => (
+            fork_url,
+            rainlang,
+            Some(block.parse().context("Invalid block number")?),
+        ),
+        _ => return Err(anyhow!("Expected <fork_url>,<rainlang>[,<block>]")),
+    };
+    Ok(CompareTarget {
+        fork: NewForkedEvm {
+            fork_url: fork_url.to_string(),
+            fork_block_number,
+            rpc_cache: None,
+            rpc: Default::default(),
+        },
+        rainlang: rainlang.parse().context("Invalid Rainlang address")?,
+    })
+}
+
 // Helper function to parse a string as either integer or hex-encoded value
 fn parse_int_or_hex(value: &str) -> Result<U256> {
     if value.starts_with("0x") || value.starts_with("0X") {
@@ -105,29 +248,81 @@ pub struct Eval {
 
     #[command(flatten)]
     fork_eval_args: ForkEvalCliArgs,
+
+    /// Also evaluate on another chain, as `<fork_url>,<rainlang>[,<block>]`,
+    /// and print a side by side comparison keyed by chain. Repeat once per
+    /// chain. Fails if the chains disagree.
+    #[arg(long, value_parser = parse_compare_target, conflicts_with = "deployer")]
+    compare: Vec<CompareTarget>,
+}
+
+impl Eval {
+    async fn run_compare(&self) -> Result<()> {
+        let args: ForkEvalArgs = self.fork_eval_args.clone().try_into()?;
+        let fork: NewForkedEvm = self.forked_evm.clone().into();
+        // Every chain shares the cache and RPC options of the main fork,
+        // except for its fallback urls.
+        let rpc_cache = fork.rpc_cache.clone();
+        let rpc = RpcOptions {
+            fallback_urls: vec![],
+            ..fork.rpc.clone()
+        };
+        let mut targets = vec![CompareTarget {
+            fork,
+            rainlang: args.rainlang,
+        }];
+        targets.extend(self.compare.iter().cloned().map(|mut target| {
+            target.fork.rpc_cache = rpc_cache.clone();
+            target.fork.rpc = rpc.clone();
+            target
+        }));
+
+        let comparison = compare(targets, args).await;
+        crate::output::output(
+            &self.output_path,
+            SupportedOutputEncoding::Binary,
+            comparison.to_string().as_bytes(),
+        )?;
+
+        if !comparison.agrees() {
+            return Err(anyhow!("Chains disagree on the eval"));
+        }
+        Ok(())
+    }
 }
 
 impl Execute for Eval {
     async fn execute(&self) -> Result<()> {
+        if !self.compare.is_empty() {
+            return self.run_compare().await;
+        }
+
         let forker = Forker::new_with_fork(self.forked_evm.clone().into(), None, None).await?;
-        let result = forker
-            .fork_eval(self.fork_eval_args.clone().try_into()?)
-            .await;
-
-        match result {
-            Ok(res) => {
-                let rain_eval_result: RainEvalResult = res.try_into().map_err(
-                    |e: rain_interpreter_eval::trace::RainEvalResultFromRawCallResultError| {
-                        anyhow!(e)
-                    },
-                )?;
-                crate::output::output(
-                    &self.output_path,
-                    SupportedOutputEncoding::Binary,
-                    format!("{:#?}", rain_eval_result).as_bytes(),
-                )
-            }
-            Err(e) => Err(anyhow!(e)),
+        let rain_eval_result = forker
+            .fork_eval_result(self.fork_eval_args.clone().try_into()?)
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        crate::output::output(
+            &self.output_path,
+            SupportedOutputEncoding::Binary,
+            format!("{:#?}", rain_eval_result).as_bytes(),
+        )?;
+
+        if let RainEvalOutcome::EnsureFailed {
+            reason,
+            source_index,
+        } = rain_eval_result.outcome
+        {
+            return Err(anyhow!(ForkCallError::EnsureFailed {
+                reason,
+                source_index,
+            }));
+        }
+
+        match rain_eval_result.error {
+            Some(error) if rain_eval_result.reverted => Err(anyhow!("Eval reverted: {error}")),
+            _ => Ok(()),
         }
     }
 }
@@ -147,14 +342,23 @@ mod tests {
 
     fn simple_cli_args() -> ForkEvalCliArgs {
         ForkEvalCliArgs {
-            rainlang_string: "_: 1;".into(),
+            rainlang_string: Some("_: 1;".into()),
+            bytecode: None,
             source_index: 0,
-            rainlang: Address::ZERO,
+            rainlang: Some(Address::ZERO),
             namespace: "0x0".into(),
             context: vec![],
+            context_file: None,
             decode_errors: false,
             inputs: None,
             state_overlay: None,
+            from: None,
+            block_timestamp: None,
+            block_number: None,
+            chain_id: None,
+            basefee: None,
+            gas_limit: None,
+            dispair: DISPaiRCliArgs::default(),
         }
     }
 
@@ -200,6 +404,169 @@ mod tests {
         assert_eq!(eval_args.context[1], vec![U256::from(0xa), U256::from(0xb)]);
     }
 
+    #[test]
+    fn test_try_from_env_overrides() {
+        let mut args = simple_cli_args();
+        args.from = Some(Address::repeat_byte(0x1));
+        args.block_timestamp = Some(1000);
+        args.chain_id = Some(5);
+        let eval_args = ForkEvalArgs::try_from(args).unwrap();
+        assert_eq!(eval_args.from, Some(Address::repeat_byte(0x1)));
+        assert_eq!(
+            eval_args.env,
+            EnvOverrides {
+                timestamp: Some(1000),
+                chain_id: Some(5),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_from_context_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{
+                "base": {
+                    "sender": "0x0101010101010101010101010101010101010101",
+                    "callingContract": "0x0202020202020202020202020202020202020202"
+                },
+                "columns": [{ "name": "amounts", "values": [{ "word": "5" }, { "float": "2" }] }]
+            }"#,
+        )
+        .unwrap();
+
+        let mut args = simple_cli_args();
+        args.context_file = Some(file.path().to_path_buf());
+        let eval_args = ForkEvalArgs::try_from(args.clone()).unwrap();
+        assert_eq!(
+            eval_args.context,
+            vec![
+                vec![
+                    U256::from_be_slice(Address::repeat_byte(0x1).as_slice()),
+                    U256::from_be_slice(Address::repeat_byte(0x2).as_slice())
+                ],
+                vec![U256::from(5), U256::from(2)],
+            ]
+        );
+
+        std::fs::write(file.path(), r#"{ "columns": [{ "values": [{ "string": "this string is far too long to fit" }] }] }"#).unwrap();
+        let err = format!("{:#}", ForkEvalArgs::try_from(args.clone()).unwrap_err());
+        assert!(err.contains("Invalid context value"), "got: {err}");
+
+        args.context_file = Some("/nonexistent/context.json".into());
+        let err = ForkEvalArgs::try_from(args).unwrap_err().to_string();
+        assert!(err.contains("Failed to read context file"), "got: {err}");
+    }
+
+    #[test]
+    fn test_try_from_context_file_yaml() {
+        let yaml = r#"
+base:
+  sender: "0x0101010101010101010101010101010101010101"
+  callingContract: "0x0202020202020202020202020202020202020202"
+columns:
+  - name: amounts
+    values:
+      - word: "5"
+"#;
+        let mut args = simple_cli_args();
+        for suffix in [".yaml", ".YML"] {
+            let file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+            std::fs::write(file.path(), yaml).unwrap();
+            args.context_file = Some(file.path().to_path_buf());
+            let eval_args = ForkEvalArgs::try_from(args.clone()).unwrap();
+            assert_eq!(eval_args.context[1], vec![U256::from(5)]);
+            assert_eq!(
+                eval_args.context[0][0],
+                U256::from_be_slice(Address::repeat_byte(0x1).as_slice())
+            );
+        }
+
+        // Anything else is read as JSON.
+        let file = tempfile::Builder::new().suffix(".txt").tempfile().unwrap();
+        std::fs::write(file.path(), yaml).unwrap();
+        args.context_file = Some(file.path().to_path_buf());
+        let err = ForkEvalArgs::try_from(args).unwrap_err().to_string();
+        assert!(err.contains("Invalid context file"), "got: {err}");
+    }
+
+    const SERIALIZED: &str = "0x00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000b0100000101000101100000";
+
+    #[test]
+    fn test_read_bytecode_hex() {
+        let bytecode = read_bytecode(SERIALIZED).unwrap();
+        assert_eq!(bytecode.to_vec(), hex::decode(SERIALIZED).unwrap());
+    }
+
+    #[test]
+    fn test_read_bytecode_abi_encoded_file() {
+        let serialized = Bytes::from(hex::decode(SERIALIZED).unwrap());
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            hex::encode_prefixed(parse2Call::abi_encode_returns(&serialized)),
+        )
+        .unwrap();
+
+        let bytecode = read_bytecode(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(bytecode, serialized);
+    }
+
+    #[test]
+    fn test_read_bytecode_binary_file() {
+        let serialized = hex::decode(SERIALIZED).unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &serialized).unwrap();
+
+        let bytecode = read_bytecode(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(bytecode.to_vec(), serialized);
+    }
+
+    #[test]
+    fn test_read_bytecode_invalid() {
+        assert!(read_bytecode("0x1234").is_err());
+        assert!(read_bytecode("/nonexistent/bytecode").is_err());
+    }
+
+    #[test]
+    fn test_try_from_bytecode() {
+        let mut args = simple_cli_args();
+        args.rainlang_string = None;
+        args.bytecode = Some(SERIALIZED.into());
+        let eval_args = ForkEvalArgs::try_from(args).unwrap();
+        assert_eq!(
+            eval_args.bytecode.unwrap().to_vec(),
+            hex::decode(SERIALIZED).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_try_from_dispair() {
+        let mut args = simple_cli_args();
+        args.rainlang = None;
+        args.dispair = DISPaiRCliArgs {
+            deployer: Some(Address::repeat_byte(0x1)),
+            interpreter: Some(Address::repeat_byte(0x2)),
+            store: None,
+            parser: None,
+        };
+        let err = ForkEvalArgs::try_from(args.clone()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ForkCallError>(),
+            Some(ForkCallError::IncompleteDISPaiR("store"))
+        ));
+
+        args.dispair.store = Some(Address::repeat_byte(0x3));
+        let eval_args = ForkEvalArgs::try_from(args).unwrap();
+        let dispair = eval_args.dispair.unwrap();
+        assert_eq!(dispair.deployer, Address::repeat_byte(0x1));
+        assert_eq!(dispair.interpreter, Address::repeat_byte(0x2));
+        assert_eq!(dispair.store, Address::repeat_byte(0x3));
+        assert_eq!(eval_args.rainlang, Address::ZERO);
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_execute() {
         let local_evm = LocalEvm::new().await;
@@ -209,20 +576,115 @@ mod tests {
             forked_evm: NewForkedEvmCliArgs {
                 fork_url: local_evm.url(),
                 fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
             },
             fork_eval_args: ForkEvalCliArgs {
-                rainlang_string: r"_: 12, _: context<0 0>(), _:context<0 1>();".into(),
+                rainlang_string: Some(r"_: 12, _: context<0 0>(), _:context<0 1>();".into()),
+                bytecode: None,
                 source_index: 0,
-                rainlang: local_evm.rainlang,
+                rainlang: Some(local_evm.rainlang),
                 namespace: "0x123".into(),
                 context: vec!["0x06,99".into()],
+                context_file: None,
                 decode_errors: true,
                 inputs: None,
                 state_overlay: None,
+                from: None,
+                block_timestamp: None,
+                block_number: None,
+                chain_id: None,
+                basefee: None,
+                gas_limit: None,
+                dispair: DISPaiRCliArgs::default(),
             },
+            compare: vec![],
         };
 
         let result = eval.execute().await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_execute_ensure_failed() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let mut fork_eval_args = simple_cli_args();
+        fork_eval_args.rainlang = Some(local_evm.rainlang);
+        fork_eval_args.rainlang_string = Some(r#":ensure(0 "always fails");"#.into());
+        let eval = Eval {
+            output_path: Some(file.path().to_path_buf()),
+            forked_evm: NewForkedEvmCliArgs {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
+            },
+            fork_eval_args,
+            compare: vec![],
+        };
+
+        let err = eval.execute().await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ForkCallError>(),
+            Some(ForkCallError::EnsureFailed { reason, source_index: 0 }) if reason == "always fails"
+        ));
+        let written = std::fs::read_to_string(file.path()).unwrap();
+        assert!(written.contains("reverted: true"));
+    }
+
+    #[test]
+    fn test_parse_compare_target() {
+        let target = parse_compare_target(
+            "http://localhost:8545,0x0101010101010101010101010101010101010101,100",
+        )
+        .unwrap();
+        assert_eq!(target.fork.fork_url, "http://localhost:8545");
+        assert_eq!(target.fork.fork_block_number, Some(100));
+        assert_eq!(target.rainlang, Address::repeat_byte(0x1));
+
+        let target = parse_compare_target(
+            "http://localhost:8545,0x0101010101010101010101010101010101010101",
+        )
+        .unwrap();
+        assert_eq!(target.fork.fork_block_number, None);
+
+        assert!(parse_compare_target("http://localhost:8545").is_err());
+        assert!(parse_compare_target("http://localhost:8545,nope").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_execute_compare() {
+        let a = LocalEvm::new().await;
+        let b = LocalEvm::new_with_tokens(1).await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let mut fork_eval_args = simple_cli_args();
+        fork_eval_args.rainlang = Some(a.rainlang);
+        fork_eval_args.rainlang_string = Some("_ _: chain-id() 1;".into());
+        let mut eval = Eval {
+            output_path: Some(file.path().to_path_buf()),
+            forked_evm: NewForkedEvmCliArgs {
+                fork_url: a.url(),
+                fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
+            },
+            fork_eval_args,
+            compare: vec![parse_compare_target(&format!("{},{}", b.url(), b.rainlang)).unwrap()],
+        };
+        eval.execute().await.unwrap();
+        let written = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(written.lines().count(), 3, "got: {written}");
+
+        eval.fork_eval_args.rainlang_string = Some("_: block-number();".into());
+        let err = eval.execute().await.unwrap_err().to_string();
+        assert!(err.contains("Chains disagree"), "got: {err}");
+        let written = std::fs::read_to_string(file.path()).unwrap();
+        assert!(written.contains("column(s) differ"), "got: {written}");
+    }
 }
@@ -0,0 +1,111 @@
+use crate::execute::Execute;
+use crate::fork::NewForkedEvmCliArgs;
+use crate::output::SupportedOutputEncoding;
+use alloy::primitives::Address;
+use anyhow::{Context, Result, anyhow};
+use clap::Args;
+use rain_interpreter_eval::fork::Forker;
+use rain_interpreter_eval::fuzz::{FuzzReport, FuzzSpec};
+use std::path::PathBuf;
+
+/// CLI subcommand that fuzzes the inputs and context of an expression.
+#[derive(Args, Clone)]
+pub struct Fuzz {
+    /// JSON fuzz spec file.
+    file: PathBuf,
+
+    /// The address of the Rainlang contract. Overrides the address in the
+    /// spec file.
+    #[arg(long)]
+    rainlang: Option<Address>,
+
+    /// Seed of the random draws. Overrides the seed in the spec file.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of runs. Overrides the runs in the spec file.
+    #[arg(long)]
+    runs: Option<u32>,
+
+    /// Output path. If not specified, the output is written to stdout.
+    #[arg(short, long)]
+    output_path: Option<PathBuf>,
+
+    #[command(flatten)]
+    forked_evm: NewForkedEvmCliArgs,
+}
+
+impl Fuzz {
+    async fn run(&self) -> Result<FuzzReport> {
+        let contents = std::fs::read_to_string(&self.file)
+            .with_context(|| format!("Failed to read fuzz spec {}", self.file.display()))?;
+        let mut spec: FuzzSpec = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid fuzz spec {}", self.file.display()))?;
+        spec.seed = self.seed.or(spec.seed);
+        spec.runs = self.runs.unwrap_or(spec.runs);
+
+        let forker = Forker::new_with_fork(self.forked_evm.clone().into(), None, None).await?;
+        Ok(forker.fuzz(&spec, self.rainlang).await?)
+    }
+}
+
+impl Execute for Fuzz {
+    async fn execute(&self) -> Result<()> {
+        let report = self.run().await?;
+        crate::output::output(
+            &self.output_path,
+            SupportedOutputEncoding::Binary,
+            report.to_string().as_bytes(),
+        )?;
+        if !report.passed() {
+            return Err(anyhow!("Fuzzing found a failing case"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{
+                "expression": ":ensure(less-than(context<0 0>() 10) \"too big\");",
+                "context": [[{ "type": "int", "min": "0", "max": "100" }]],
+                "runs": 1
+            }"#,
+        )
+        .unwrap();
+
+        let mut fuzz = Fuzz {
+            file: file.path().to_path_buf(),
+            rainlang: Some(local_evm.rainlang),
+            seed: Some(1),
+            runs: Some(50),
+            output_path: None,
+            forked_evm: NewForkedEvmCliArgs {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
+            },
+        };
+
+        let report = fuzz.run().await.unwrap();
+        assert_eq!(report.seed, 1);
+        let failure = report.failure.unwrap();
+        assert_eq!(failure.context, vec![vec!["10".to_string()]]);
+        assert!(fuzz.execute().await.is_err());
+
+        fuzz.rainlang = None;
+        let err = fuzz.run().await.unwrap_err().to_string();
+        assert!(err.contains("No Rainlang address"), "got: {err}");
+    }
+}
@@ -1,5 +1,15 @@
 mod eval;
+mod fuzz;
 mod parse;
+mod scenario;
+mod serve;
+mod sign_context;
+mod test;
 
 pub use self::eval::Eval;
+pub use self::fuzz::Fuzz;
 pub use self::parse::Parse;
+pub use self::scenario::Scenario;
+pub use self::serve::Serve;
+pub use self::sign_context::SignContext;
+pub use self::test::Test;
@@ -1,3 +1,4 @@
+use crate::dispair::DISPaiRCliArgs;
 use crate::execute::Execute;
 use crate::fork::NewForkedEvmCliArgs;
 use crate::output::SupportedOutputEncoding;
@@ -5,6 +6,7 @@ use alloy::primitives::Address;
 use anyhow::Result;
 use anyhow::anyhow;
 use clap::Args;
+use rain_interpreter_eval::bytecode::{SerializedExpression, integrity_reports};
 use rain_interpreter_eval::eval::ForkParseArgs;
 use rain_interpreter_eval::fork::Forker;
 use std::path::PathBuf;
@@ -12,14 +14,21 @@ use std::path::PathBuf;
 /// CLI arguments for parsing a Rainlang expression.
 #[derive(Args, Clone, Debug)]
 pub struct ForkParseArgsCli {
-    #[arg(long, help = "The address of the Rainlang contract")]
-    rainlang: Address,
+    #[arg(
+        long,
+        help = "The address of the Rainlang contract",
+        required_unless_present = "deployer"
+    )]
+    rainlang: Option<Address>,
 
     #[arg(short, long, help = "The Rainlang string to parse")]
     rainlang_string: String,
 
     #[arg(short, long, help = "Decode errors using the openchain.xyz database")]
     decode_errors: bool,
+
+    #[command(flatten)]
+    dispair: DISPaiRCliArgs,
 }
 
 /// CLI subcommand that parses a Rainlang expression into bytecode.
@@ -31,6 +40,21 @@ pub struct Parse {
     /// Output encoding. If not specified, the output is written in binary format.
     #[arg(short = 'E', long, default_value = "binary")]
     output_encoding: SupportedOutputEncoding,
+    /// Parse with the parser directly, skipping the deployer's integrity
+    /// check. Outputs the serialized constants and bytecode, as accepted by
+    /// `eval --bytecode`.
+    #[arg(long = "unsafe")]
+    unsafe_parse: bool,
+    /// With `--unsafe`, also run the deployer's integrity check once the
+    /// output is written and fail with its error if it rejects the
+    /// expression.
+    #[arg(long, requires = "unsafe_parse")]
+    check_integrity: bool,
+    /// Output the inputs, outputs and max stack height of each source
+    /// instead of the bytecode. Not available with `--unsafe`, as the
+    /// headers are only the integrity check's conclusions once it has run.
+    #[arg(long, conflicts_with = "unsafe_parse")]
+    report: bool,
 
     #[command(flatten)]
     forked_evm: NewForkedEvmCliArgs,
@@ -42,9 +66,10 @@ pub struct Parse {
 impl From<ForkParseArgsCli> for ForkParseArgs {
     fn from(args: ForkParseArgsCli) -> Self {
         ForkParseArgs {
-            rainlang: args.rainlang,
+            rainlang: args.rainlang.unwrap_or_default(),
             rainlang_string: args.rainlang_string,
             decode_errors: args.decode_errors,
+            dispair: args.dispair.into_dispair(),
         }
     }
 }
@@ -52,16 +77,51 @@ impl From<ForkParseArgsCli> for ForkParseArgs {
 impl Execute for Parse {
     async fn execute(&self) -> Result<()> {
         let forker = Forker::new_with_fork(self.forked_evm.clone().into(), None, None).await?;
-        let result = forker.fork_parse(self.fork_parse_args.clone().into()).await;
+        let args: ForkParseArgs = self.fork_parse_args.clone().into();
 
-        match result {
-            Ok(res) => crate::output::output(
+        if self.unsafe_parse {
+            let res = forker
+                .fork_unsafe_parse(args.clone())
+                .await
+                .map_err(|e| anyhow!(e))?;
+            let expression = SerializedExpression {
+                constants: res.typed_return._1,
+                bytecode: res.typed_return._0,
+            };
+            crate::output::output(
                 &self.output_path,
                 self.output_encoding.clone(),
-                res.raw.result.to_owned().to_vec().as_slice(),
-            ),
-            Err(e) => Err(anyhow!(e)),
+                &expression.serialize(),
+            )?;
+            if self.check_integrity {
+                forker
+                    .fork_parse(args)
+                    .await
+                    .map_err(|e| anyhow!(e).context("Integrity check failed"))?;
+            }
+            return Ok(());
+        }
+
+        let res = forker.fork_parse(args).await.map_err(|e| anyhow!(e))?;
+
+        if self.report {
+            let expression = SerializedExpression::deserialize(&res.typed_return)?;
+            let report = integrity_reports(&expression.bytecode)?
+                .iter()
+                .map(|source_report| format!("{source_report}\n"))
+                .collect::<String>();
+            return crate::output::output(
+                &self.output_path,
+                SupportedOutputEncoding::Binary,
+                report.as_bytes(),
+            );
         }
+
+        crate::output::output(
+            &self.output_path,
+            self.output_encoding.clone(),
+            res.raw.result.to_vec().as_slice(),
+        )
     }
 }
 
@@ -78,18 +138,122 @@ mod tests {
         let parse = Parse {
             output_path: None,
             output_encoding: SupportedOutputEncoding::Binary,
+            unsafe_parse: false,
+            check_integrity: false,
+            report: false,
             forked_evm: NewForkedEvmCliArgs {
                 fork_url: local_evm.url(),
                 fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
             },
             fork_parse_args: ForkParseArgsCli {
-                rainlang: local_evm.rainlang,
+                rainlang: Some(local_evm.rainlang),
                 rainlang_string: "_: 1;".into(),
                 decode_errors: false,
+                dispair: DISPaiRCliArgs::default(),
             },
         };
 
         let result = parse.execute().await;
         assert!(result.is_ok());
     }
+
+    fn unsafe_parse(local_evm: &LocalEvm, output_path: PathBuf, check_integrity: bool) -> Parse {
+        Parse {
+            output_path: Some(output_path),
+            output_encoding: SupportedOutputEncoding::Binary,
+            unsafe_parse: true,
+            check_integrity,
+            report: false,
+            forked_evm: NewForkedEvmCliArgs {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
+            },
+            fork_parse_args: ForkParseArgsCli {
+                rainlang: Some(local_evm.rainlang),
+                rainlang_string: "_: add(1 2);".into(),
+                decode_errors: false,
+                dispair: DISPaiRCliArgs::default(),
+            },
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_execute_unsafe() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let mut parse = unsafe_parse(&local_evm, file.path().to_path_buf(), false);
+        parse.execute().await.unwrap();
+        let expression =
+            SerializedExpression::deserialize(&std::fs::read(file.path()).unwrap()).unwrap();
+        assert_eq!(expression.constants.len(), 2);
+        assert!(!expression.bytecode.is_empty());
+
+        // The integrity check passes, so checking it changes nothing.
+        parse.check_integrity = true;
+        parse.execute().await.unwrap();
+        assert_eq!(
+            SerializedExpression::deserialize(&std::fs::read(file.path()).unwrap()).unwrap(),
+            expression
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_execute_unsafe_integrity_error() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let mut parse = unsafe_parse(&local_evm, file.path().to_path_buf(), false);
+        parse.fork_parse_args.rainlang_string = "_: add(1);".into();
+        parse.execute().await.unwrap();
+        let written = std::fs::read(file.path()).unwrap();
+        assert!(SerializedExpression::deserialize(&written).is_ok());
+
+        // The output is still written before the integrity error is returned.
+        std::fs::write(file.path(), []).unwrap();
+        parse.check_integrity = true;
+        let err = parse.execute().await.unwrap_err();
+        assert!(err.to_string().contains("Integrity check failed"));
+        assert_eq!(std::fs::read(file.path()).unwrap(), written);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_execute_report() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let parse = Parse {
+            output_path: Some(file.path().to_path_buf()),
+            output_encoding: SupportedOutputEncoding::Binary,
+            unsafe_parse: false,
+            check_integrity: false,
+            report: true,
+            forked_evm: NewForkedEvmCliArgs {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
+            },
+            fork_parse_args: ForkParseArgsCli {
+                rainlang: Some(local_evm.rainlang),
+                rainlang_string: "_: 1;".into(),
+                decode_errors: false,
+                dispair: DISPaiRCliArgs::default(),
+            },
+        };
+
+        parse.execute().await.unwrap();
+        let written = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            written,
+            "source 0: inputs 0, outputs 1, max stack height 1, ops 1\n"
+        );
+    }
 }
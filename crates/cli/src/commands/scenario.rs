@@ -0,0 +1,153 @@
+use crate::execute::Execute;
+use crate::output::SupportedOutputEncoding;
+use alloy::primitives::{Address, BlockNumber};
+use anyhow::{Context, Result, anyhow};
+use clap::Args;
+use rain_interpreter_eval::fork::{Forker, NewForkedEvm};
+use rain_interpreter_eval::scenario;
+use std::path::PathBuf;
+
+/// CLI subcommand that runs scenario files, each against a fresh EVM.
+#[derive(Args, Clone)]
+pub struct Scenario {
+    /// JSON scenario files to run.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// The address of the Rainlang contract. Overrides the address in the
+    /// scenario files.
+    #[arg(long)]
+    rainlang: Option<Address>,
+
+    /// Output path. If not specified, the output is written to stdout.
+    #[arg(short, long)]
+    output_path: Option<PathBuf>,
+
+    /// RPC url to fork from. Without it scenarios run on an empty in-memory
+    /// EVM.
+    #[arg(short = 'i', long)]
+    fork_url: Option<String>,
+
+    /// Optional block number to fork from.
+    #[arg(short = 'b', long, requires = "fork_url")]
+    fork_block_number: Option<BlockNumber>,
+}
+
+impl Scenario {
+    async fn forker(&self) -> Result<Forker> {
+        let Some(fork_url) = &self.fork_url else {
+            return Forker::new().map_err(|e| anyhow!(e));
+        };
+        let args = NewForkedEvm {
+            fork_url: fork_url.clone(),
+            fork_block_number: self.fork_block_number,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        Ok(Forker::new_with_fork(args, None, None).await?)
+    }
+
+    /// Runs every file and returns the report text and the number of failed
+    /// scenarios.
+    async fn run(&self) -> Result<(String, usize)> {
+        let mut output = String::new();
+        let mut passed = 0;
+        let mut failed = 0;
+        for file in &self.files {
+            let contents = std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read scenario file {}", file.display()))?;
+            let scenario: scenario::Scenario = serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid scenario file {}", file.display()))?;
+
+            let mut forker = self.forker().await?;
+            let report = forker.run_scenario(&scenario, self.rainlang).await;
+            if report.passed() {
+                passed += 1;
+            } else {
+                failed += 1;
+            }
+            output.push_str(&format!("{}\n{report}", file.display()));
+        }
+        output.push_str(&format!("\n{passed} passed, {failed} failed\n"));
+
+        Ok((output, failed))
+    }
+}
+
+impl Execute for Scenario {
+    async fn execute(&self) -> Result<()> {
+        let (output, failed) = self.run().await?;
+        crate::output::output(
+            &self.output_path,
+            SupportedOutputEncoding::Binary,
+            output.as_bytes(),
+        )?;
+        if failed > 0 {
+            return Err(anyhow!("{failed} scenario(s) failed"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_in_memory() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{ "steps": [
+                { "action": "advanceBlocks", "blocks": 2 },
+                { "action": "advanceTime", "seconds": 12 }
+            ] }"#,
+        )
+        .unwrap();
+
+        let cmd = Scenario {
+            files: vec![file.path().to_path_buf()],
+            rainlang: None,
+            output_path: None,
+            fork_url: None,
+            fork_block_number: None,
+        };
+
+        let (output, failed) = cmd.run().await.unwrap();
+        assert_eq!(failed, 0);
+        assert!(output.contains("PASS step 0: advance 2 blocks\n"));
+        assert!(output.contains("PASS step 1: advance time 12s\n"));
+        assert!(output.ends_with("1 passed, 0 failed\n"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_fork() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{ "steps": [
+                { "action": "eval", "name": "set", "expression": ":set(1 7);", "commit": true, "expect": {} },
+                { "action": "assertStore", "key": "1", "expected": "8" }
+            ] }"#,
+        )
+        .unwrap();
+
+        let cmd = Scenario {
+            files: vec![file.path().to_path_buf()],
+            rainlang: Some(local_evm.rainlang),
+            output_path: None,
+            fork_url: Some(local_evm.url()),
+            fork_block_number: None,
+        };
+
+        let (output, failed) = cmd.run().await.unwrap();
+        assert_eq!(failed, 1);
+        assert!(output.contains("PASS step 0: eval set and commit\n"));
+        assert!(
+            output.contains("FAIL step 1: assert store key 1\n  store key 1: expected 8, got 7\n")
+        );
+        assert!(cmd.execute().await.is_err());
+    }
+}
@@ -0,0 +1,530 @@
+use crate::execute::Execute;
+use crate::fork::NewForkedEvmCliArgs;
+use alloy::hex;
+use alloy::primitives::{Address, B256, U256};
+use anyhow::Result;
+use clap::Args;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode, header};
+use hyper_util::rt::TokioIo;
+use rain_interpreter_bindings::IInterpreterStoreV3::getCall;
+use rain_interpreter_dispair::DISPaiR;
+use rain_interpreter_eval::error::ForkCallError;
+use rain_interpreter_eval::eval::{ForkEvalArgs, ForkParseArgs};
+use rain_interpreter_eval::fork::Forker;
+use rain_interpreter_eval::pool::ForkerPool;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// CLI subcommand that keeps a fork warm and serves parse and eval requests
+/// over HTTP.
+///
+/// Every endpoint takes a JSON `POST` body:
+///
+/// - `/parse`: the fields of `ForkParseArgs`, returns `{ "bytecode" }`.
+/// - `/eval`: the fields of `ForkEvalArgs`, returns a `RainEvalResult`.
+/// - `/eval/batch`: `{ "evals": [...] }` of `/eval` bodies, returns
+///   `{ "results": [...] }` in the same order, each a `RainEvalResult` or an
+///   `{ "error" }`. `--timeout` limits each eval on its own, so one that
+///   times out is an `{ "error" }` of the batch.
+/// - `/trace`: an `/eval` body with `"paths"`, returns `{ "values" }` found
+///   by trace path, e.g. `0.1`, in the order of the paths.
+/// - `/store`: `{ "namespace", "key" }` and optionally a Rainlang contract or
+///   DISPaiR, returns the `{ "value" }` the store holds.
+///
+/// Errors are returned as `{ "error" }`, with status 400 for bad requests,
+/// 413 for bodies over 1 MiB or batches over 1000 evals, 502 for RPC
+/// failures, 504 for timeouts and 422 for everything else.
+///
+/// Runs until interrupted, then saves the RPC caches.
+#[derive(Args, Clone)]
+pub struct Serve {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// The Rainlang contract used by requests that name neither a Rainlang
+    /// contract nor a DISPaiR.
+    #[arg(long)]
+    rainlang: Option<Address>,
+
+    /// Evals run at once by batch requests. Defaults to the number of CPUs.
+    #[arg(long)]
+    workers: Option<NonZeroUsize>,
+
+    /// Milliseconds each request may run the EVM before it fails. Each eval
+    /// of a batch is limited on its own.
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    #[command(flatten)]
+    forked_evm: NewForkedEvmCliArgs,
+}
+
+impl Execute for Serve {
+    async fn execute(&self) -> Result<()> {
+        let mut forker = Forker::new_with_fork(self.forked_evm.clone().into(), None, None).await?;
+        forker.set_call_timeout(self.timeout.map(Duration::from_millis));
+        let pool = match self.workers {
+            Some(workers) => ForkerPool::new(forker.clone(), workers),
+            None => ForkerPool::with_available_parallelism(forker.clone()),
+        };
+        let state = Arc::new(ServeState {
+            forker,
+            pool,
+            rainlang: self.rainlang,
+        });
+
+        let listener = TcpListener::bind(self.listen).await?;
+        tracing::info!("Listening on http://{}", listener.local_addr()?);
+        serve(listener, state, tokio::signal::ctrl_c()).await
+    }
+}
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// Most evals accepted in a single `/eval/batch` request.
+const MAX_BATCH_LEN: usize = 1000;
+
+/// The warm fork shared by all requests.
+struct ServeState {
+    forker: Forker,
+    pool: ForkerPool,
+    rainlang: Option<Address>,
+}
+
+/// Serves requests on `listener` until it fails or `shutdown` resolves, then
+/// saves the state the fork fetched to its RPC cache.
+async fn serve(
+    listener: TcpListener,
+    state: Arc<ServeState>,
+    shutdown: impl Future<Output = std::io::Result<()>>,
+) -> Result<()> {
+    let mut shutdown = std::pin::pin!(shutdown);
+    loop {
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            signal = &mut shutdown => {
+                signal?;
+                break;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(state.clone(), request));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!("Connection closed: {e}");
+            }
+        });
+    }
+
+    tracing::info!("Shutting down");
+    state.forker.save_rpc_caches()?;
+    Ok(())
+}
+
+async fn handle(
+    state: Arc<ServeState>,
+    request: Request<Incoming>,
+) -> Result<Response<Full<hyper::body::Bytes>>, Infallible> {
+    let response = route(&state, request).await;
+    let (status, body) = match response {
+        Ok(body) => (StatusCode::OK, body),
+        Err(e) => (e.status, json!({ "error": e.message })),
+    };
+    let response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(body.to_string().into()))
+        .expect("status and header are valid");
+    Ok(response)
+}
+
+async fn route(
+    state: &ServeState,
+    request: Request<Incoming>,
+) -> Result<serde_json::Value, ApiError> {
+    let path = request.uri().path().to_owned();
+    if !matches!(
+        path.as_str(),
+        "/parse" | "/eval" | "/eval/batch" | "/trace" | "/store"
+    ) {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("No endpoint {path}"),
+        ));
+    }
+    if request.method() != Method::POST {
+        return Err(ApiError::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{path} only accepts POST"),
+        ));
+    }
+    let body = Limited::new(request.into_body(), MAX_BODY_SIZE)
+        .collect()
+        .await
+        .map_err(|e| {
+            let status = if e.is::<LengthLimitError>() {
+                StatusCode::PAYLOAD_TOO_LARGE
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            ApiError::new(status, e.to_string())
+        })?
+        .to_bytes();
+
+    match path.as_str() {
+        "/parse" => state.parse(serde_json::from_slice(&body)?).await,
+        "/eval" => state.eval(serde_json::from_slice(&body)?).await,
+        "/eval/batch" => state.eval_batch(serde_json::from_slice(&body)?).await,
+        "/trace" => state.trace(serde_json::from_slice(&body)?).await,
+        _ => state.store(serde_json::from_slice(&body)?).await,
+    }
+}
+
+impl ServeState {
+    async fn parse(&self, mut args: ForkParseArgs) -> Result<serde_json::Value, ApiError> {
+        args.rainlang = self.rainlang(args.rainlang, &args.dispair)?;
+        let parsed = self.forker.fork_parse(args).await?;
+        Ok(json!({ "bytecode": parsed.typed_return }))
+    }
+
+    async fn eval(&self, args: ForkEvalArgs) -> Result<serde_json::Value, ApiError> {
+        let result = self.forker.fork_eval_result(self.eval_args(args)?).await?;
+        Ok(serde_json::to_value(result)?)
+    }
+
+    async fn eval_batch(&self, request: BatchRequest) -> Result<serde_json::Value, ApiError> {
+        if request.evals.len() > MAX_BATCH_LEN {
+            return Err(ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Batch of {} evals is over the limit of {MAX_BATCH_LEN}",
+                    request.evals.len()
+                ),
+            ));
+        }
+        let args = request
+            .evals
+            .into_iter()
+            .map(|args| self.eval_args(args))
+            .collect::<Result<Vec<_>, _>>()?;
+        let results: Vec<serde_json::Value> = self
+            .pool
+            .fork_eval_result(args)
+            .await
+            .into_iter()
+            .map(|result| match result {
+                Ok(result) => serde_json::to_value(result),
+                Err(e) => Ok(json!({ "error": ApiError::from(e).message })),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(json!({ "results": results }))
+    }
+
+    async fn trace(&self, request: TraceRequest) -> Result<serde_json::Value, ApiError> {
+        let result = self
+            .forker
+            .fork_eval_result(self.eval_args(request.eval)?)
+            .await?;
+        let values = request
+            .paths
+            .iter()
+            .map(|path| result.search_trace_by_path(path))
+            .collect::<Result<Vec<U256>, _>>()
+            .map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+        Ok(json!({ "values": values }))
+    }
+
+    async fn store(&self, request: StoreRequest) -> Result<serde_json::Value, ApiError> {
+        let store = match request.dispair {
+            Some(dispair) => dispair.store,
+            None => {
+                let rainlang = self.rainlang(request.rainlang, &None)?;
+                self.forker.resolve_dispair(rainlang, false).await?.store
+            }
+        };
+        let get = getCall {
+            namespace: request.namespace.into(),
+            key: request.key,
+        };
+        let value = self
+            .forker
+            .alloy_call(Address::ZERO, store, get, false)
+            .await?
+            .typed_return;
+        Ok(json!({ "value": value }))
+    }
+
+    fn eval_args(&self, mut args: ForkEvalArgs) -> Result<ForkEvalArgs, ApiError> {
+        args.rainlang = self.rainlang(args.rainlang, &args.dispair)?;
+        Ok(args)
+    }
+
+    /// The Rainlang contract of a request, falling back to `--rainlang` when
+    /// it is left out. Not needed when the request names a DISPaiR.
+    fn rainlang(&self, rainlang: Address, dispair: &Option<DISPaiR>) -> Result<Address, ApiError> {
+        if rainlang != Address::ZERO {
+            return Ok(rainlang);
+        }
+        match (self.rainlang, dispair) {
+            (Some(rainlang), _) => Ok(rainlang),
+            (None, Some(_)) => Ok(Address::ZERO),
+            (None, None) => Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "No Rainlang address, set it in the request or with --rainlang".to_owned(),
+            )),
+        }
+    }
+}
+
+/// An error response.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: String) -> Self {
+        Self { status, message }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, e.to_string())
+    }
+}
+
+impl From<ForkCallError> for ApiError {
+    fn from(e: ForkCallError) -> Self {
+        let status = match &e {
+            ForkCallError::Rpc(_) => StatusCode::BAD_GATEWAY,
+            ForkCallError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ForkCallError::RainlangAndBytecode
+            | ForkCallError::NoRainlangOrBytecode
+            | ForkCallError::IncompleteDISPaiR(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        let message = match e {
+            // The full call result with its traces is too much for a
+            // response.
+            ForkCallError::Failed(raw) => format!(
+                "Call failed: {:?} 0x{}",
+                raw.exit_reason,
+                hex::encode(&raw.result)
+            ),
+            e => e.to_string(),
+        };
+        Self::new(status, message)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchRequest {
+    evals: Vec<ForkEvalArgs>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceRequest {
+    #[serde(flatten)]
+    eval: ForkEvalArgs,
+    paths: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoreRequest {
+    namespace: U256,
+    key: B256,
+    #[serde(default)]
+    rainlang: Address,
+    #[serde(default)]
+    dispair: Option<DISPaiR>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rain_interpreter_test_fixtures::LocalEvm;
+    use serde_json::Value;
+
+    /// Serves a fork of `local_evm` on a free port and returns its URL.
+    async fn start(local_evm: &LocalEvm) -> String {
+        start_until(local_evm, std::future::pending()).await.0
+    }
+
+    /// Like [`start`], serving until `shutdown` resolves.
+    async fn start_until(
+        local_evm: &LocalEvm,
+        shutdown: impl Future<Output = std::io::Result<()>> + Send + 'static,
+    ) -> (String, tokio::task::JoinHandle<Result<()>>) {
+        let forked_evm = NewForkedEvmCliArgs {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            cache_dir: None,
+            offline: false,
+            rpc: Default::default(),
+        };
+        let forker = Forker::new_with_fork(forked_evm.into(), None, None)
+            .await
+            .unwrap();
+        let state = Arc::new(ServeState {
+            pool: ForkerPool::new(forker.clone(), NonZeroUsize::new(2).unwrap()),
+            forker,
+            rainlang: Some(local_evm.rainlang),
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        (url, tokio::spawn(serve(listener, state, shutdown)))
+    }
+
+    async fn post(url: &str, body: Value) -> (StatusCode, Value) {
+        let response = reqwest::Client::new()
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+        (status, response.json().await.unwrap())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_serve() {
+        let local_evm = LocalEvm::new().await;
+        let url = start(&local_evm).await;
+
+        let (status, parsed) = post(
+            &format!("{url}/parse"),
+            json!({ "rainlangString": "_: 1;" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(parsed["bytecode"].as_str().unwrap().starts_with("0x"));
+
+        let (status, result) = post(
+            &format!("{url}/eval"),
+            json!({ "rainlangString": "_ _: 1 2;" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "got: {result}");
+        assert_eq!(result["reverted"], false);
+        assert_eq!(result["outcome"]["type"], "success");
+        let stack = result["traces"][0]["stack"].as_array().unwrap().clone();
+        assert_eq!(stack.len(), 2);
+
+        let (status, traced) = post(
+            &format!("{url}/trace"),
+            json!({ "rainlangString": "_ _: 1 2;", "paths": ["0.0", "0.1"] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "got: {traced}");
+        assert_eq!(traced["values"], json!([stack[1], stack[0]]));
+
+        let (status, batch) = post(
+            &format!("{url}/eval/batch"),
+            json!({ "evals": [
+                { "rainlangString": "_ _: 1 2;" },
+                { "rainlangString": ":ensure(0 \"nope\");" },
+                { "rainlangString": "_: nope();" },
+            ] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "got: {batch}");
+        let results = batch["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["traces"][0]["stack"], json!(stack));
+        assert_eq!(results[1]["outcome"]["type"], "ensureFailed");
+        assert_eq!(results[1]["outcome"]["reason"], "nope");
+        assert!(results[2]["error"].is_string());
+
+        let (status, stored) = post(
+            &format!("{url}/store"),
+            json!({ "namespace": "0x1", "key": B256::ZERO }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "got: {stored}");
+        assert_eq!(stored["value"], json!(B256::ZERO));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_serve_errors() {
+        let local_evm = LocalEvm::new().await;
+        let url = start(&local_evm).await;
+
+        let (status, body) = post(&format!("{url}/eval"), json!({ "sourceIndex": "x" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+
+        let (status, _) = post(
+            &format!("{url}/eval"),
+            json!({ "rainlangString": "_: nope();" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, body) = post(
+            &format!("{url}/trace"),
+            json!({ "rainlangString": "_: 1;", "paths": ["0.5"] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["error"].as_str().unwrap().contains("out of bounds"));
+
+        let (status, _) = post(&format!("{url}/nope"), json!({})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let response = reqwest::get(format!("{url}/eval")).await.unwrap();
+        assert_eq!(response.status().as_u16(), 405);
+
+        let (status, body) = post(
+            &format!("{url}/eval"),
+            json!({ "rainlangString": "_".repeat(MAX_BODY_SIZE) }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body["error"].is_string());
+
+        let evals = vec![json!({ "rainlangString": "_: 1;" }); MAX_BATCH_LEN + 1];
+        let (status, body) = post(&format!("{url}/eval/batch"), json!({ "evals": evals })).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body["error"].as_str().unwrap().contains("over the limit"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_serve_shutdown() {
+        let local_evm = LocalEvm::new().await;
+        let (shutdown, shutdown_rx) = tokio::sync::oneshot::channel();
+        let (url, served) = start_until(&local_evm, async move {
+            shutdown_rx.await.ok();
+            Ok(())
+        })
+        .await;
+
+        let (status, _) = post(&format!("{url}/eval"), json!({ "rainlangString": "_: 1;" })).await;
+        assert_eq!(status, StatusCode::OK);
+
+        shutdown.send(()).unwrap();
+        served.await.unwrap().unwrap();
+        assert!(
+            TcpListener::bind(url.trim_start_matches("http://"))
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::execute::Execute;
+use crate::output::SupportedOutputEncoding;
+use alloy::primitives::U256;
+use alloy::signers::local::PrivateKeySigner;
+use anyhow::{Context, Result};
+use clap::Args;
+use rain_interpreter_eval::context::SignedContext;
+use std::path::PathBuf;
+
+/// CLI subcommand that signs context rows with a local private key. The
+/// output is a signed context for the `signedContexts` of a context file.
+#[derive(Args, Clone)]
+pub struct SignContext {
+    /// Path to a file containing the hex private key to sign with.
+    #[arg(short, long)]
+    key_file: PathBuf,
+
+    /// The context rows as comma separated integer or hex values.
+    #[arg(required = true, value_delimiter = ',')]
+    rows: Vec<U256>,
+
+    /// Output path. If not specified, the output is written to stdout.
+    #[arg(short, long)]
+    output_path: Option<PathBuf>,
+}
+
+impl SignContext {
+    fn sign(&self) -> Result<SignedContext> {
+        let key = std::fs::read_to_string(&self.key_file)
+            .with_context(|| format!("Failed to read key file {}", self.key_file.display()))?;
+        let signer: PrivateKeySigner = key.trim().parse().context("Invalid private key")?;
+        Ok(SignedContext::sign(&signer, self.rows.clone())?)
+    }
+}
+
+impl Execute for SignContext {
+    async fn execute(&self) -> Result<()> {
+        let signed_context = self.sign()?;
+        crate::output::output(
+            &self.output_path,
+            SupportedOutputEncoding::Binary,
+            serde_json::to_string_pretty(&signed_context)?.as_bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+
+    #[tokio::test]
+    async fn test_execute() {
+        let key_file = tempfile::NamedTempFile::new().unwrap();
+        let signer = PrivateKeySigner::from_bytes(&B256::repeat_byte(0x11)).unwrap();
+        std::fs::write(
+            key_file.path(),
+            format!("{}\n", alloy::hex::encode_prefixed(signer.to_bytes())),
+        )
+        .unwrap();
+        let output = tempfile::NamedTempFile::new().unwrap();
+
+        let sign_context = SignContext {
+            key_file: key_file.path().to_path_buf(),
+            rows: vec![U256::from(1), U256::from(0xff)],
+            output_path: Some(output.path().to_path_buf()),
+        };
+        sign_context.execute().await.unwrap();
+
+        let written = std::fs::read_to_string(output.path()).unwrap();
+        let signed_context: SignedContext = serde_json::from_str(&written).unwrap();
+        assert_eq!(signed_context.signer, signer.address());
+        assert_eq!(
+            signed_context.context,
+            vec![U256::from(1), U256::from(0xff)]
+        );
+        assert!(signed_context.verify());
+    }
+
+    #[test]
+    fn test_sign_invalid_key() {
+        let key_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(key_file.path(), "not a key").unwrap();
+
+        let sign_context = SignContext {
+            key_file: key_file.path().to_path_buf(),
+            rows: vec![U256::from(1)],
+            output_path: None,
+        };
+        let err = sign_context.sign().unwrap_err().to_string();
+        assert!(err.contains("Invalid private key"), "got: {err}");
+    }
+}
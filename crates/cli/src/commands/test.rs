@@ -0,0 +1,152 @@
+use crate::execute::Execute;
+use crate::fork::NewForkedEvmCliArgs;
+use crate::output::SupportedOutputEncoding;
+use alloy::primitives::Address;
+use anyhow::{Context, Result, anyhow};
+use clap::Args;
+use rain_interpreter_eval::fork::Forker;
+use rain_interpreter_eval::suite::TestSuite;
+use std::path::PathBuf;
+
+/// CLI subcommand that runs declarative Rainlang test files.
+#[derive(Args, Clone)]
+pub struct Test {
+    /// JSON test files to run.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// The address of the Rainlang contract. Overrides the address in the
+    /// test files.
+    #[arg(long)]
+    rainlang: Option<Address>,
+
+    /// Output path. If not specified, the output is written to stdout.
+    #[arg(short, long)]
+    output_path: Option<PathBuf>,
+
+    #[command(flatten)]
+    forked_evm: NewForkedEvmCliArgs,
+}
+
+impl Test {
+    /// Runs every file and returns the report text and the number of failed
+    /// cases.
+    async fn run(&self) -> Result<(String, usize)> {
+        let forker = Forker::new_with_fork(self.forked_evm.clone().into(), None, None).await?;
+
+        let mut output = String::new();
+        let mut passed = 0;
+        let mut failed = 0;
+        for file in &self.files {
+            let contents = std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read test file {}", file.display()))?;
+            let suite: TestSuite = serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid test file {}", file.display()))?;
+            if self.rainlang.is_none() && suite.rainlang.is_none() {
+                return Err(anyhow!(
+                    "No Rainlang address for {}, set it in the file or with --rainlang",
+                    file.display()
+                ));
+            }
+
+            output.push_str(&format!("{}\n", file.display()));
+            for report in forker.run_test_suite(&suite, self.rainlang).await {
+                if report.passed() {
+                    passed += 1;
+                } else {
+                    failed += 1;
+                }
+                output.push_str(&report.to_string());
+            }
+        }
+        output.push_str(&format!("\n{passed} passed, {failed} failed\n"));
+
+        Ok((output, failed))
+    }
+}
+
+impl Execute for Test {
+    async fn execute(&self) -> Result<()> {
+        let (output, failed) = self.run().await?;
+        crate::output::output(
+            &self.output_path,
+            SupportedOutputEncoding::Binary,
+            output.as_bytes(),
+        )?;
+        if failed > 0 {
+            return Err(anyhow!("{failed} test case(s) failed"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    const SUITE: &str = r#"{
+        "cases": [
+            {
+                "name": "constant",
+                "expression": "_: 3;",
+                "expect": { "stack": ["3"] }
+            },
+            {
+                "name": "wrong",
+                "expression": "_: 3;",
+                "expect": { "stack": ["4"] }
+            }
+        ]
+    }"#;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), SUITE).unwrap();
+
+        let test = Test {
+            files: vec![file.path().to_path_buf()],
+            rainlang: Some(local_evm.rainlang),
+            output_path: None,
+            forked_evm: NewForkedEvmCliArgs {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
+            },
+        };
+
+        let (output, failed) = test.run().await.unwrap();
+        assert_eq!(failed, 1);
+        assert!(output.contains("PASS constant\n"));
+        assert!(output.contains("FAIL wrong\n  stack mismatch:\n    - [0] 4\n    + [0] 3\n"));
+        assert!(output.ends_with("1 passed, 1 failed\n"));
+        assert!(test.execute().await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_missing_rainlang() {
+        let local_evm = LocalEvm::new().await;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), SUITE).unwrap();
+
+        let test = Test {
+            files: vec![file.path().to_path_buf()],
+            rainlang: None,
+            output_path: None,
+            forked_evm: NewForkedEvmCliArgs {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                cache_dir: None,
+                offline: false,
+                rpc: Default::default(),
+            },
+        };
+
+        let err = test.run().await.unwrap_err().to_string();
+        assert!(err.contains("No Rainlang address"), "got: {err}");
+    }
+}
@@ -0,0 +1,100 @@
+use alloy::primitives::Address;
+use clap::Args;
+use rain_interpreter_dispair::DISPaiR;
+use rain_interpreter_eval::error::ForkCallError;
+use rain_interpreter_eval::eval::check_eval_dispair;
+
+/// CLI arguments for targeting explicit interpreter components instead of
+/// resolving them from a Rainlang contract.
+#[derive(Args, Clone, Debug, Default)]
+pub struct DISPaiRCliArgs {
+    #[arg(
+        long,
+        help = "The address of the expression deployer",
+        conflicts_with = "rainlang"
+    )]
+    pub deployer: Option<Address>,
+    #[arg(long, help = "The address of the interpreter", requires = "deployer")]
+    pub interpreter: Option<Address>,
+    #[arg(
+        long,
+        help = "The address of the interpreter store",
+        requires = "deployer"
+    )]
+    pub store: Option<Address>,
+    #[arg(long, help = "The address of the parser", requires = "deployer")]
+    pub parser: Option<Address>,
+}
+
+impl DISPaiRCliArgs {
+    /// Returns the explicit DISPaiR if a deployer was given. Components that
+    /// were not given are left as the zero address, which is enough to
+    /// parse; see [`DISPaiRCliArgs::into_eval_dispair`] for eval.
+    pub fn into_dispair(self) -> Option<DISPaiR> {
+        self.deployer.map(|deployer| {
+            DISPaiR::new(
+                deployer,
+                self.interpreter.unwrap_or_default(),
+                self.store.unwrap_or_default(),
+                self.parser.unwrap_or_default(),
+            )
+        })
+    }
+
+    /// Returns the explicit DISPaiR like [`DISPaiRCliArgs::into_dispair`],
+    /// failing if it lacks the interpreter or store that eval calls.
+    pub fn into_eval_dispair(self) -> Result<Option<DISPaiR>, ForkCallError> {
+        let dispair = self.into_dispair();
+        if let Some(dispair) = &dispair {
+            check_eval_dispair(dispair)?;
+        }
+        Ok(dispair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_dispair() {
+        assert!(DISPaiRCliArgs::default().into_dispair().is_none());
+
+        let args = DISPaiRCliArgs {
+            deployer: Some(Address::repeat_byte(0x1)),
+            interpreter: Some(Address::repeat_byte(0x2)),
+            store: None,
+            parser: Some(Address::repeat_byte(0x4)),
+        };
+        let dispair = args.into_dispair().unwrap();
+        assert_eq!(dispair.deployer, Address::repeat_byte(0x1));
+        assert_eq!(dispair.interpreter, Address::repeat_byte(0x2));
+        assert_eq!(dispair.store, Address::ZERO);
+        assert_eq!(dispair.parser, Address::repeat_byte(0x4));
+    }
+
+    #[test]
+    fn test_into_eval_dispair() {
+        assert!(
+            DISPaiRCliArgs::default()
+                .into_eval_dispair()
+                .unwrap()
+                .is_none()
+        );
+
+        let mut args = DISPaiRCliArgs {
+            deployer: Some(Address::repeat_byte(0x1)),
+            interpreter: Some(Address::repeat_byte(0x2)),
+            store: None,
+            parser: None,
+        };
+        assert!(matches!(
+            args.clone().into_eval_dispair(),
+            Err(ForkCallError::IncompleteDISPaiR("store"))
+        ));
+
+        args.store = Some(Address::repeat_byte(0x3));
+        let dispair = args.into_eval_dispair().unwrap().unwrap();
+        assert_eq!(dispair.store, Address::repeat_byte(0x3));
+    }
+}
@@ -1,6 +1,8 @@
 use alloy::primitives::BlockNumber;
 use clap::Args;
-use rain_interpreter_eval::fork::NewForkedEvm;
+use rain_interpreter_eval::fork::{NewForkedEvm, RpcCache, RpcOptions};
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// CLI arguments for connecting to a forked EVM via RPC.
 #[derive(Args, Clone, Debug)]
@@ -9,6 +11,49 @@ pub struct NewForkedEvmCliArgs {
     pub fork_url: String,
     #[arg(short = 'b', long, help = "Optional block number to fork from")]
     pub fork_block_number: Option<BlockNumber>,
+    #[arg(
+        long,
+        help = "Directory to cache forked state in, reused by later runs at the same block. Requires a block number"
+    )]
+    pub cache_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Run from the cached forked state without connecting to the RPC. Reading state that is not cached is an error",
+        requires_all = ["cache_dir", "fork_block_number"]
+    )]
+    pub offline: bool,
+    #[command(flatten)]
+    pub rpc: RpcCliArgs,
+}
+
+/// CLI arguments for how forks talk to their RPC.
+#[derive(Args, Clone, Debug, Default)]
+pub struct RpcCliArgs {
+    #[arg(
+        long = "fallback-url",
+        help = "RPC url to fork from if the fork url cannot be reached. Repeat to try several in order"
+    )]
+    pub fallback_urls: Vec<String>,
+    #[arg(long, help = "Times a failed RPC request is retried")]
+    pub rpc_retries: Option<u32>,
+    #[arg(
+        long,
+        help = "Milliseconds to wait before the first RPC retry, doubled for each retry after it"
+    )]
+    pub rpc_retry_backoff: Option<u64>,
+    #[arg(long, help = "Compute units per second the RPC allows")]
+    pub compute_units_per_second: Option<u64>,
+}
+
+impl From<RpcCliArgs> for RpcOptions {
+    fn from(args: RpcCliArgs) -> Self {
+        RpcOptions {
+            fallback_urls: args.fallback_urls,
+            retries: args.rpc_retries,
+            retry_backoff: args.rpc_retry_backoff.map(Duration::from_millis),
+            compute_units_per_second: args.compute_units_per_second,
+        }
+    }
 }
 
 impl From<NewForkedEvmCliArgs> for NewForkedEvm {
@@ -16,6 +61,11 @@ impl From<NewForkedEvmCliArgs> for NewForkedEvm {
         NewForkedEvm {
             fork_url: args.fork_url,
             fork_block_number: args.fork_block_number,
+            rpc_cache: args.cache_dir.map(|dir| RpcCache {
+                dir,
+                offline: args.offline,
+            }),
+            rpc: args.rpc.into(),
         }
     }
 }
@@ -4,20 +4,37 @@ use crate::commands::Parse;
 use crate::execute::Execute;
 use anyhow::Result;
 use clap::Parser;
-use commands::Eval;
+use commands::{Eval, Fuzz, Scenario, Serve, SignContext, Test};
+use rain_interpreter_eval::error::ForkCallError;
 
 mod commands;
+mod dispair;
 mod execute;
 mod fork;
 mod output;
 
-/// Top-level CLI command enum dispatching to `Parse` or `Eval` subcommands.
+/// Process exit code used when an eval stops on a failed `ensure`, so that
+/// scripts can tell rejected conditions apart from other failures.
+pub const ENSURE_FAILED_EXIT_CODE: i32 = 3;
+
+/// Top-level CLI command enum dispatching to the `Parse`, `Eval`, `Test`,
+/// `Scenario`, `Fuzz`, `SignContext` and `Serve` subcommands.
 #[derive(Parser)]
 pub enum Interpreter {
     /// Parse a Rainlang expression into bytecode.
     Parse(Parse),
     /// Evaluate a Rainlang expression against a forked EVM.
     Eval(Eval),
+    /// Run Rainlang test files against a forked EVM.
+    Test(Test),
+    /// Run multi-step scenario files against a fresh EVM.
+    Scenario(Scenario),
+    /// Fuzz the inputs and context of an expression against a forked EVM.
+    Fuzz(Fuzz),
+    /// Sign context rows with a local private key.
+    SignContext(SignContext),
+    /// Serve parse and eval requests over HTTP from a warm fork.
+    Serve(Serve),
 }
 
 impl Interpreter {
@@ -26,6 +43,19 @@ impl Interpreter {
         match self {
             Interpreter::Parse(parse) => parse.execute().await,
             Interpreter::Eval(eval) => eval.execute().await,
+            Interpreter::Test(test) => test.execute().await,
+            Interpreter::Scenario(scenario) => scenario.execute().await,
+            Interpreter::Fuzz(fuzz) => fuzz.execute().await,
+            Interpreter::SignContext(sign_context) => sign_context.execute().await,
+            Interpreter::Serve(serve) => serve.execute().await,
         }
     }
 }
+
+/// Returns the process exit code for an error returned by `execute`.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<ForkCallError>() {
+        Some(ForkCallError::EnsureFailed { .. }) => ENSURE_FAILED_EXIT_CODE,
+        _ => 1,
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use clap::Parser;
-use rain_i9r_cli::Interpreter;
+use rain_i9r_cli::{Interpreter, exit_code};
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
 
 #[derive(Parser)]
@@ -29,5 +29,9 @@ async fn main() -> Result<()> {
         .init();
 
     let cli = Cli::parse();
-    cli.i9r.execute().await
+    if let Err(err) = cli.i9r.execute().await {
+        eprintln!("Error: {err:?}");
+        std::process::exit(exit_code(&err));
+    }
+    Ok(())
 }
@@ -1,16 +1,20 @@
 //! DISPaiR (Deployer/Interpreter/Store/Parser) address tuple.
 
 use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
 
 /// Deployer/Interpreter/Store/Parser address tuple.
 ///
 /// Groups the four contract addresses that together form a complete
 /// Rain interpreter deployment.
-#[derive(Debug, Clone, Default)]
+///
+/// In JSON the parser may be left out, as only parsing needs it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DISPaiR {
     pub deployer: Address,
     pub interpreter: Address,
     pub store: Address,
+    #[serde(default)]
     pub parser: Address,
 }
 
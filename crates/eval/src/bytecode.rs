@@ -0,0 +1,286 @@
+use alloy::primitives::{B256, Bytes};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Size in bytes of each source header: ops count, stack allocation, inputs
+/// and outputs.
+pub(crate) const SOURCE_HEADER_SIZE: usize = 4;
+
+/// Size in bytes of a single op in a source.
+pub(crate) const OP_SIZE: usize = 4;
+
+/// Errors that can occur when decoding serialized expressions or bytecode.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BytecodeError {
+    #[error("Serialized expression is truncated")]
+    TruncatedSerialized,
+    #[error("Bytecode is empty")]
+    EmptyBytecode,
+    #[error("Bytecode is too short for the offsets of {0} sources")]
+    TruncatedOffsets(usize),
+    #[error("Source {0} is out of bounds of the bytecode")]
+    SourceOutOfBounds(usize),
+}
+
+/// An expression as returned by `parse2`: constants and bytecode laid out as
+/// `[constants length][constants...][bytecode length][bytecode...]`, which is
+/// also what `eval4` expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializedExpression {
+    pub constants: Vec<B256>,
+    pub bytecode: Bytes,
+}
+
+impl SerializedExpression {
+    /// Decodes the `parse2` serialization into its constants and bytecode.
+    ///
+    /// Lengths are checked against the data before anything is allocated,
+    /// so untrusted input can't make this allocate more than it holds.
+    pub fn deserialize(serialized: &[u8]) -> Result<Self, BytecodeError> {
+        let constants_length = read_length(serialized, 0)?;
+        if constants_length > (serialized.len() - 32) / 32 {
+            return Err(BytecodeError::TruncatedSerialized);
+        }
+        let mut cursor = 32;
+        let mut constants = Vec::with_capacity(constants_length);
+        for _ in 0..constants_length {
+            let word = serialized
+                .get(cursor..advance(cursor, 32)?)
+                .ok_or(BytecodeError::TruncatedSerialized)?;
+            constants.push(B256::from_slice(word));
+            cursor = advance(cursor, 32)?;
+        }
+
+        let bytecode_length = read_length(serialized, cursor)?;
+        cursor = advance(cursor, 32)?;
+        let bytecode = serialized
+            .get(cursor..advance(cursor, bytecode_length)?)
+            .ok_or(BytecodeError::TruncatedSerialized)?;
+
+        Ok(SerializedExpression {
+            constants,
+            bytecode: Bytes::copy_from_slice(bytecode),
+        })
+    }
+
+    /// Encodes the constants and bytecode into the `parse2` serialization.
+    pub fn serialize(&self) -> Bytes {
+        let mut serialized =
+            Vec::with_capacity(64 + self.constants.len() * 32 + self.bytecode.len());
+        serialized.extend_from_slice(
+            &B256::left_padding_from(&(self.constants.len() as u64).to_be_bytes())[..],
+        );
+        for constant in &self.constants {
+            serialized.extend_from_slice(constant.as_slice());
+        }
+        serialized.extend_from_slice(
+            &B256::left_padding_from(&(self.bytecode.len() as u64).to_be_bytes())[..],
+        );
+        serialized.extend_from_slice(&self.bytecode);
+        serialized.into()
+    }
+}
+
+/// Moves `cursor` forward by `length`, treating overflow as truncation.
+fn advance(cursor: usize, length: usize) -> Result<usize, BytecodeError> {
+    cursor
+        .checked_add(length)
+        .ok_or(BytecodeError::TruncatedSerialized)
+}
+
+/// Reads a 32 byte big endian length word at `offset`.
+fn read_length(data: &[u8], offset: usize) -> Result<usize, BytecodeError> {
+    let word = data
+        .get(offset..advance(offset, 32)?)
+        .ok_or(BytecodeError::TruncatedSerialized)?;
+    // Anything that doesn't fit in the low 8 bytes can't be in bounds.
+    if word[..24].iter().any(|b| *b != 0) {
+        return Err(BytecodeError::TruncatedSerialized);
+    }
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&word[24..]);
+    usize::try_from(u64::from_be_bytes(low)).map_err(|_| BytecodeError::TruncatedSerialized)
+}
+
+/// The IO and stack conclusions of the integrity check for a single source.
+///
+/// The deployer's integrity check reverts unless the source header agrees
+/// with what it computes, so for bytecode returned by `parse2` the header is
+/// the integrity check's result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub source_index: usize,
+    pub ops_count: u8,
+    pub inputs: u8,
+    pub outputs: u8,
+    pub max_stack_height: u8,
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "source {}: inputs {}, outputs {}, max stack height {}, ops {}",
+            self.source_index, self.inputs, self.outputs, self.max_stack_height, self.ops_count
+        )
+    }
+}
+
+/// Builds an [`IntegrityReport`] for every source in the bytecode by reading
+/// the source headers.
+pub fn integrity_reports(bytecode: &[u8]) -> Result<Vec<IntegrityReport>, BytecodeError> {
+    let source_count = *bytecode.first().ok_or(BytecodeError::EmptyBytecode)? as usize;
+    let sources_start = 1 + source_count * 2;
+    if bytecode.len() < sources_start {
+        return Err(BytecodeError::TruncatedOffsets(source_count));
+    }
+
+    (0..source_count)
+        .map(|source_index| {
+            let offset = u16::from_be_bytes([
+                bytecode[1 + source_index * 2],
+                bytecode[2 + source_index * 2],
+            ]) as usize;
+            let start = sources_start + offset;
+            let header = bytecode
+                .get(start..start + SOURCE_HEADER_SIZE)
+                .ok_or(BytecodeError::SourceOutOfBounds(source_index))?;
+            let ops_count = header[0];
+            let end = start + SOURCE_HEADER_SIZE + ops_count as usize * OP_SIZE;
+            if end > bytecode.len() {
+                return Err(BytecodeError::SourceOutOfBounds(source_index));
+            }
+
+            Ok(IntegrityReport {
+                source_index,
+                ops_count,
+                max_stack_height: header[1],
+                inputs: header[2],
+                outputs: header[3],
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::hex;
+
+    #[test]
+    fn test_deserialize_roundtrip() {
+        let serialized = hex::decode("0x00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000b0100000101000101100000").unwrap();
+        let expression = SerializedExpression::deserialize(&serialized).unwrap();
+
+        assert_eq!(expression.constants, vec![B256::left_padding_from(&[1u8])]);
+        assert_eq!(
+            expression.bytecode.to_vec(),
+            hex::decode("0x0100000101000101100000").unwrap()
+        );
+        assert_eq!(expression.serialize().to_vec(), serialized);
+    }
+
+    #[test]
+    fn test_deserialize_truncated() {
+        assert_eq!(
+            SerializedExpression::deserialize(&[0u8; 31]),
+            Err(BytecodeError::TruncatedSerialized)
+        );
+
+        // One constant declared but none present.
+        let mut serialized = vec![0u8; 32];
+        serialized[31] = 1;
+        assert_eq!(
+            SerializedExpression::deserialize(&serialized),
+            Err(BytecodeError::TruncatedSerialized)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_huge_constants_length() {
+        // Claims u64::MAX constants with only two words of data.
+        let mut serialized = vec![0u8; 64];
+        serialized[24..32].copy_from_slice(&[0xff; 8]);
+        assert_eq!(
+            SerializedExpression::deserialize(&serialized),
+            Err(BytecodeError::TruncatedSerialized)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_overflowing_bytecode_length() {
+        // No constants, then a bytecode length that overflows the cursor.
+        let mut serialized = vec![0u8; 64];
+        serialized[56..64].copy_from_slice(&[0xff; 8]);
+        assert_eq!(
+            SerializedExpression::deserialize(&serialized),
+            Err(BytecodeError::TruncatedSerialized)
+        );
+    }
+
+    #[test]
+    fn test_integrity_reports_single_source() {
+        let bytecode = hex::decode("0x0100000101000101100000").unwrap();
+        let reports = integrity_reports(&bytecode).unwrap();
+        assert_eq!(
+            reports,
+            vec![IntegrityReport {
+                source_index: 0,
+                ops_count: 1,
+                inputs: 0,
+                outputs: 1,
+                max_stack_height: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_integrity_reports_two_sources() {
+        // Source 0 has no ops and no IO, source 1 has 2 inputs, 1 output,
+        // stack height 3 and one op.
+        let bytecode = hex::decode("0x02000000040000000001030201aabbccdd").unwrap();
+        let reports = integrity_reports(&bytecode).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].ops_count, 0);
+        assert_eq!(reports[1].source_index, 1);
+        assert_eq!(reports[1].ops_count, 1);
+        assert_eq!(reports[1].max_stack_height, 3);
+        assert_eq!(reports[1].inputs, 2);
+        assert_eq!(reports[1].outputs, 1);
+    }
+
+    #[test]
+    fn test_integrity_reports_errors() {
+        assert_eq!(integrity_reports(&[]), Err(BytecodeError::EmptyBytecode));
+        assert_eq!(
+            integrity_reports(&[0x02, 0x00]),
+            Err(BytecodeError::TruncatedOffsets(2))
+        );
+        assert_eq!(
+            integrity_reports(&[0x01, 0x00, 0x00, 0x01, 0x01]),
+            Err(BytecodeError::SourceOutOfBounds(0))
+        );
+        // Header declares one op but no op bytes follow.
+        assert_eq!(
+            integrity_reports(&[0x01, 0x00, 0x00, 0x01, 0x01, 0x00, 0x01]),
+            Err(BytecodeError::SourceOutOfBounds(0))
+        );
+    }
+
+    #[test]
+    fn test_integrity_report_display() {
+        let report = IntegrityReport {
+            source_index: 2,
+            ops_count: 5,
+            inputs: 1,
+            outputs: 2,
+            max_stack_height: 4,
+        };
+        assert_eq!(
+            report.to_string(),
+            "source 2: inputs 1, outputs 2, max stack height 4, ops 5"
+        );
+    }
+}
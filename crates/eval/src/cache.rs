@@ -0,0 +1,230 @@
+use alloy::primitives::{Address, B256, BlockNumber, Bytes, keccak256};
+use foundry_evm::backend::LocalForkId;
+use lru::LruCache;
+use rain_interpreter_dispair::DISPaiR;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+/// Default number of parse results kept by a `Forker`.
+pub(crate) const DEFAULT_PARSE_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
+
+/// The fork a lookup was made on and the block its state was at. Clones of
+/// a `Forker` share the cache but roll their forks independently, so the
+/// block is part of every key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct ForkBlock {
+    pub(crate) fork_id: Option<LocalForkId>,
+    pub(crate) block_number: BlockNumber,
+}
+
+/// Key of a cached parse result: the deployer that parsed it, the hash of
+/// the parsed text and the fork and block it was parsed on. Keying by
+/// deployer rather than Rainlang contract lets explicitly provided DISPaiRs
+/// share the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct ParseCacheKey {
+    deployer: Address,
+    text_hash: B256,
+    fork: ForkBlock,
+}
+
+impl ParseCacheKey {
+    pub(crate) fn new(deployer: Address, text: &str, fork: ForkBlock) -> Self {
+        Self {
+            deployer,
+            text_hash: keccak256(text.as_bytes()),
+            fork,
+        }
+    }
+}
+
+/// Memoized lookups shared by clones of a `Forker`.
+///
+/// Resolved DISPaiRs, parsed bytecode and interpreters' opcode function
+/// pointers only depend on the code deployed on a fork at a block, so they
+/// are keyed by both. A fork's entries are still dropped when it is rolled,
+/// as that also discards locally committed code.
+pub(crate) struct ForkerCache {
+    dispairs: HashMap<(Address, ForkBlock), DISPaiR>,
+    parses: LruCache<ParseCacheKey, Bytes>,
+    /// `buildOpcodeFunctionPointers` of each interpreter.
+    function_pointers: HashMap<(Address, ForkBlock), Bytes>,
+}
+
+impl Default for ForkerCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PARSE_CACHE_CAPACITY)
+    }
+}
+
+impl ForkerCache {
+    pub(crate) fn new(parse_capacity: NonZeroUsize) -> Self {
+        Self {
+            dispairs: HashMap::new(),
+            parses: LruCache::new(parse_capacity),
+            function_pointers: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn dispair(&self, rainlang: Address, fork: ForkBlock) -> Option<DISPaiR> {
+        self.dispairs.get(&(rainlang, fork)).cloned()
+    }
+
+    pub(crate) fn insert_dispair(&mut self, rainlang: Address, fork: ForkBlock, dispair: DISPaiR) {
+        self.dispairs.insert((rainlang, fork), dispair);
+    }
+
+    pub(crate) fn parse(&mut self, key: &ParseCacheKey) -> Option<Bytes> {
+        self.parses.get(key).cloned()
+    }
+
+    pub(crate) fn insert_parse(&mut self, key: ParseCacheKey, bytecode: Bytes) {
+        self.parses.put(key, bytecode);
+    }
+
+    pub(crate) fn function_pointers(&self, interpreter: Address, fork: ForkBlock) -> Option<Bytes> {
+        self.function_pointers.get(&(interpreter, fork)).cloned()
+    }
+
+    pub(crate) fn insert_function_pointers(
+        &mut self,
+        interpreter: Address,
+        fork: ForkBlock,
+        function_pointers: Bytes,
+    ) {
+        self.function_pointers
+            .insert((interpreter, fork), function_pointers);
+    }
+
+    pub(crate) fn resize(&mut self, parse_capacity: NonZeroUsize) {
+        self.parses.resize(parse_capacity);
+    }
+
+    /// Drops every entry that was resolved on the given fork, at any block.
+    pub(crate) fn invalidate_fork(&mut self, fork_id: Option<LocalForkId>) {
+        self.dispairs.retain(|(_, fork), _| fork.fork_id != fork_id);
+        self.function_pointers
+            .retain(|(_, fork), _| fork.fork_id != fork_id);
+        let stale: Vec<ParseCacheKey> = self
+            .parses
+            .iter()
+            .filter(|(key, _)| key.fork.fork_id == fork_id)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in stale {
+            self.parses.pop(&key);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.dispairs.clear();
+        self.parses.clear();
+        self.function_pointers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    fn dispair() -> DISPaiR {
+        DISPaiR::new(
+            Address::repeat_byte(0x1),
+            Address::repeat_byte(0x2),
+            Address::repeat_byte(0x3),
+            Address::repeat_byte(0x4),
+        )
+    }
+
+    fn fork(id: u64, block_number: BlockNumber) -> ForkBlock {
+        ForkBlock {
+            fork_id: Some(U256::from(id)),
+            block_number,
+        }
+    }
+
+    #[test]
+    fn test_parse_cache_key() {
+        let deployer = Address::repeat_byte(0x5);
+        let fork_id = fork(0, 10);
+        assert_eq!(
+            ParseCacheKey::new(deployer, "_: 1;", fork_id),
+            ParseCacheKey::new(deployer, "_: 1;", fork_id)
+        );
+        assert_ne!(
+            ParseCacheKey::new(deployer, "_: 1;", fork_id),
+            ParseCacheKey::new(deployer, "_: 2;", fork_id)
+        );
+        assert_ne!(
+            ParseCacheKey::new(deployer, "_: 1;", fork_id),
+            ParseCacheKey::new(deployer, "_: 1;", fork(1, 10))
+        );
+        assert_ne!(
+            ParseCacheKey::new(deployer, "_: 1;", fork_id),
+            ParseCacheKey::new(deployer, "_: 1;", fork(0, 11))
+        );
+        assert_ne!(
+            ParseCacheKey::new(deployer, "_: 1;", fork_id),
+            ParseCacheKey::new(Address::repeat_byte(0x6), "_: 1;", fork_id)
+        );
+    }
+
+    #[test]
+    fn test_parse_cache_evicts_least_recently_used() {
+        let mut cache = ForkerCache::new(NonZeroUsize::new(2).unwrap());
+        let rainlang = Address::repeat_byte(0x5);
+        let no_fork = ForkBlock {
+            fork_id: None,
+            block_number: 0,
+        };
+        let a = ParseCacheKey::new(rainlang, "a", no_fork);
+        let b = ParseCacheKey::new(rainlang, "b", no_fork);
+        let c = ParseCacheKey::new(rainlang, "c", no_fork);
+
+        cache.insert_parse(a, Bytes::from(vec![0xa]));
+        cache.insert_parse(b, Bytes::from(vec![0xb]));
+        // Touch `a` so that `b` is the least recently used.
+        assert_eq!(cache.parse(&a), Some(Bytes::from(vec![0xa])));
+        cache.insert_parse(c, Bytes::from(vec![0xc]));
+
+        assert!(cache.parse(&b).is_none());
+        assert!(cache.parse(&a).is_some());
+        assert!(cache.parse(&c).is_some());
+    }
+
+    #[test]
+    fn test_invalidate_fork() {
+        let mut cache = ForkerCache::default();
+        let rainlang = Address::repeat_byte(0x5);
+        let fork_0 = fork(0, 10);
+        let fork_0_later = fork(0, 11);
+        let fork_1 = fork(1, 10);
+
+        cache.insert_dispair(rainlang, fork_0, dispair());
+        cache.insert_dispair(rainlang, fork_0_later, dispair());
+        cache.insert_dispair(rainlang, fork_1, dispair());
+        let key_0 = ParseCacheKey::new(rainlang, "_: 1;", fork_0);
+        let key_1 = ParseCacheKey::new(rainlang, "_: 1;", fork_1);
+        cache.insert_parse(key_0, Bytes::from(vec![0x0]));
+        cache.insert_parse(key_1, Bytes::from(vec![0x1]));
+        let interpreter = Address::repeat_byte(0x6);
+        cache.insert_function_pointers(interpreter, fork_0, Bytes::from(vec![0x0]));
+        cache.insert_function_pointers(interpreter, fork_1, Bytes::from(vec![0x1]));
+
+        cache.invalidate_fork(fork_0.fork_id);
+
+        assert!(cache.dispair(rainlang, fork_0).is_none());
+        assert!(cache.dispair(rainlang, fork_0_later).is_none());
+        assert!(cache.parse(&key_0).is_none());
+        assert!(cache.dispair(rainlang, fork_1).is_some());
+        assert!(cache.parse(&key_1).is_some());
+        assert!(cache.function_pointers(interpreter, fork_0).is_none());
+        assert!(cache.function_pointers(interpreter, fork_1).is_some());
+
+        cache.clear();
+        assert!(cache.dispair(rainlang, fork_1).is_none());
+        assert!(cache.parse(&key_1).is_none());
+        assert!(cache.function_pointers(interpreter, fork_1).is_none());
+    }
+}
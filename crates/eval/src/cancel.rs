@@ -0,0 +1,212 @@
+use crate::error::ForkCallError;
+use foundry_evm::Env;
+use foundry_evm::InspectorExt;
+use foundry_evm::backend::CowBackend;
+use foundry_evm::executors::{Executor, RawCallResult};
+use foundry_evm::inspectors::{InspectorData, InspectorStack};
+use revm::Inspector;
+use revm::context::result::{ExecutionResult, Output, ResultAndState};
+use revm::interpreter::interpreter_types::LoopControl;
+use revm::interpreter::{
+    CallInputs, CallOutcome, CreateInputs, CreateOutcome, InstructionResult, Interpreter,
+    InterpreterTypes,
+};
+use revm::primitives::{Address, Bytes, Log, U256};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Steps between checks of the deadline, which reads the clock.
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+/// When a call on a blocking thread should stop: once its flag is set, or
+/// once its timeout has passed since it started.
+pub(crate) struct Cancel<'a> {
+    flag: &'a AtomicBool,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+/// The flag of calls that are never cancelled.
+static NEVER: AtomicBool = AtomicBool::new(false);
+
+impl<'a> Cancel<'a> {
+    /// Cancels once `flag` is set or `timeout` has passed from now.
+    pub(crate) fn new(flag: &'a AtomicBool, timeout: Option<Duration>) -> Self {
+        Self {
+            flag,
+            timeout,
+            deadline: timeout.and_then(|timeout| Instant::now().checked_add(timeout)),
+        }
+    }
+
+    /// Never cancels, for the sync calls.
+    pub(crate) fn never() -> Cancel<'static> {
+        Cancel::new(&NEVER, None)
+    }
+
+    /// Whether the flag is set.
+    pub(crate) fn is_flagged(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// Whether the timeout has passed.
+    fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Fails with [`ForkCallError::Timeout`] once the timeout has passed, as
+    /// the calls behind `result` may have been halted by it.
+    pub(crate) fn check<R>(&self, result: Result<R, ForkCallError>) -> Result<R, ForkCallError> {
+        match self.timeout {
+            Some(timeout) if self.is_expired() => Err(ForkCallError::Timeout(timeout)),
+            _ => result,
+        }
+    }
+}
+
+/// Sets the flag that cancels blocking calls once their caller stops
+/// waiting.
+pub(crate) struct CancelOnDrop(pub(crate) Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Wraps the executor's inspectors, halting every frame at its next step
+/// once the call is cancelled, so a call that is no longer awaited stops
+/// rather than running on until it runs out of gas.
+struct Cancellable<'a, 'b, I> {
+    inner: I,
+    cancel: &'a Cancel<'b>,
+    steps: u32,
+    halted: bool,
+}
+
+impl<CTX, INTR: InterpreterTypes, I: Inspector<CTX, INTR>> Inspector<CTX, INTR>
+    for Cancellable<'_, '_, I>
+{
+    fn initialize_interp(&mut self, interp: &mut Interpreter<INTR>, context: &mut CTX) {
+        self.inner.initialize_interp(interp, context);
+    }
+
+    fn step(&mut self, interp: &mut Interpreter<INTR>, context: &mut CTX) {
+        let check_deadline = self.steps % DEADLINE_CHECK_INTERVAL == 0;
+        self.steps = self.steps.wrapping_add(1);
+        self.halted =
+            self.halted || self.cancel.is_flagged() || (check_deadline && self.cancel.is_expired());
+        if self.halted {
+            // The result of a cancelled call is discarded, so the reason it
+            // halts with is never seen.
+            interp
+                .control
+                .set_instruction_result(InstructionResult::OutOfGas);
+            return;
+        }
+        self.inner.step(interp, context);
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter<INTR>, context: &mut CTX) {
+        self.inner.step_end(interp, context);
+    }
+
+    fn log(&mut self, interp: &mut Interpreter<INTR>, context: &mut CTX, log: Log) {
+        self.inner.log(interp, context, log);
+    }
+
+    fn call(&mut self, context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.inner.call(context, inputs)
+    }
+
+    fn call_end(&mut self, context: &mut CTX, inputs: &CallInputs, outcome: &mut CallOutcome) {
+        self.inner.call_end(context, inputs, outcome);
+    }
+
+    fn create(&mut self, context: &mut CTX, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        self.inner.create(context, inputs)
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut CTX,
+        inputs: &CreateInputs,
+        outcome: &mut CreateOutcome,
+    ) {
+        self.inner.create_end(context, inputs, outcome);
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        self.inner.selfdestruct(contract, target, value);
+    }
+}
+
+// The forker's inspector stack has no cheatcodes, so the defaults hold.
+impl InspectorExt for Cancellable<'_, '_, InspectorStack> {}
+
+/// Runs `env` on `executor` without committing, like
+/// [`Executor::call_with_env`], halting once `cancel` says so.
+pub(crate) fn call(
+    executor: &Executor,
+    inspector: InspectorStack,
+    mut env: Env,
+    cancel: &Cancel<'_>,
+) -> eyre::Result<RawCallResult> {
+    let mut inspector = Cancellable {
+        inner: inspector,
+        cancel,
+        steps: 0,
+        halted: false,
+    };
+    let mut backend = CowBackend::new_borrowed(executor.backend());
+    let result = backend.inspect(&mut env, &mut inspector)?;
+    Ok(raw_call_result(env, inspector.inner, result))
+}
+
+/// Builds the [`RawCallResult`] the executor would for `result`, keeping
+/// what the forker reads: the exit, output, gas, logs, traces and state.
+fn raw_call_result(env: Env, inspector: InspectorStack, result: ResultAndState) -> RawCallResult {
+    let ResultAndState { result, state } = result;
+    let (exit_reason, gas_used, gas_refunded, out, logs): (InstructionResult, _, _, _, _) =
+        match result {
+            ExecutionResult::Success {
+                reason,
+                gas_used,
+                gas_refunded,
+                output,
+                logs,
+            } => (reason.into(), gas_used, gas_refunded, Some(output), logs),
+            ExecutionResult::Revert { gas_used, output } => (
+                InstructionResult::Revert,
+                gas_used,
+                0,
+                Some(Output::Call(output)),
+                vec![],
+            ),
+            ExecutionResult::Halt { reason, gas_used } => {
+                (reason.into(), gas_used, 0, None, vec![])
+            }
+        };
+    let result = match &out {
+        Some(Output::Call(data)) => data.clone(),
+        _ => Bytes::new(),
+    };
+    let InspectorData { labels, traces, .. } = inspector.collect();
+
+    RawCallResult {
+        exit_reason,
+        reverted: !exit_reason.is_ok(),
+        result,
+        gas_used,
+        gas_refunded,
+        logs,
+        labels,
+        traces,
+        state_changeset: state,
+        env,
+        out,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,416 @@
+use crate::error::ForkCallError;
+use crate::eval::ForkEvalArgs;
+use crate::fork::{Forker, NewForkedEvm};
+use crate::trace::{RainEvalResult, flattened_trace_path_names};
+use alloy::primitives::{Address, U256};
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A chain to evaluate on and the Rainlang contract deployed there.
+#[derive(Debug, Clone)]
+pub struct CompareTarget {
+    pub fork: NewForkedEvm,
+    pub rainlang: Address,
+}
+
+/// The chain and block a comparison row was evaluated at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChainKey {
+    pub chain_id: u64,
+    pub block_number: u64,
+}
+
+impl fmt::Display for ChainKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "chain {} @ {}", self.chain_id, self.block_number)
+    }
+}
+
+/// The eval of one [`CompareTarget`].
+#[derive(Debug)]
+pub struct ChainEval {
+    pub target: CompareTarget,
+    /// `None` if the fork of the target could not be created.
+    pub chain: Option<ChainKey>,
+    /// Reverts are part of the result. An error means the expression could
+    /// not be evaluated at all, e.g. the Rainlang contract is not deployed
+    /// on this chain or its RPC is down.
+    pub result: Result<RainEvalResult, ForkCallError>,
+}
+
+impl ChainEval {
+    /// The chain and block the eval ran at, or the fork URL and block of the
+    /// target if its fork could not be created.
+    pub fn label(&self) -> String {
+        match (&self.chain, self.target.fork.fork_block_number) {
+            (Some(chain), _) => chain.to_string(),
+            (None, Some(block_number)) => format!("{} @ {block_number}", self.target.fork.fork_url),
+            (None, None) => self.target.fork.fork_url.clone(),
+        }
+    }
+
+    /// The stack of every source, flattened in the order of its
+    /// [`flattened_trace_path_names`]. `None` if the eval failed or reverted.
+    pub fn row(&self) -> Option<Vec<U256>> {
+        match &self.result {
+            Ok(result) if !result.reverted => Some(
+                result
+                    .traces
+                    .iter()
+                    .flat_map(|trace| trace.stack.iter().rev().copied())
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// The [`ChainEval::row`] keyed by flattened trace path. A path reached
+    /// more than once, e.g. a source called twice by the same parent, gets
+    /// `#2`, `#3` and so on appended to its later occurrences.
+    pub fn columns(&self) -> Option<Vec<(String, U256)>> {
+        let Ok(result) = &self.result else {
+            return None;
+        };
+        let row = self.row()?;
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let names = flattened_trace_path_names(&result.traces)
+            .into_iter()
+            .map(|name| {
+                let count = seen.entry(name.clone()).or_default();
+                *count += 1;
+                match *count {
+                    1 => name,
+                    n => format!("{name}#{n}"),
+                }
+            });
+        Some(names.zip(row).collect())
+    }
+}
+
+/// Side by side results of one expression evaluated on several chains, in
+/// the order of the targets.
+#[derive(Debug)]
+pub struct Comparison {
+    /// Flattened trace paths of every chain that evaluated successfully, in
+    /// the order they were first seen.
+    pub column_names: Vec<String>,
+    pub chains: Vec<ChainEval>,
+    /// Indices of the columns whose value differs between the chains that
+    /// evaluated successfully, including columns missing on some of them.
+    pub divergent: Vec<usize>,
+}
+
+impl Comparison {
+    /// Lines up the chains by trace path rather than by position, so a
+    /// source that only runs on some chains doesn't shift the columns after
+    /// it.
+    fn new(chains: Vec<ChainEval>) -> Self {
+        let rows: Vec<Vec<(String, U256)>> = chains.iter().filter_map(ChainEval::columns).collect();
+
+        let mut column_names: Vec<String> = vec![];
+        for (name, _) in rows.iter().flatten() {
+            if !column_names.contains(name) {
+                column_names.push(name.clone());
+            }
+        }
+
+        let rows: Vec<HashMap<&str, U256>> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|(name, value)| (name.as_str(), *value))
+                    .collect()
+            })
+            .collect();
+        let divergent = column_names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| {
+                let first = rows[0].get(name.as_str());
+                rows.iter().any(|row| row.get(name.as_str()) != first)
+            })
+            .map(|(column, _)| column)
+            .collect();
+
+        Self {
+            column_names,
+            chains,
+            divergent,
+        }
+    }
+
+    /// Whether every chain evaluated without reverting to the same stacks.
+    pub fn agrees(&self) -> bool {
+        self.divergent.is_empty()
+            && self
+                .chains
+                .iter()
+                .all(|chain| matches!(&chain.result, Ok(result) if !result.reverted))
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header: Vec<String> = self
+            .column_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                if self.divergent.contains(&i) {
+                    format!("{name}*")
+                } else {
+                    name.clone()
+                }
+            })
+            .collect();
+        writeln!(f, "chain\t{}", header.join("\t"))?;
+
+        for chain in &self.chains {
+            match (&chain.result, chain.columns()) {
+                (_, Some(columns)) => {
+                    let columns: HashMap<String, U256> = columns.into_iter().collect();
+                    let values: Vec<String> = self
+                        .column_names
+                        .iter()
+                        .map(|name| columns.get(name).map_or("-".to_string(), U256::to_string))
+                        .collect();
+                    writeln!(f, "{}\t{}", chain.label(), values.join("\t"))?;
+                }
+                (Ok(result), None) => match &result.error {
+                    Some(error) => writeln!(f, "{}\treverted: {error}", chain.label())?,
+                    None => writeln!(f, "{}\treverted", chain.label())?,
+                },
+                (Err(error), None) => writeln!(f, "{}\terror: {error}", chain.label())?,
+            }
+        }
+
+        if !self.divergent.is_empty() {
+            let names: Vec<&str> = self
+                .divergent
+                .iter()
+                .map(|&i| self.column_names.get(i).map_or("?", String::as_str))
+                .collect();
+            writeln!(
+                f,
+                "{} column(s) differ between chains: {}",
+                names.len(),
+                names.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates the same expression on every target concurrently, each on its
+/// own fork and against its own Rainlang contract.
+///
+/// `args.rainlang` and `args.dispair` are replaced by the target's Rainlang
+/// contract. Errors, including a fork that cannot be created, are reported
+/// per chain rather than failing the whole comparison.
+pub async fn compare(targets: Vec<CompareTarget>, args: ForkEvalArgs) -> Comparison {
+    let evals = targets.into_iter().map(|target| {
+        let args = ForkEvalArgs {
+            rainlang: target.rainlang,
+            dispair: None,
+            ..args.clone()
+        };
+        async move {
+            let forker = match Forker::new_with_fork(target.fork.clone(), None, None).await {
+                Ok(forker) => forker,
+                Err(e) => {
+                    return ChainEval {
+                        target,
+                        chain: None,
+                        result: Err(e),
+                    };
+                }
+            };
+            let env = &forker.executor.env().evm_env;
+            let chain = ChainKey {
+                chain_id: env.cfg_env.chain_id,
+                block_number: env.block_env.number,
+            };
+            ChainEval {
+                target,
+                chain: Some(chain),
+                result: forker.fork_eval_result(args).await,
+            }
+        }
+    });
+
+    Comparison::new(join_all(evals).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::{RainEvalOutcome, RainSourceTrace};
+    use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    fn eval_args(rainlang_string: &str) -> ForkEvalArgs {
+        ForkEvalArgs {
+            rainlang_string: rainlang_string.into(),
+            source_index: 0,
+            rainlang: Address::ZERO,
+            namespace: FullyQualifiedNamespace::default(),
+            context: vec![],
+            decode_errors: false,
+            inputs: vec![],
+            state_overlay: vec![],
+            bytecode: None,
+            dispair: None,
+            from: None,
+            env: Default::default(),
+        }
+    }
+
+    fn target(local_evm: &LocalEvm) -> CompareTarget {
+        CompareTarget {
+            fork: NewForkedEvm {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
+            },
+            rainlang: local_evm.rainlang,
+        }
+    }
+
+    fn chain_eval(chain_id: u64, traces: &[(u16, u16, &[u64])]) -> ChainEval {
+        let traces = traces
+            .iter()
+            .map(
+                |(parent_source_index, source_index, stack)| RainSourceTrace {
+                    parent_source_index: *parent_source_index,
+                    source_index: *source_index,
+                    stack: stack.iter().map(|v| U256::from(*v)).collect(),
+                },
+            )
+            .collect();
+        ChainEval {
+            target: CompareTarget {
+                fork: NewForkedEvm {
+                    fork_url: String::new(),
+                    fork_block_number: None,
+                    rpc_cache: None,
+                    rpc: Default::default(),
+                },
+                rainlang: Address::ZERO,
+            },
+            chain: Some(ChainKey {
+                chain_id,
+                block_number: 1,
+            }),
+            result: Ok(RainEvalResult {
+                reverted: false,
+                stack: vec![],
+                writes: vec![],
+                traces,
+                error: None,
+                outcome: RainEvalOutcome::Success,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_comparison_by_path() {
+        // Source 1 only runs on the second chain, between sources 0 and 2.
+        let comparison = Comparison::new(vec![
+            chain_eval(1, &[(0, 0, &[7]), (0, 2, &[9])]),
+            chain_eval(2, &[(0, 0, &[7]), (0, 1, &[4]), (0, 2, &[9])]),
+        ]);
+        assert_eq!(comparison.column_names, vec!["0.0", "0.2.0", "0.1.0"]);
+        assert_eq!(comparison.divergent, vec![2]);
+        assert_eq!(
+            comparison.to_string(),
+            "chain\t0.0\t0.2.0\t0.1.0*\n\
+             chain 1 @ 1\t7\t9\t-\n\
+             chain 2 @ 1\t7\t9\t4\n\
+             1 column(s) differ between chains: 0.1.0\n"
+        );
+
+        let comparison = Comparison::new(vec![
+            chain_eval(1, &[(0, 0, &[7]), (0, 2, &[9])]),
+            chain_eval(2, &[(0, 0, &[7]), (0, 2, &[8])]),
+        ]);
+        assert_eq!(comparison.divergent, vec![1]);
+    }
+
+    #[test]
+    fn test_columns_repeated_path() {
+        let chain = chain_eval(1, &[(0, 0, &[1]), (0, 1, &[2]), (0, 1, &[3])]);
+        assert_eq!(
+            chain.columns().unwrap(),
+            vec![
+                ("0.0".to_string(), U256::from(1)),
+                ("0.1.0".to_string(), U256::from(2)),
+                ("0.1.0#2".to_string(), U256::from(3)),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_compare() {
+        let a = LocalEvm::new().await;
+        // Deploying the token mines extra blocks on this chain.
+        let b = LocalEvm::new_with_tokens(1).await;
+        let targets = vec![target(&a), target(&b)];
+
+        let comparison = compare(targets.clone(), eval_args("_ _: block-number() 1;")).await;
+        assert_eq!(comparison.chains.len(), 2);
+        assert_ne!(comparison.chains[0].chain, comparison.chains[1].chain);
+        assert_eq!(comparison.column_names, vec!["0.0", "0.1"]);
+        assert_eq!(comparison.divergent, vec![0]);
+        assert!(!comparison.agrees());
+        let output = comparison.to_string();
+        assert!(output.contains("0.0*\t0.1\n"), "got: {output}");
+        assert!(output.contains("1 column(s) differ between chains: 0.0"));
+
+        let comparison = compare(targets.clone(), eval_args("_ _: chain-id() 1;")).await;
+        assert!(comparison.divergent.is_empty());
+        assert!(comparison.agrees());
+
+        let comparison = compare(targets, eval_args(":ensure(0 \"nope\");")).await;
+        assert!(comparison.chains.iter().all(|chain| chain.row().is_none()));
+        assert!(!comparison.agrees());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_compare_missing_deployment() {
+        let local_evm = LocalEvm::new().await;
+        let mut missing = target(&local_evm);
+        missing.rainlang = Address::repeat_byte(0x42);
+
+        let comparison = compare(vec![target(&local_evm), missing], eval_args("_: 1;")).await;
+        assert!(comparison.chains[0].result.is_ok());
+        assert!(comparison.chains[1].result.is_err());
+        assert!(comparison.divergent.is_empty());
+        assert!(!comparison.agrees());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_compare_fork_error() {
+        let local_evm = LocalEvm::new().await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut dead = target(&local_evm);
+        dead.fork.fork_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let comparison = compare(vec![target(&local_evm), dead.clone()], eval_args("_: 1;")).await;
+        assert!(comparison.chains[0].result.is_ok());
+        assert!(comparison.chains[1].chain.is_none());
+        assert!(matches!(
+            comparison.chains[1].result,
+            Err(ForkCallError::Rpc(_))
+        ));
+        assert!(!comparison.agrees());
+        let output = comparison.to_string();
+        assert!(
+            output.contains(&format!("{}\terror: ", dead.fork.fork_url)),
+            "got: {output}"
+        );
+    }
+}
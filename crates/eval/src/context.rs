@@ -0,0 +1,509 @@
+use crate::float::{DecimalFloat, FloatError};
+use alloy::primitives::{Address, B256, Bytes, Signature, U256, keccak256};
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// Longest string that fits in a single context word, as the first byte of
+/// the word holds the length.
+const MAX_STRING_LENGTH: usize = 31;
+
+/// Errors that can occur when building a context matrix.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ContextError {
+    #[error("String {0:?} is longer than {MAX_STRING_LENGTH} bytes")]
+    StringTooLong(String),
+    #[error("Duplicate context column name {0}")]
+    DuplicateColumn(String),
+    #[error("Invalid signature for signed context {0}")]
+    InvalidSignature(usize),
+    #[error(transparent)]
+    Float(#[from] FloatError),
+}
+
+/// A single context value and how it is encoded into a word.
+///
+/// In JSON a value is an object with a single key naming its type, such as
+/// `{ "address": "0x..." }` or `{ "float": "1.5" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContextValue {
+    /// A raw word, as decimal or 0x-prefixed hex.
+    Word(U256),
+    /// An address, right aligned in the word.
+    Address(Address),
+    /// A Rain decimal float.
+    Float(DecimalFloat),
+    /// A string of up to 31 bytes, encoded the way Rainlang string literals
+    /// are, with the length in the first byte followed by the bytes.
+    String(String),
+}
+
+impl ContextValue {
+    /// Encodes the value into a context word.
+    pub fn to_word(&self) -> Result<U256, ContextError> {
+        match self {
+            ContextValue::Word(word) => Ok(*word),
+            ContextValue::Address(address) => Ok(U256::from_be_slice(address.as_slice())),
+            ContextValue::Float(float) => Ok(float.to_word()?),
+            ContextValue::String(string) => {
+                let bytes = string.as_bytes();
+                if bytes.len() > MAX_STRING_LENGTH {
+                    return Err(ContextError::StringTooLong(string.clone()));
+                }
+                let mut word = [0u8; 32];
+                word[0] = bytes.len() as u8;
+                word[1..=bytes.len()].copy_from_slice(bytes);
+                Ok(U256::from_be_bytes(word))
+            }
+        }
+    }
+}
+
+impl From<U256> for ContextValue {
+    fn from(word: U256) -> Self {
+        ContextValue::Word(word)
+    }
+}
+
+impl From<Address> for ContextValue {
+    fn from(address: Address) -> Self {
+        ContextValue::Address(address)
+    }
+}
+
+impl From<DecimalFloat> for ContextValue {
+    fn from(float: DecimalFloat) -> Self {
+        ContextValue::Float(float)
+    }
+}
+
+/// The base column that calling contracts conventionally put at index 0 of
+/// the context, read in Rainlang as `context<0 0>()` and `context<0 1>()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaseContext {
+    /// The caller of the calling contract, `msg.sender`.
+    pub sender: Address,
+    /// The contract running the expression, `address(this)`.
+    pub calling_contract: Address,
+}
+
+/// A context column, optionally named so that its index can be looked up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextColumn {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub values: Vec<ContextValue>,
+}
+
+/// Context rows signed by a signer, as calling contracts receive them in
+/// `SignedContextV1`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedContext {
+    pub signer: Address,
+    pub context: Vec<U256>,
+    /// 65 byte ECDSA signature of the EIP-191 message of
+    /// [`SignedContext::hash`].
+    pub signature: Bytes,
+}
+
+impl SignedContext {
+    /// Signs the rows with a local private key.
+    pub fn sign(
+        signer: &PrivateKeySigner,
+        context: Vec<U256>,
+    ) -> Result<Self, alloy::signers::Error> {
+        let signature = signer.sign_message_sync(Self::hash(&context).as_slice())?;
+        Ok(SignedContext {
+            signer: signer.address(),
+            context,
+            signature: Bytes::copy_from_slice(&signature.as_bytes()),
+        })
+    }
+
+    /// Hash of the rows that is signed, the keccak256 of the packed words.
+    pub fn hash(context: &[U256]) -> B256 {
+        let words: Vec<u8> = context
+            .iter()
+            .flat_map(|word| word.to_be_bytes::<32>())
+            .collect();
+        keccak256(words)
+    }
+
+    /// Whether the signature recovers to the signer. Only ECDSA signatures
+    /// are supported, so contracts signing through ERC-1271 never verify.
+    pub fn verify(&self) -> bool {
+        Signature::try_from(self.signature.as_ref())
+            .ok()
+            .and_then(|signature| {
+                signature
+                    .recover_address_from_msg(Self::hash(&self.context))
+                    .ok()
+            })
+            .is_some_and(|recovered| recovered == self.signer)
+    }
+}
+
+/// Builds the context matrix of an eval from typed values.
+///
+/// Columns are laid out the way calling contracts build the context. When a
+/// base column is set it comes first, followed by the other columns in the
+/// order they were added. Signed contexts come last, as a column of their
+/// signers followed by the rows of each signed context as its own column.
+///
+/// Also deserializes from JSON, for example `{ "base": { "sender": "0x...",
+/// "callingContract": "0x..." }, "columns": [{ "name": "prices", "values":
+/// [{ "float": "1.5" }] }], "signedContexts": [{ "signer": "0x...",
+/// "context": ["0x01"], "signature": "0x..." }] }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextBuilder {
+    #[serde(default)]
+    pub base: Option<BaseContext>,
+    #[serde(default)]
+    pub columns: Vec<ContextColumn>,
+    #[serde(default)]
+    pub signed_contexts: Vec<SignedContext>,
+}
+
+impl ContextBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the base column from the sender and the calling contract.
+    pub fn base(mut self, sender: Address, calling_contract: Address) -> Self {
+        self.base = Some(BaseContext {
+            sender,
+            calling_contract,
+        });
+        self
+    }
+
+    /// Appends an unnamed column.
+    pub fn column(mut self, values: impl IntoIterator<Item = ContextValue>) -> Self {
+        self.columns.push(ContextColumn {
+            name: None,
+            values: values.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Appends a named column.
+    pub fn named_column(
+        mut self,
+        name: impl Into<String>,
+        values: impl IntoIterator<Item = ContextValue>,
+    ) -> Self {
+        self.columns.push(ContextColumn {
+            name: Some(name.into()),
+            values: values.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Appends a signed context.
+    pub fn signed_context(mut self, signed_context: SignedContext) -> Self {
+        self.signed_contexts.push(signed_context);
+        self
+    }
+
+    /// Index in the built context of the column with the given name.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        let offset = usize::from(self.base.is_some());
+        self.columns
+            .iter()
+            .position(|column| column.name.as_deref() == Some(name))
+            .map(|index| index + offset)
+    }
+
+    /// Encodes every column into the context matrix, rejecting signed
+    /// contexts whose signature doesn't verify, as calling contracts do.
+    pub fn build(&self) -> Result<Vec<Vec<U256>>, ContextError> {
+        let mut names = HashSet::new();
+        for name in self
+            .columns
+            .iter()
+            .filter_map(|column| column.name.as_ref())
+        {
+            if !names.insert(name) {
+                return Err(ContextError::DuplicateColumn(name.clone()));
+            }
+        }
+
+        let mut context = Vec::with_capacity(self.columns.len() + 1);
+        if let Some(base) = &self.base {
+            context.push(vec![
+                ContextValue::Address(base.sender).to_word()?,
+                ContextValue::Address(base.calling_contract).to_word()?,
+            ]);
+        }
+        for column in &self.columns {
+            context.push(
+                column
+                    .values
+                    .iter()
+                    .map(ContextValue::to_word)
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+
+        if !self.signed_contexts.is_empty() {
+            if let Some(index) = self.signed_contexts.iter().position(|s| !s.verify()) {
+                return Err(ContextError::InvalidSignature(index));
+            }
+            context.push(
+                self.signed_contexts
+                    .iter()
+                    .map(|signed_context| ContextValue::Address(signed_context.signer).to_word())
+                    .collect::<Result<_, _>>()?,
+            );
+            context.extend(
+                self.signed_contexts
+                    .iter()
+                    .map(|signed_context| signed_context.context.clone()),
+            );
+        }
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::ForkEvalArgs;
+    use crate::fork::{Forker, NewForkedEvm};
+    use crate::trace::RainEvalOutcome;
+    use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    #[test]
+    fn test_to_word() {
+        assert_eq!(
+            ContextValue::Word(U256::from(7)).to_word().unwrap(),
+            U256::from(7)
+        );
+        assert_eq!(
+            ContextValue::Address(Address::with_last_byte(0xab))
+                .to_word()
+                .unwrap(),
+            U256::from(0xab)
+        );
+        assert_eq!(
+            ContextValue::Float("3".parse().unwrap()).to_word().unwrap(),
+            U256::from(3)
+        );
+
+        let word = ContextValue::String("abc".into()).to_word().unwrap();
+        let bytes = word.to_be_bytes::<32>();
+        assert_eq!(bytes[..4], [3, b'a', b'b', b'c']);
+        assert!(bytes[4..].iter().all(|b| *b == 0));
+
+        assert_eq!(
+            ContextValue::String("a".repeat(32)).to_word(),
+            Err(ContextError::StringTooLong("a".repeat(32)))
+        );
+        assert!(ContextValue::String("a".repeat(31)).to_word().is_ok());
+    }
+
+    #[test]
+    fn test_build() {
+        let sender = Address::repeat_byte(0x1);
+        let calling_contract = Address::repeat_byte(0x2);
+        let builder = ContextBuilder::new()
+            .base(sender, calling_contract)
+            .named_column("amounts", [U256::from(1).into(), U256::from(2).into()])
+            .column([Address::repeat_byte(0x3).into()]);
+
+        assert_eq!(builder.column_index("amounts"), Some(1));
+        assert_eq!(builder.column_index("missing"), None);
+        assert_eq!(
+            builder.build().unwrap(),
+            vec![
+                vec![
+                    U256::from_be_slice(sender.as_slice()),
+                    U256::from_be_slice(calling_contract.as_slice())
+                ],
+                vec![U256::from(1), U256::from(2)],
+                vec![U256::from_be_slice(Address::repeat_byte(0x3).as_slice())],
+            ]
+        );
+
+        let builder = ContextBuilder::new().named_column("amounts", []);
+        assert_eq!(builder.column_index("amounts"), Some(0));
+        assert_eq!(builder.build().unwrap(), vec![Vec::<U256>::new()]);
+    }
+
+    #[test]
+    fn test_build_duplicate_column() {
+        let builder = ContextBuilder::new()
+            .named_column("a", [])
+            .column([])
+            .named_column("a", []);
+        assert_eq!(
+            builder.build(),
+            Err(ContextError::DuplicateColumn("a".into()))
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let builder: ContextBuilder = serde_json::from_str(
+            r#"{
+                "base": {
+                    "sender": "0x0101010101010101010101010101010101010101",
+                    "callingContract": "0x0202020202020202020202020202020202020202"
+                },
+                "columns": [
+                    {
+                        "name": "values",
+                        "values": [
+                            { "word": "0x10" },
+                            { "address": "0x0303030303030303030303030303030303030303" },
+                            { "float": "1.5" },
+                            { "string": "hi" }
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            builder,
+            ContextBuilder::new()
+                .base(Address::repeat_byte(0x1), Address::repeat_byte(0x2))
+                .named_column(
+                    "values",
+                    [
+                        U256::from(0x10).into(),
+                        Address::repeat_byte(0x3).into(),
+                        "1.5".parse::<DecimalFloat>().unwrap().into(),
+                        ContextValue::String("hi".into()),
+                    ]
+                )
+        );
+    }
+
+    fn key() -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&B256::repeat_byte(0x11)).unwrap()
+    }
+
+    #[test]
+    fn test_signed_context() {
+        let signer = key();
+        let rows = vec![U256::from(1), U256::from(2)];
+        let signed = SignedContext::sign(&signer, rows.clone()).unwrap();
+        assert_eq!(signed.signer, signer.address());
+        assert_eq!(signed.signature.len(), 65);
+        assert!(signed.verify());
+
+        let mut words = [0u8; 64];
+        words[31] = 1;
+        words[63] = 2;
+        assert_eq!(SignedContext::hash(&rows), keccak256(words));
+
+        let mut tampered = signed.clone();
+        tampered.context[0] = U256::from(3);
+        assert!(!tampered.verify());
+
+        let mut wrong_signer = signed.clone();
+        wrong_signer.signer = Address::repeat_byte(0x1);
+        assert!(!wrong_signer.verify());
+
+        let mut malformed = signed;
+        malformed.signature = Bytes::from(vec![0u8; 3]);
+        assert!(!malformed.verify());
+    }
+
+    #[test]
+    fn test_build_signed_contexts() {
+        let signer = key();
+        let first = SignedContext::sign(&signer, vec![U256::from(1)]).unwrap();
+        let second = SignedContext::sign(&signer, vec![U256::from(2), U256::from(3)]).unwrap();
+        let builder = ContextBuilder::new()
+            .base(Address::repeat_byte(0x1), Address::repeat_byte(0x2))
+            .column([U256::from(9).into()])
+            .signed_context(first)
+            .signed_context(second.clone());
+
+        let context = builder.build().unwrap();
+        let signer_word = U256::from_be_slice(signer.address().as_slice());
+        assert_eq!(context.len(), 5);
+        assert_eq!(context[1], vec![U256::from(9)]);
+        assert_eq!(context[2], vec![signer_word, signer_word]);
+        assert_eq!(context[3], vec![U256::from(1)]);
+        assert_eq!(context[4], vec![U256::from(2), U256::from(3)]);
+
+        let mut invalid = second;
+        invalid.context.push(U256::from(4));
+        assert_eq!(
+            builder.signed_context(invalid).build(),
+            Err(ContextError::InvalidSignature(2))
+        );
+
+        let json = serde_json::to_string(
+            &ContextBuilder::new()
+                .signed_context(SignedContext::sign(&signer, vec![U256::from(5)]).unwrap()),
+        )
+        .unwrap();
+        let builder: ContextBuilder = serde_json::from_str(&json).unwrap();
+        assert_eq!(builder.build().unwrap()[1], vec![U256::from(5)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_built_context() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let sender = Address::repeat_byte(0x1);
+        let builder = ContextBuilder::new()
+            .base(sender, Address::repeat_byte(0x2))
+            .named_column(
+                "reasons",
+                [
+                    ContextValue::Float("2.5".parse().unwrap()),
+                    ContextValue::String("from context".into()),
+                ],
+            );
+        let result = fork
+            .fork_eval_result(ForkEvalArgs {
+                rainlang_string:
+                    r"sender: context<0 0>(), amount: context<1 0>(), :ensure(0 context<1 1>());"
+                        .into(),
+                source_index: 0,
+                rainlang: local_evm.rainlang,
+                namespace: FullyQualifiedNamespace::default(),
+                context: builder.build().unwrap(),
+                decode_errors: false,
+                inputs: vec![],
+                state_overlay: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
+            })
+            .await
+            .unwrap();
+
+        // The string column value is decoded back as the ensure reason.
+        assert_eq!(
+            result.outcome,
+            RainEvalOutcome::EnsureFailed {
+                reason: "from context".into(),
+                source_index: 0,
+            }
+        );
+    }
+}
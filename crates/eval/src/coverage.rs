@@ -0,0 +1,446 @@
+use crate::bytecode::{BytecodeError, OP_SIZE, SOURCE_HEADER_SIZE, integrity_reports};
+use crate::trace::RainEvalResult;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Number of times each opcode was dispatched during one eval, keyed by its
+/// index in the interpreter's function pointers.
+pub type OpcodeHits = BTreeMap<u8, u64>;
+
+/// Counts the dispatches of each opcode from the program counters of the
+/// interpreter's execution steps.
+///
+/// `function_pointers` are the 2 byte code offsets returned by the
+/// interpreter's `buildOpcodeFunctionPointers`. Every dispatch jumps to the
+/// pointer of its opcode, and opcodes don't jump to each other's entry
+/// points, so each step at a pointer is one dispatch of that opcode.
+pub fn count_opcode_hits(
+    function_pointers: &[u8],
+    pcs: impl IntoIterator<Item = usize>,
+) -> OpcodeHits {
+    let opcodes: BTreeMap<usize, u8> = function_pointers
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(opcode, pointer)| {
+            let pc = u16::from_be_bytes([pointer[0], pointer[1]]) as usize;
+            (pc, opcode as u8)
+        })
+        .collect();
+
+    let mut hits = OpcodeHits::new();
+    for pc in pcs {
+        if let Some(opcode) = opcodes.get(&pc) {
+            *hits.entry(*opcode).or_default() += 1;
+        }
+    }
+    hits
+}
+
+/// Dispatch count of one opcode across every recorded eval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpcodeCoverage {
+    /// Index of the opcode in the interpreter's function pointers.
+    pub opcode: u8,
+    /// Number of ops in the bytecode with this opcode.
+    pub ops: usize,
+    pub hits: u64,
+}
+
+/// Coverage of a single source across every recorded eval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceCoverage {
+    pub source_index: usize,
+    /// Number of evals that entered the source.
+    pub entered: u64,
+    /// Number of evals in which the source ran to completion.
+    pub completed: u64,
+    /// Opcodes of the ops in the source, in bytecode order.
+    pub opcodes: Vec<u8>,
+}
+
+/// Aggregated coverage of an expression: how often each source was entered
+/// and completed, and how often each of its opcodes was dispatched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageReport {
+    pub runs: u64,
+    pub sources: Vec<SourceCoverage>,
+    pub sources_found: usize,
+    pub sources_hit: usize,
+    pub opcodes: Vec<OpcodeCoverage>,
+    pub opcodes_found: usize,
+    pub opcodes_hit: usize,
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "runs: {}", self.runs)?;
+        writeln!(
+            f,
+            "sources: {}/{} entered, opcodes: {}/{} dispatched",
+            self.sources_hit, self.sources_found, self.opcodes_hit, self.opcodes_found
+        )?;
+        for source in &self.sources {
+            if source.entered == 0 {
+                writeln!(f, "source {}: not entered", source.source_index)?;
+                continue;
+            }
+            writeln!(
+                f,
+                "source {}: entered {}, completed {}, ops {}",
+                source.source_index,
+                source.entered,
+                source.completed,
+                source.opcodes.len()
+            )?;
+        }
+        for opcode in &self.opcodes {
+            writeln!(
+                f,
+                "opcode {}: ops {}, hits {}",
+                opcode.opcode, opcode.ops, opcode.hits
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects source and opcode coverage of one expression from many evals.
+///
+/// Sources are entered and completed according to the traces of each eval,
+/// as only sources that run to completion emit a trace. Opcode hits are the
+/// dispatches measured by [`Forker::fork_eval_coverage`], which can't tell
+/// apart two ops with the same opcode, so they are reported per opcode
+/// rather than per op. Rainlang evaluates the inputs of `if` and
+/// `conditions` eagerly, so branches only show up as coverage when their
+/// arms are separate sources reached with `call`.
+///
+/// [`Forker::fork_eval_coverage`]: crate::fork::Forker::fork_eval_coverage
+#[derive(Debug, Clone)]
+pub struct CoverageCollector {
+    runs: u64,
+    sources: Vec<SourceCoverage>,
+    hits: OpcodeHits,
+}
+
+impl CoverageCollector {
+    /// Creates a collector for the sources and ops of the given bytecode, as
+    /// found inside a serialized expression.
+    pub fn new(bytecode: &[u8]) -> Result<Self, BytecodeError> {
+        let reports = integrity_reports(bytecode)?;
+        let sources_start = 1 + reports.len() * 2;
+
+        let sources = reports
+            .iter()
+            .map(|report| {
+                let i = report.source_index;
+                let offset = u16::from_be_bytes([bytecode[1 + i * 2], bytecode[2 + i * 2]]);
+                let ops_start = sources_start + offset as usize + SOURCE_HEADER_SIZE;
+                SourceCoverage {
+                    source_index: i,
+                    entered: 0,
+                    completed: 0,
+                    opcodes: (0..report.ops_count as usize)
+                        .map(|op_index| bytecode[ops_start + op_index * OP_SIZE])
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            runs: 0,
+            sources,
+            hits: OpcodeHits::new(),
+        })
+    }
+
+    /// Records one eval that started at `source_index`, along with the
+    /// opcode dispatches measured during it.
+    ///
+    /// A source is entered if it emitted a trace, called a source that did,
+    /// or is the entry source. Sources missing from the bytecode are ignored.
+    pub fn record(&mut self, result: &RainEvalResult, hits: &OpcodeHits, source_index: u16) {
+        self.runs += 1;
+
+        let mut entered = HashSet::from([source_index]);
+        for trace in &result.traces {
+            entered.insert(trace.parent_source_index);
+            entered.insert(trace.source_index);
+            if let Some(source) = self.sources.get_mut(trace.source_index as usize) {
+                source.completed += 1;
+            }
+        }
+        for index in entered {
+            if let Some(source) = self.sources.get_mut(index as usize) {
+                source.entered += 1;
+            }
+        }
+        for (opcode, count) in hits {
+            *self.hits.entry(*opcode).or_default() += count;
+        }
+    }
+
+    /// Builds the report of everything recorded so far. Only opcodes used
+    /// by the bytecode are reported.
+    pub fn report(&self) -> CoverageReport {
+        let mut ops = BTreeMap::<u8, usize>::new();
+        for opcode in self.sources.iter().flat_map(|s| &s.opcodes) {
+            *ops.entry(*opcode).or_default() += 1;
+        }
+        let opcodes: Vec<OpcodeCoverage> = ops
+            .into_iter()
+            .map(|(opcode, ops)| OpcodeCoverage {
+                opcode,
+                ops,
+                hits: self.hits.get(&opcode).copied().unwrap_or_default(),
+            })
+            .collect();
+
+        CoverageReport {
+            runs: self.runs,
+            sources: self.sources.clone(),
+            sources_found: self.sources.len(),
+            sources_hit: self.sources.iter().filter(|s| s.entered > 0).count(),
+            opcodes_found: opcodes.len(),
+            opcodes_hit: opcodes.iter().filter(|o| o.hits > 0).count(),
+            opcodes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::SerializedExpression;
+    use crate::eval::{ForkEvalArgs, ForkParseArgs};
+    use crate::fork::{Forker, NewForkedEvm};
+    use crate::trace::{RainEvalOutcome, RainSourceTrace};
+    use alloy::hex;
+    use alloy::primitives::U256;
+    use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    fn result(traces: Vec<RainSourceTrace>) -> RainEvalResult {
+        RainEvalResult {
+            reverted: false,
+            stack: vec![],
+            writes: vec![],
+            traces,
+            error: None,
+            outcome: RainEvalOutcome::Success,
+        }
+    }
+
+    fn trace(parent_source_index: u16, source_index: u16) -> RainSourceTrace {
+        RainSourceTrace {
+            parent_source_index,
+            source_index,
+            stack: vec![],
+        }
+    }
+
+    // Source 0 has no ops, source 1 has one op with opcode 0xaa, source 2
+    // has two ops with opcodes 0x01 and 0x02.
+    fn bytecode() -> Vec<u8> {
+        hex::decode("0x0300000004000c0000000001030201aabbccdd020100010100000002000000").unwrap()
+    }
+
+    fn hits(hits: &[(u8, u64)]) -> OpcodeHits {
+        hits.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_count_opcode_hits() {
+        // Opcode 0 at pc 0x0010, opcode 1 at 0x0123, opcode 2 at 0x0010.
+        let function_pointers = hex::decode("0x001001230010").unwrap();
+        let pcs = [0x10, 0x11, 0x123, 0x10, 0x5, 0x123, 0x123];
+        // A pointer shared by two opcodes counts for the last of them.
+        assert_eq!(
+            count_opcode_hits(&function_pointers, pcs),
+            hits(&[(1, 3), (2, 2)])
+        );
+        assert!(count_opcode_hits(&function_pointers, []).is_empty());
+        // A trailing odd byte isn't a pointer.
+        assert!(count_opcode_hits(&[0x00], [0]).is_empty());
+    }
+
+    #[test]
+    fn test_new() {
+        let report = CoverageCollector::new(&bytecode()).unwrap().report();
+        assert_eq!(report.runs, 0);
+        assert_eq!(report.sources_found, 3);
+        assert_eq!(report.sources_hit, 0);
+        assert!(report.sources[0].opcodes.is_empty());
+        assert_eq!(report.sources[1].opcodes, vec![0xaa]);
+        assert_eq!(report.sources[2].opcodes, vec![0x01, 0x02]);
+        assert_eq!(report.opcodes_found, 3);
+        assert_eq!(report.opcodes_hit, 0);
+    }
+
+    #[test]
+    fn test_new_invalid_bytecode() {
+        assert_eq!(
+            CoverageCollector::new(&[]).unwrap_err(),
+            BytecodeError::EmptyBytecode
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let mut collector = CoverageCollector::new(&bytecode()).unwrap();
+        collector.record(
+            &result(vec![trace(0, 0), trace(0, 1)]),
+            &hits(&[(0xaa, 1)]),
+            0,
+        );
+        // Reverted in source 0 after source 1 completed.
+        collector.record(&result(vec![trace(0, 1)]), &hits(&[(0xaa, 2)]), 0);
+        // Trace of a source the bytecode doesn't have, and an opcode it
+        // doesn't use.
+        collector.record(&result(vec![trace(0, 9)]), &hits(&[(0x07, 1)]), 0);
+
+        let report = collector.report();
+        assert_eq!(report.runs, 3);
+        assert_eq!(report.sources[0].entered, 3);
+        assert_eq!(report.sources[0].completed, 1);
+        assert_eq!(report.sources[1].entered, 2);
+        assert_eq!(report.sources[1].completed, 2);
+        assert_eq!(report.sources[2].entered, 0);
+        assert_eq!(report.sources_hit, 2);
+        assert_eq!(
+            report.opcodes,
+            vec![
+                OpcodeCoverage {
+                    opcode: 0x01,
+                    ops: 1,
+                    hits: 0
+                },
+                OpcodeCoverage {
+                    opcode: 0x02,
+                    ops: 1,
+                    hits: 0
+                },
+                OpcodeCoverage {
+                    opcode: 0xaa,
+                    ops: 1,
+                    hits: 3
+                },
+            ]
+        );
+        assert_eq!(report.opcodes_found, 3);
+        assert_eq!(report.opcodes_hit, 1);
+    }
+
+    #[test]
+    fn test_report_display() {
+        let mut collector = CoverageCollector::new(&bytecode()).unwrap();
+        collector.record(
+            &result(vec![trace(0, 0), trace(0, 1)]),
+            &hits(&[(0xaa, 1)]),
+            0,
+        );
+
+        assert_eq!(
+            collector.report().to_string(),
+            "runs: 1\n\
+             sources: 2/3 entered, opcodes: 1/3 dispatched\n\
+             source 0: entered 1, completed 1, ops 0\n\
+             source 1: entered 1, completed 1, ops 1\n\
+             source 2: not entered\n\
+             opcode 1: ops 1, hits 0\n\
+             opcode 2: ops 1, hits 0\n\
+             opcode 170: ops 1, hits 1\n"
+        );
+    }
+
+    #[test]
+    fn test_report_json() {
+        let mut collector = CoverageCollector::new(&bytecode()).unwrap();
+        collector.record(&result(vec![trace(1, 1)]), &hits(&[(0xaa, 1)]), 1);
+
+        let json = serde_json::to_value(collector.report()).unwrap();
+        assert_eq!(json["runs"], 1);
+        assert_eq!(json["sourcesHit"], 1);
+        assert_eq!(json["opcodesFound"], 3);
+        assert_eq!(json["opcodesHit"], 1);
+        assert_eq!(json["sources"][1]["sourceIndex"], 1);
+        assert_eq!(json["sources"][1]["opcodes"][0], 0xaa);
+        assert_eq!(json["opcodes"][2]["opcode"], 0xaa);
+        assert_eq!(json["opcodes"][2]["hits"], 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_coverage() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let rainlang_string = r#"
+        _: call<1>(context<0 0>());
+        a:, :ensure(a "zero");
+        _: block-number();
+        "#;
+        let parsed = fork
+            .fork_parse(ForkParseArgs {
+                rainlang_string: rainlang_string.into(),
+                rainlang: local_evm.rainlang,
+                decode_errors: false,
+                dispair: None,
+            })
+            .await
+            .unwrap();
+        let expression = SerializedExpression::deserialize(&parsed.typed_return).unwrap();
+        let mut collector = CoverageCollector::new(&expression.bytecode).unwrap();
+
+        for condition in [1u8, 0u8] {
+            let (result, hits) = fork
+                .fork_eval_coverage(ForkEvalArgs {
+                    rainlang_string: rainlang_string.into(),
+                    source_index: 0,
+                    rainlang: local_evm.rainlang,
+                    namespace: FullyQualifiedNamespace::default(),
+                    context: vec![vec![U256::from(condition)]],
+                    decode_errors: false,
+                    state_overlay: vec![],
+                    inputs: vec![],
+                    bytecode: None,
+                    dispair: None,
+                    from: None,
+                    env: Default::default(),
+                })
+                .await
+                .unwrap();
+            collector.record(&result, &hits, 0);
+        }
+
+        let report = collector.report();
+        assert_eq!(report.runs, 2);
+        assert_eq!(report.sources_found, 3);
+        assert_eq!(report.sources[0].entered, 2);
+        assert_eq!(report.sources[0].completed, 1);
+        assert_eq!(report.sources[1].completed, 1);
+        // Nothing calls source 2.
+        assert_eq!(report.sources[2].entered, 0);
+        assert_eq!(report.sources_hit, 2);
+
+        // Every opcode of sources 0 and 1 is dispatched in both runs, the
+        // `ensure` even when it reverts, but source 2's `block-number` never.
+        let block_number = report.sources[2].opcodes[0];
+        for opcode in &report.opcodes {
+            if opcode.opcode == block_number {
+                assert_eq!(opcode.hits, 0);
+            } else {
+                assert!(opcode.hits >= 2, "{opcode:?}");
+            }
+        }
+        assert_eq!(report.opcodes_hit, report.opcodes_found - 1);
+    }
+}
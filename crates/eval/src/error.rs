@@ -1,7 +1,11 @@
+use crate::bytecode::BytecodeError;
+use crate::trace::RainEvalResultFromRawCallResultError;
+use alloy::primitives::U256;
 use alloy::primitives::ruint::FromUintError;
 #[cfg(not(target_family = "wasm"))]
 use foundry_evm::{backend::DatabaseError, executors::RawCallResult};
 use rain_error_decoding::{AbiDecodeFailedErrors, AbiDecodedErrorType};
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur when calling a forked EVM.
@@ -26,6 +30,56 @@ pub enum ForkCallError {
     Eyre(#[from] eyre::Report),
     #[error("Replay transaction error: {:#?}", .0)]
     ReplayTransactionError(#[from] ReplayTransactionError),
+    #[error(transparent)]
+    BytecodeError(#[from] BytecodeError),
+    #[error("No parser address provided")]
+    MissingParserAddress,
+    #[error("Explicit DISPaiR has no {0} address")]
+    IncompleteDISPaiR(&'static str),
+    #[error("Expected either a Rainlang string or bytecode, got both")]
+    RainlangAndBytecode,
+    #[error("Expected either a Rainlang string or bytecode, got neither")]
+    NoRainlangOrBytecode,
+    #[error(transparent)]
+    EvalResult(#[from] RainEvalResultFromRawCallResultError),
+    #[error("Ensure failed in source {source_index}: {reason}")]
+    EnsureFailed { reason: String, source_index: u16 },
+    #[error("No fork with id {0}")]
+    UnknownFork(U256),
+    #[error("Fork {0} is active and cannot be removed")]
+    RemoveActiveFork(U256),
+    #[error("Offline forks need a pinned block number")]
+    OfflineUnpinned,
+    #[error("No cached fork state at {0}, run once online to fill the cache")]
+    OfflineCacheMiss(String),
+    #[error("Offline fork state {1} has no {0}, run once online to fill the cache")]
+    OfflineStateMiss(String, String),
+    #[error("RPC cache {0}: {1}")]
+    RpcCache(String, String),
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+    #[error("Call timed out after {0:?}")]
+    Timeout(Duration),
+}
+
+/// Failures of the RPC a fork is created from or fetches its state from, as
+/// opposed to failures of the EVM running a call.
+#[derive(Debug, Error)]
+pub enum RpcError {
+    #[error("Failed to fork from {url}: {message}")]
+    Connect { url: String, message: String },
+    #[error("Failed to fork from any RPC url: {}", join_errors(.0))]
+    AllUrlsFailed(Vec<RpcError>),
+    #[error("Failed to fetch fork state: {0}")]
+    Fetch(String),
+}
+
+fn join_errors(errors: &[RpcError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 /// Errors specific to replaying a historical transaction.
@@ -1,45 +1,122 @@
+use crate::bytecode::{IntegrityReport, SerializedExpression, integrity_reports};
+use crate::cache::ParseCacheKey;
+use crate::cancel::Cancel;
+use crate::coverage::{OpcodeHits, count_opcode_hits};
 use crate::error::ForkCallError;
-use crate::fork::{ForkTypedReturn, Forker};
-use alloy::primitives::{Address, U256};
+use crate::fork::{EnvOverrides, ForkTypedReturn, Forker, decode_revert};
+use crate::trace::{RainEvalOutcome, RainEvalResult, revert_raised_by};
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use foundry_evm::executors::RawCallResult;
+use foundry_evm::traces::TraceMode;
+use rain_error_decoding::AbiDecodedErrorType;
 use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
 use rain_interpreter_bindings::IInterpreterV4::{EvalV4, eval4Call};
 use rain_interpreter_bindings::IParserV2::parse2Call;
 use rain_interpreter_bindings::Rainlang::{
-    expressionDeployerAddressCall, interpreterAddressCall, storeAddressCall,
+    expressionDeployerAddressCall, interpreterAddressCall, parserAddressCall, storeAddressCall,
 };
+use rain_interpreter_bindings::RainterpreterParser::unsafeParseCall;
+use rain_interpreter_dispair::DISPaiR;
+use revm::interpreter::InstructionResult;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+sol! {
+    interface IOpcodeToolingV1 {
+        function buildOpcodeFunctionPointers() external view returns (bytes memory);
+    }
+}
+
+use IOpcodeToolingV1::buildOpcodeFunctionPointersCall;
 
 /// Arguments for evaluating a Rainlang string in a forked EVM context
-#[derive(Debug, Clone)]
+///
+/// Exactly one of `rainlang_string` and `bytecode` must be set. In JSON every
+/// field may be left out for its default, so a body needs `rainlangString` or
+/// `bytecode`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ForkEvalArgs {
-    /// The Rainlang string to evaluate
+    /// The Rainlang string to evaluate. Empty when `bytecode` is set.
+    #[serde(default)]
     pub rainlang_string: String,
     /// The source index of the rainlang to evaluate
+    #[serde(default)]
     pub source_index: u16,
     /// The address of the Rainlang contract. All component addresses
     /// (deployer, interpreter, store, parser) are discovered from this
     /// address.
+    #[serde(default)]
     pub rainlang: Address,
     /// The fully qualified namespace
+    #[serde(
+        default,
+        serialize_with = "serialize_namespace",
+        deserialize_with = "deserialize_namespace"
+    )]
     pub namespace: FullyQualifiedNamespace,
     /// The context matrix, that will be available in "context" word and its aliases
+    #[serde(default)]
     pub context: Vec<Vec<U256>>,
     /// Whether to decode errors
+    #[serde(default)]
     pub decode_errors: bool,
     /// Inputs vector which are prepopulated stack items
+    #[serde(default)]
     pub inputs: Vec<U256>,
     /// Applies to the state before evaluation to facilitate "what if" analysis
+    #[serde(default)]
     pub state_overlay: Vec<U256>,
+    /// Pre-parsed serialized bytecode, as returned by `parse2`. When set it
+    /// is evaluated as-is and `rainlang_string` must be empty.
+    #[serde(default)]
+    pub bytecode: Option<Bytes>,
+    /// Explicit component addresses, for deployments without a Rainlang
+    /// contract. When set `rainlang` is ignored.
+    #[serde(default)]
+    pub dispair: Option<DISPaiR>,
+    /// The caller of `eval4`. Defaults to the zero address.
+    #[serde(default)]
+    pub from: Option<Address>,
+    /// Block and chain environment seen by the expression.
+    #[serde(default)]
+    pub env: EnvOverrides,
 }
 
 /// Arguments for parsing a Rainlang string in a forked EVM context
-#[derive(Debug, Clone)]
+///
+/// In JSON every field but `rainlangString` may be left out for its default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ForkParseArgs {
     /// The Rainlang string to parse
     pub rainlang_string: String,
     /// The address of the Rainlang contract.
+    #[serde(default)]
     pub rainlang: Address,
     /// Whether to decode errors
+    #[serde(default)]
     pub decode_errors: bool,
+    /// Explicit component addresses, for deployments without a Rainlang
+    /// contract. When set `rainlang` is ignored.
+    #[serde(default)]
+    pub dispair: Option<DISPaiR>,
+}
+
+/// Serializes a namespace as the word it wraps.
+fn serialize_namespace<S: Serializer>(
+    namespace: &FullyQualifiedNamespace,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    namespace.into_underlying().serialize(serializer)
+}
+
+/// Deserializes a namespace from the word it wraps.
+fn deserialize_namespace<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<FullyQualifiedNamespace, D::Error> {
+    U256::deserialize(deserializer).map(FullyQualifiedNamespace::from)
 }
 
 impl From<ForkEvalArgs> for ForkParseArgs {
@@ -48,11 +125,95 @@ impl From<ForkEvalArgs> for ForkParseArgs {
             rainlang_string: args.rainlang_string,
             rainlang: args.rainlang,
             decode_errors: args.decode_errors,
+            dispair: args.dispair,
         }
     }
 }
 
+/// Checks that an explicit DISPaiR has the interpreter and store that
+/// evaluation calls, rather than letting `eval4` go to the zero address.
+pub fn check_eval_dispair(dispair: &DISPaiR) -> Result<(), ForkCallError> {
+    if dispair.interpreter == Address::ZERO {
+        return Err(ForkCallError::IncompleteDISPaiR("interpreter"));
+    }
+    if dispair.store == Address::ZERO {
+        return Err(ForkCallError::IncompleteDISPaiR("store"));
+    }
+    Ok(())
+}
+
 impl Forker {
+    /// Resolves the DISPaiR of a Rainlang contract on the active fork.
+    ///
+    /// The result is memoized per Rainlang address and fork, so only the
+    /// first call for a given pair hits the Rainlang contract.
+    pub async fn resolve_dispair(
+        &self,
+        rainlang: Address,
+        decode_errors: bool,
+    ) -> Result<DISPaiR, ForkCallError> {
+        let result = self
+            .run_blocking(move |forker, cancel| forker.resolve_dispair_blocking(rainlang, cancel))
+            .await;
+        decode_revert(result, decode_errors).await
+    }
+
+    /// Resolves the DISPaiR like [`Forker::resolve_dispair`], but on the
+    /// current thread and without decoding reverts.
+    fn resolve_dispair_blocking(
+        &self,
+        rainlang: Address,
+        cancel: &Cancel<'_>,
+    ) -> Result<DISPaiR, ForkCallError> {
+        let fork = self.cache_fork_block();
+        if let Some(dispair) = self.cache().dispair(rainlang, fork) {
+            return Ok(dispair);
+        }
+
+        let from = Address::default();
+        let deployer = self
+            .typed_call(from, rainlang, &expressionDeployerAddressCall {}, cancel)?
+            .typed_return;
+        let interpreter = self
+            .typed_call(from, rainlang, &interpreterAddressCall {}, cancel)?
+            .typed_return;
+        let store = self
+            .typed_call(from, rainlang, &storeAddressCall {}, cancel)?
+            .typed_return;
+        let parser = self
+            .typed_call(from, rainlang, &parserAddressCall {}, cancel)?
+            .typed_return;
+
+        let dispair = DISPaiR::new(deployer, interpreter, store, parser);
+        self.cache().insert_dispair(rainlang, fork, dispair.clone());
+        Ok(dispair)
+    }
+
+    /// Parses Rainlang string via the deployer, returning the serialized
+    /// bytecode from the parse cache when it has been parsed on the active
+    /// fork before.
+    fn cached_parse(
+        &self,
+        deployer: Address,
+        rainlang_string: &str,
+        cancel: &Cancel<'_>,
+    ) -> Result<Bytes, ForkCallError> {
+        let key = ParseCacheKey::new(deployer, rainlang_string, self.cache_fork_block());
+        if let Some(bytecode) = self.cache().parse(&key) {
+            return Ok(bytecode);
+        }
+
+        let parse_call = parse2Call {
+            data: rainlang_string.as_bytes().to_vec().into(),
+        };
+        let bytecode = self
+            .typed_call(Address::default(), deployer, &parse_call, cancel)?
+            .typed_return;
+
+        self.cache().insert_parse(key, bytecode.clone());
+        Ok(bytecode)
+    }
+
     /// Parses Rainlang string and returns the parsed result.
     ///
     /// Discovers the deployer address from Rainlang, then calls
@@ -60,108 +221,381 @@ impl Forker {
     pub async fn fork_parse(
         &self,
         args: ForkParseArgs,
+    ) -> Result<ForkTypedReturn<parse2Call>, ForkCallError> {
+        let decode_errors = args.decode_errors;
+        let result = self
+            .run_blocking(move |forker, cancel| forker.fork_parse_blocking(args, cancel))
+            .await;
+        decode_revert(result, decode_errors).await
+    }
+
+    /// Parses like [`Forker::fork_parse`], but on the current thread and
+    /// without decoding reverts.
+    fn fork_parse_blocking(
+        &self,
+        args: ForkParseArgs,
+        cancel: &Cancel<'_>,
     ) -> Result<ForkTypedReturn<parse2Call>, ForkCallError> {
         let ForkParseArgs {
             rainlang_string,
             rainlang,
-            decode_errors,
+            dispair,
+            ..
         } = args;
 
-        let deployer = self
-            .alloy_call(
-                Address::default(),
-                rainlang,
-                expressionDeployerAddressCall {},
-                decode_errors,
-            )
-            .await?
-            .typed_return;
+        let deployer = match dispair {
+            Some(dispair) if dispair.deployer == Address::ZERO => {
+                return Err(ForkCallError::IncompleteDISPaiR("deployer"));
+            }
+            Some(dispair) => dispair.deployer,
+            None => self.resolve_dispair_blocking(rainlang, cancel)?.deployer,
+        };
 
         let parse_call = parse2Call {
             data: rainlang_string.as_bytes().to_vec().into(),
         };
+        self.typed_call(Address::default(), deployer, &parse_call, cancel)
+    }
 
-        let parse_result = self
-            .alloy_call(Address::default(), deployer, parse_call, decode_errors)
-            .await?;
+    /// Parses Rainlang string and returns the integrity check's conclusions
+    /// (inputs, outputs and max stack height) for each source.
+    ///
+    /// The report is read from the source headers of the bytecode returned
+    /// by `parse2`, which the deployer only returns if they passed integrity.
+    pub async fn fork_parse_integrity_reports(
+        &self,
+        args: ForkParseArgs,
+    ) -> Result<Vec<IntegrityReport>, ForkCallError> {
+        let parse_result = self.fork_parse(args).await?;
+        let expression = SerializedExpression::deserialize(&parse_result.typed_return)?;
+        Ok(integrity_reports(&expression.bytecode)?)
+    }
+
+    /// Parses Rainlang string without the deployer's integrity check.
+    ///
+    /// Discovers the parser address from Rainlang, then calls `unsafeParse`
+    /// on it directly. The returned bytecode and constants are exactly what
+    /// the parser produced, even if the deployer would reject them, which
+    /// makes this useful for inspecting expressions that fail integrity.
+    pub async fn fork_unsafe_parse(
+        &self,
+        args: ForkParseArgs,
+    ) -> Result<ForkTypedReturn<unsafeParseCall>, ForkCallError> {
+        let decode_errors = args.decode_errors;
+        let result = self
+            .run_blocking(move |forker, cancel| forker.fork_unsafe_parse_blocking(args, cancel))
+            .await;
+        decode_revert(result, decode_errors).await
+    }
+
+    /// Parses like [`Forker::fork_unsafe_parse`], but on the current thread
+    /// and without decoding reverts.
+    fn fork_unsafe_parse_blocking(
+        &self,
+        args: ForkParseArgs,
+        cancel: &Cancel<'_>,
+    ) -> Result<ForkTypedReturn<unsafeParseCall>, ForkCallError> {
+        let ForkParseArgs {
+            rainlang_string,
+            rainlang,
+            dispair,
+            ..
+        } = args;
 
-        Ok(parse_result)
+        let parser = match dispair {
+            Some(dispair) => dispair.parser,
+            None => self.resolve_dispair_blocking(rainlang, cancel)?.parser,
+        };
+        if parser == Address::ZERO {
+            return Err(ForkCallError::MissingParserAddress);
+        }
+
+        let parse_call = unsafeParseCall {
+            data: rainlang_string.as_bytes().to_vec().into(),
+        };
+        self.typed_call(Address::default(), parser, &parse_call, cancel)
     }
 
     /// Evaluates the Rain language string and returns the evaluation result.
     ///
     /// Discovers all component addresses from Rainlang, parses the
     /// Rainlang string via the deployer, then evaluates via the interpreter.
+    /// If `bytecode` is provided the parse is skipped and it is evaluated
+    /// directly. Both the discovered addresses and the parse result are
+    /// cached for subsequent evals on the same fork.
     pub async fn fork_eval(
         &self,
         args: ForkEvalArgs,
     ) -> Result<ForkTypedReturn<eval4Call>, ForkCallError> {
-        let ForkEvalArgs {
-            rainlang_string,
-            source_index,
-            rainlang,
-            namespace,
-            context,
-            decode_errors,
-            inputs,
-            state_overlay,
-        } = args;
+        let decode_errors = args.decode_errors;
+        let result = self
+            .run_blocking(move |forker, cancel| forker.fork_eval_blocking(args, cancel))
+            .await;
+        Self::eval_typed_return(result, decode_errors).await
+    }
 
-        let deployer = self
-            .alloy_call(
-                Address::default(),
-                rainlang,
-                expressionDeployerAddressCall {},
-                decode_errors,
-            )
-            .await?
-            .typed_return;
-        let interpreter = self
-            .alloy_call(
-                Address::default(),
-                rainlang,
-                interpreterAddressCall {},
-                decode_errors,
-            )
-            .await?
-            .typed_return;
-        let store = self
-            .alloy_call(
-                Address::default(),
-                rainlang,
-                storeAddressCall {},
-                decode_errors,
-            )
-            .await?
+    /// Evaluates the Rain language string like [`Forker::fork_eval`], but a
+    /// revert is returned as a [`RainEvalResult`] rather than an error.
+    ///
+    /// A reverted result has `reverted` set, the traces of every source that
+    /// completed before the revert, and the revert data in `error`, decoded
+    /// via the selector registry when `decode_errors` is set. Reverts from
+    /// `ensure` are reported as [`RainEvalOutcome::EnsureFailed`].
+    pub async fn fork_eval_result(
+        &self,
+        args: ForkEvalArgs,
+    ) -> Result<RainEvalResult, ForkCallError> {
+        let decode_errors = args.decode_errors;
+        let source_index = args.source_index;
+        let result = self
+            .run_blocking(move |forker, cancel| forker.fork_eval_blocking(args, cancel))
+            .await;
+        Self::eval_result(result, source_index, decode_errors).await
+    }
+
+    /// Evaluates the Rain language string like [`Forker::fork_eval_result`],
+    /// also measuring how many times each opcode was dispatched.
+    ///
+    /// The eval runs with step tracing, so it is much slower than
+    /// [`Forker::fork_eval_result`]. Dispatches are counted from the steps
+    /// of the interpreter frame that land on one of the opcode function
+    /// pointers reported by the interpreter's `buildOpcodeFunctionPointers`.
+    pub async fn fork_eval_coverage(
+        &self,
+        args: ForkEvalArgs,
+    ) -> Result<(RainEvalResult, OpcodeHits), ForkCallError> {
+        let decode_errors = args.decode_errors;
+        let source_index = args.source_index;
+        let result = self
+            .run_blocking(move |forker, cancel| forker.fork_eval_coverage_blocking(args, cancel))
+            .await;
+        match result {
+            Ok((interpreter, raw, hits)) => {
+                let result =
+                    Self::rain_eval_result(raw, interpreter, source_index, decode_errors).await?;
+                Ok((result, hits))
+            }
+            Err(e) => decode_revert(Err(e), decode_errors).await,
+        }
+    }
+
+    /// Evaluates like [`Forker::fork_eval_coverage`], but on the current
+    /// thread, returning the interpreter, the raw `eval4` call result and the
+    /// opcode hits.
+    fn fork_eval_coverage_blocking(
+        &self,
+        args: ForkEvalArgs,
+        cancel: &Cancel<'_>,
+    ) -> Result<(Address, RawCallResult, OpcodeHits), ForkCallError> {
+        let from = args.from.unwrap_or_default();
+        let overrides = args.env;
+        let (interpreter, eval_call) = self.eval_call(args, cancel)?;
+
+        let function_pointers = self.function_pointers(from, interpreter, cancel)?;
+        let env = self.call_env(
+            from,
+            interpreter,
+            eval_call.abi_encode().into(),
+            U256::ZERO,
+            &overrides,
+        );
+        let raw = self.call_in_env(env, Some(TraceMode::Steps), cancel)?;
+
+        let pcs = raw.traces.iter().flat_map(|traces| {
+            traces
+                .arena
+                .nodes()
+                .iter()
+                .filter(|node| node.trace.address == interpreter)
+                .flat_map(|node| node.trace.steps.iter().map(|step| step.pc))
+        });
+        let hits = count_opcode_hits(&function_pointers, pcs);
+        Ok((interpreter, raw, hits))
+    }
+
+    /// Reads the opcode function pointers of `interpreter`, memoized per
+    /// interpreter and fork like its DISPaiR.
+    fn function_pointers(
+        &self,
+        from: Address,
+        interpreter: Address,
+        cancel: &Cancel<'_>,
+    ) -> Result<Bytes, ForkCallError> {
+        let fork = self.cache_fork_block();
+        if let Some(function_pointers) = self.cache().function_pointers(interpreter, fork) {
+            return Ok(function_pointers);
+        }
+
+        let function_pointers = self
+            .typed_call(
+                from,
+                interpreter,
+                &buildOpcodeFunctionPointersCall {},
+                cancel,
+            )?
             .typed_return;
+        self.cache()
+            .insert_function_pointers(interpreter, fork, function_pointers.clone());
+        Ok(function_pointers)
+    }
 
-        let parse_call = parse2Call {
-            data: rainlang_string.as_bytes().to_vec().into(),
+    /// Evaluates like [`Forker::fork_eval`], but on the current thread and
+    /// without decoding reverts, returning the interpreter and the raw
+    /// `eval4` call result.
+    pub(crate) fn fork_eval_blocking(
+        &self,
+        args: ForkEvalArgs,
+        cancel: &Cancel<'_>,
+    ) -> Result<(Address, RawCallResult), ForkCallError> {
+        let from = args.from.unwrap_or_default();
+        let overrides = args.env;
+        let (interpreter, eval_call) = self.eval_call(args, cancel)?;
+
+        let env = self.call_env(
+            from,
+            interpreter,
+            eval_call.abi_encode().into(),
+            U256::ZERO,
+            &overrides,
+        );
+        let raw = self.call_in_env(env, None, cancel)?;
+        Ok((interpreter, raw))
+    }
+
+    /// Decodes the `eval4` return of [`Forker::fork_eval_blocking`], and its
+    /// reverts when `decode_errors` is set.
+    pub(crate) async fn eval_typed_return(
+        result: Result<(Address, RawCallResult), ForkCallError>,
+        decode_errors: bool,
+    ) -> Result<ForkTypedReturn<eval4Call>, ForkCallError> {
+        let result = result.and_then(|(_, raw)| Self::decode_return(raw));
+        decode_revert(result, decode_errors).await
+    }
+
+    /// Builds the [`RainEvalResult`] of [`Forker::fork_eval_blocking`],
+    /// decoding the reverts of the calls before `eval4` when `decode_errors`
+    /// is set.
+    pub(crate) async fn eval_result(
+        result: Result<(Address, RawCallResult), ForkCallError>,
+        source_index: u16,
+        decode_errors: bool,
+    ) -> Result<RainEvalResult, ForkCallError> {
+        match result {
+            Ok((interpreter, raw)) => {
+                Self::rain_eval_result(raw, interpreter, source_index, decode_errors).await
+            }
+            Err(e) => decode_revert(Err(e), decode_errors).await,
+        }
+    }
+
+    /// Builds the [`RainEvalResult`] of a raw `eval4` call to `interpreter`,
+    /// turning a revert into a reverted result.
+    async fn rain_eval_result(
+        raw: RawCallResult,
+        interpreter: Address,
+        source_index: u16,
+        decode_errors: bool,
+    ) -> Result<RainEvalResult, ForkCallError> {
+        if raw.exit_reason == InstructionResult::Revert {
+            let revert_data = raw.result.clone();
+            let error = if decode_errors {
+                AbiDecodedErrorType::selector_registry_abi_decode(&revert_data, None)
+                    .await
+                    .unwrap_or_else(|_| AbiDecodedErrorType::Unknown(revert_data.to_vec()))
+            } else {
+                AbiDecodedErrorType::Unknown(revert_data.to_vec())
+            };
+            let raised_by_interpreter = raw
+                .traces
+                .as_ref()
+                .is_some_and(|traces| revert_raised_by(traces.arena.nodes(), interpreter));
+            let mut result = RainEvalResult::try_from(raw)?;
+            result.reverted = true;
+            result.error = Some(error);
+            result.outcome = RainEvalOutcome::from_revert(
+                &revert_data,
+                &result.traces,
+                source_index,
+                raised_by_interpreter,
+            );
+            return Ok(result);
+        }
+
+        if !raw.exit_reason.is_ok() {
+            return Err(raw.into());
+        }
+
+        let typed_return = eval4Call::abi_decode_returns(&raw.result)
+            .map_err(|e| ForkCallError::TypedError(format!("{e:?}")))?;
+        Ok(RainEvalResult::try_from(ForkTypedReturn {
+            raw,
+            typed_return,
+        })?)
+    }
+
+    /// Resolves the interpreter and builds the `eval4` call for the given
+    /// args, parsing the Rainlang string unless `bytecode` is provided.
+    fn eval_call(
+        &self,
+        args: ForkEvalArgs,
+        cancel: &Cancel<'_>,
+    ) -> Result<(Address, eval4Call), ForkCallError> {
+        check_eval_bytecode(&args)?;
+        let dispair = match &args.dispair {
+            Some(dispair) => {
+                check_eval_dispair(dispair)?;
+                dispair.clone()
+            }
+            None => self.resolve_dispair_blocking(args.rainlang, cancel)?,
         };
-        let parse_result = self
-            .alloy_call(Address::default(), deployer, parse_call, decode_errors)
-            .await?;
-
-        let eval_args = eval4Call {
-            eval: EvalV4 {
-                bytecode: parse_result.typed_return,
-                sourceIndex: U256::from(source_index),
-                store,
-                namespace: namespace.into(),
-                context: context
-                    .into_iter()
-                    .map(|v| v.into_iter().map(Into::into).collect())
-                    .collect(),
-                inputs: inputs.into_iter().map(Into::into).collect(),
-                stateOverlay: state_overlay.into_iter().map(Into::into).collect(),
-            },
+
+        let bytecode = match &args.bytecode {
+            Some(bytecode) => bytecode.clone(),
+            None => self.cached_parse(dispair.deployer, &args.rainlang_string, cancel)?,
         };
 
-        let res = self
-            .alloy_call(Address::default(), interpreter, eval_args, decode_errors)
-            .await?;
+        Ok((
+            dispair.interpreter,
+            build_eval_call(args, dispair.store, bytecode),
+        ))
+    }
+}
+
+/// Rejects args that set both pre-parsed bytecode and a Rainlang string, or
+/// neither, before anything is fetched for them.
+fn check_eval_bytecode(args: &ForkEvalArgs) -> Result<(), ForkCallError> {
+    match (&args.bytecode, args.rainlang_string.is_empty()) {
+        (Some(_), false) => Err(ForkCallError::RainlangAndBytecode),
+        (None, true) => Err(ForkCallError::NoRainlangOrBytecode),
+        _ => Ok(()),
+    }
+}
+
+/// Builds the `eval4` call of `bytecode` against `store` from the rest of
+/// the args.
+fn build_eval_call(args: ForkEvalArgs, store: Address, bytecode: Bytes) -> eval4Call {
+    let ForkEvalArgs {
+        source_index,
+        namespace,
+        context,
+        inputs,
+        state_overlay,
+        ..
+    } = args;
 
-        Ok(res)
+    eval4Call {
+        eval: EvalV4 {
+            bytecode,
+            sourceIndex: U256::from(source_index),
+            store,
+            namespace: namespace.into(),
+            context: context
+                .into_iter()
+                .map(|v| v.into_iter().map(Into::into).collect())
+                .collect(),
+            inputs: inputs.into_iter().map(Into::into).collect(),
+            stateOverlay: state_overlay.into_iter().map(Into::into).collect(),
+        },
     }
 }
 
@@ -170,7 +604,9 @@ mod tests {
     use super::*;
     use crate::fork::NewForkedEvm;
     use alloy::primitives::FixedBytes;
+    use alloy::providers::Provider;
     use rain_interpreter_test_fixtures::LocalEvm;
+    use std::time::Duration;
     use std::{ops::Deref, sync::Arc};
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
@@ -179,6 +615,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let fork = Forker::new_with_fork(args, None, None).await.unwrap();
 
@@ -187,6 +625,7 @@ mod tests {
                 rainlang_string: r"_: 1;".to_owned(),
                 rainlang: local_evm.rainlang,
                 decode_errors: true,
+                dispair: None,
             })
             .await
             .unwrap();
@@ -195,12 +634,99 @@ mod tests {
         assert_eq!(res.typed_return.0, expected_bytes);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_parse_integrity_reports() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let reports = fork
+            .fork_parse_integrity_reports(ForkParseArgs {
+                rainlang_string: r"a b: 1 2, _: call<1>(a b); c d:, _: add(c d);".to_owned(),
+                rainlang: local_evm.rainlang,
+                decode_errors: true,
+                dispair: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].inputs, 0);
+        assert_eq!(reports[0].outputs, 3);
+        // Both inputs stay on the stack alongside the `add` result, and the
+        // copies of `c` and `d` push the stack to 4 before `add` runs.
+        assert_eq!(reports[1].inputs, 2);
+        assert_eq!(reports[1].outputs, 3);
+        assert_eq!(reports[1].max_stack_height, 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_unsafe_parse() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let res = fork
+            .fork_unsafe_parse(ForkParseArgs {
+                rainlang_string: r"_: 1;".to_owned(),
+                rainlang: local_evm.rainlang,
+                decode_errors: true,
+                dispair: None,
+            })
+            .await
+            .unwrap();
+
+        let expected_bytecode: Vec<u8> = alloy::hex::decode("0x0100000101000101100000").unwrap();
+        assert_eq!(res.typed_return._0.to_vec(), expected_bytecode);
+        assert_eq!(
+            res.typed_return._1,
+            vec![<FixedBytes<32>>::left_padding_from(&[1u8])]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_unsafe_parse_skips_integrity() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+        let parse_args = ForkParseArgs {
+            rainlang_string: r"_: add(1);".to_owned(),
+            rainlang: local_evm.rainlang,
+            decode_errors: false,
+            dispair: None,
+        };
+
+        // The deployer rejects the expression during its integrity check.
+        assert!(fork.fork_parse(parse_args.clone()).await.is_err());
+
+        // The parser alone still produces bytecode for it.
+        let res = fork.fork_unsafe_parse(parse_args).await.unwrap();
+        assert!(!res.typed_return._0.is_empty());
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_fork_eval() {
         let local_evm = LocalEvm::new().await;
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let fork = Forker::new_with_fork(args, None, None).await.unwrap();
         let res = fork
@@ -213,6 +739,10 @@ mod tests {
                 decode_errors: true,
                 state_overlay: vec![],
                 inputs: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
             })
             .await
             .unwrap();
@@ -243,6 +773,508 @@ mod tests {
         assert_eq!(trace_address, expected_trace_address);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_bytecode() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let bytecode = fork
+            .fork_parse(ForkParseArgs {
+                rainlang_string: r"_: 3;".to_owned(),
+                rainlang: local_evm.rainlang,
+                decode_errors: true,
+                dispair: None,
+            })
+            .await
+            .unwrap()
+            .typed_return;
+
+        let res = fork
+            .fork_eval(ForkEvalArgs {
+                rainlang_string: String::new(),
+                source_index: 0,
+                rainlang: local_evm.rainlang,
+                namespace: FullyQualifiedNamespace::default(),
+                context: vec![],
+                decode_errors: true,
+                state_overlay: vec![],
+                inputs: vec![],
+                bytecode: Some(bytecode.clone()),
+                dispair: None,
+                from: None,
+                env: Default::default(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res.typed_return.stack,
+            vec![<FixedBytes<32>>::left_padding_from(&[3u8])]
+        );
+
+        let both = fork
+            .fork_eval(ForkEvalArgs {
+                rainlang_string: r"_: 4;".into(),
+                source_index: 0,
+                rainlang: local_evm.rainlang,
+                namespace: FullyQualifiedNamespace::default(),
+                context: vec![],
+                decode_errors: true,
+                state_overlay: vec![],
+                inputs: vec![],
+                bytecode: Some(bytecode),
+                dispair: None,
+                from: None,
+                env: Default::default(),
+            })
+            .await;
+        assert!(matches!(both, Err(ForkCallError::RainlangAndBytecode)));
+
+        let neither = fork
+            .fork_eval(ForkEvalArgs {
+                rainlang_string: String::new(),
+                source_index: 0,
+                rainlang: local_evm.rainlang,
+                namespace: FullyQualifiedNamespace::default(),
+                context: vec![],
+                decode_errors: true,
+                state_overlay: vec![],
+                inputs: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
+            })
+            .await;
+        assert!(matches!(neither, Err(ForkCallError::NoRainlangOrBytecode)));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_result() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let res = fork
+            .fork_eval_result(ForkEvalArgs {
+                rainlang_string: r"_: 3;".into(),
+                source_index: 0,
+                rainlang: local_evm.rainlang,
+                namespace: FullyQualifiedNamespace::default(),
+                context: vec![],
+                decode_errors: false,
+                state_overlay: vec![],
+                inputs: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
+            })
+            .await
+            .unwrap();
+
+        assert!(!res.reverted);
+        assert!(res.error.is_none());
+        assert_eq!(res.outcome, RainEvalOutcome::Success);
+        assert_eq!(res.stack, vec![U256::from(3)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_env_overrides() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+        let block_env = fork.executor.env().evm_env.block_env.clone();
+
+        let eval_args = ForkEvalArgs {
+            rainlang_string: r"_ _ _: block-timestamp() block-number() chain-id();".into(),
+            source_index: 0,
+            rainlang: local_evm.rainlang,
+            namespace: FullyQualifiedNamespace::default(),
+            context: vec![],
+            decode_errors: false,
+            state_overlay: vec![],
+            inputs: vec![],
+            bytecode: None,
+            dispair: None,
+            from: Some(Address::repeat_byte(0x1)),
+            env: EnvOverrides {
+                timestamp: Some(1000),
+                number: Some(77),
+                chain_id: Some(5),
+                basefee: Some(1),
+                gas_limit: Some(30_000_000),
+            },
+        };
+        let res = fork.fork_eval_result(eval_args.clone()).await.unwrap();
+        assert!(!res.reverted);
+        for expected in [1000, 77, 5] {
+            assert!(res.stack.contains(&U256::from(expected)), "{res:?}");
+        }
+
+        let typed = fork.fork_eval(eval_args).await.unwrap();
+        assert_eq!(RainEvalResult::try_from(typed).unwrap().stack, res.stack);
+
+        // Overrides only apply to the call they were given with.
+        assert_eq!(fork.executor.env().evm_env.block_env, block_env);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_result_reverted() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let eval_args = ForkEvalArgs {
+            rainlang_string: r#"
+            _ _: call<1>(2),
+            :ensure(0 "always fails");
+            a:, b: 5;
+            "#
+            .into(),
+            source_index: 0,
+            rainlang: local_evm.rainlang,
+            namespace: FullyQualifiedNamespace::default(),
+            context: vec![],
+            decode_errors: false,
+            state_overlay: vec![],
+            inputs: vec![],
+            bytecode: None,
+            dispair: None,
+            from: None,
+            env: Default::default(),
+        };
+
+        // The plain eval surfaces the revert as an error.
+        assert!(fork.fork_eval(eval_args.clone()).await.is_err());
+
+        let res = fork.fork_eval_result(eval_args).await.unwrap();
+        assert!(res.reverted);
+        assert!(res.stack.is_empty());
+        assert!(matches!(res.error, Some(AbiDecodedErrorType::Unknown(_))));
+        assert_eq!(
+            res.outcome,
+            RainEvalOutcome::EnsureFailed {
+                reason: "always fails".into(),
+                source_index: 0,
+            }
+        );
+
+        // Source 1 completed before source 0 reverted, so only its trace
+        // was captured.
+        assert_eq!(res.traces.len(), 1);
+        assert_eq!(res.traces[0].parent_source_index, 0);
+        assert_eq!(res.traces[0].source_index, 1);
+        assert_eq!(res.traces[0].stack, vec![U256::from(5), U256::from(2)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_result_ensure_after_sibling() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let res = fork
+            .fork_eval_result(ForkEvalArgs {
+                rainlang_string: r#"
+                _: call<1>(),
+                :call<2>();
+                _: 1;
+                :ensure(0 "fails in 2");
+                "#
+                .into(),
+                source_index: 0,
+                rainlang: local_evm.rainlang,
+                namespace: FullyQualifiedNamespace::default(),
+                context: vec![],
+                decode_errors: false,
+                state_overlay: vec![],
+                inputs: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
+            })
+            .await
+            .unwrap();
+
+        // Source 2 fails before completing, so only its sibling left a
+        // trace and the failure is attributed to their caller.
+        assert_eq!(res.traces.len(), 1);
+        assert_eq!(res.traces[0].source_index, 1);
+        assert_eq!(
+            res.outcome,
+            RainEvalOutcome::EnsureFailed {
+                reason: "fails in 2".into(),
+                source_index: 0,
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_resolve_dispair() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let dispair = fork
+            .resolve_dispair(local_evm.rainlang, false)
+            .await
+            .unwrap();
+        let deployer = fork
+            .alloy_call(
+                Address::default(),
+                local_evm.rainlang,
+                expressionDeployerAddressCall {},
+                false,
+            )
+            .await
+            .unwrap()
+            .typed_return;
+        assert_eq!(dispair.deployer, deployer);
+        assert_ne!(dispair.interpreter, Address::ZERO);
+        assert_ne!(dispair.store, Address::ZERO);
+        assert_ne!(dispair.parser, Address::ZERO);
+
+        let fork_block = fork.cache_fork_block();
+        assert!(
+            fork.cache()
+                .dispair(local_evm.rainlang, fork_block)
+                .is_some()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_explicit_dispair() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+        let dispair = fork
+            .resolve_dispair(local_evm.rainlang, false)
+            .await
+            .unwrap();
+
+        // A fresh fork has nothing cached, and the Rainlang address is unset,
+        // so the addresses can only come from the explicit DISPaiR.
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+        let res = fork
+            .fork_eval(ForkEvalArgs {
+                rainlang_string: r"_: 3;".into(),
+                source_index: 0,
+                rainlang: Address::ZERO,
+                namespace: FullyQualifiedNamespace::default(),
+                context: vec![],
+                decode_errors: true,
+                state_overlay: vec![],
+                inputs: vec![],
+                bytecode: None,
+                dispair: Some(dispair.clone()),
+                from: None,
+                env: Default::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            res.typed_return.stack,
+            vec![<FixedBytes<32>>::left_padding_from(&[3u8])]
+        );
+
+        let without_store = fork
+            .fork_eval(ForkEvalArgs {
+                rainlang_string: r"_: 3;".into(),
+                source_index: 0,
+                rainlang: Address::ZERO,
+                namespace: FullyQualifiedNamespace::default(),
+                context: vec![],
+                decode_errors: true,
+                state_overlay: vec![],
+                inputs: vec![],
+                bytecode: None,
+                dispair: Some(DISPaiR {
+                    store: Address::ZERO,
+                    ..dispair.clone()
+                }),
+                from: None,
+                env: Default::default(),
+            })
+            .await;
+        assert!(matches!(
+            without_store,
+            Err(ForkCallError::IncompleteDISPaiR("store"))
+        ));
+
+        let parse_args = ForkParseArgs {
+            rainlang_string: r"_: 3;".into(),
+            rainlang: Address::ZERO,
+            decode_errors: true,
+            dispair: Some(dispair.clone()),
+        };
+        fork.fork_parse(parse_args.clone()).await.unwrap();
+        fork.fork_unsafe_parse(parse_args.clone()).await.unwrap();
+
+        let without_parser = ForkParseArgs {
+            dispair: Some(DISPaiR {
+                parser: Address::ZERO,
+                ..dispair.clone()
+            }),
+            ..parse_args.clone()
+        };
+        assert!(matches!(
+            fork.fork_unsafe_parse(without_parser).await,
+            Err(ForkCallError::MissingParserAddress)
+        ));
+
+        let without_deployer = ForkParseArgs {
+            dispair: Some(DISPaiR {
+                deployer: Address::ZERO,
+                ..dispair
+            }),
+            ..parse_args
+        };
+        assert!(matches!(
+            fork.fork_parse(without_deployer).await,
+            Err(ForkCallError::IncompleteDISPaiR("deployer"))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_eval_caches_parse() {
+        let local_evm = LocalEvm::new().await;
+        let block_number = local_evm.provider.get_block_number().await.unwrap();
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: Some(block_number),
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let mut fork = Forker::new_with_fork(args, None, None).await.unwrap();
+        let eval_args = ForkEvalArgs {
+            rainlang_string: r"_: 3;".into(),
+            source_index: 0,
+            rainlang: local_evm.rainlang,
+            namespace: FullyQualifiedNamespace::default(),
+            context: vec![],
+            decode_errors: true,
+            state_overlay: vec![],
+            inputs: vec![],
+            bytecode: None,
+            dispair: None,
+            from: None,
+            env: Default::default(),
+        };
+
+        fork.fork_eval(eval_args.clone()).await.unwrap();
+        let deployer = fork
+            .resolve_dispair(local_evm.rainlang, false)
+            .await
+            .unwrap()
+            .deployer;
+        let key = ParseCacheKey::new(deployer, r"_: 3;", fork.cache_fork_block());
+        let cached = fork.cache().parse(&key).unwrap();
+
+        // A cached result is used instead of parsing again.
+        let res = fork.fork_eval(eval_args.clone()).await.unwrap();
+        assert_eq!(
+            res.typed_return.stack,
+            vec![<FixedBytes<32>>::left_padding_from(&[3u8])]
+        );
+        assert_eq!(fork.cache().parse(&key), Some(cached));
+
+        // A roll that fails leaves the fork, and what was resolved on it, as
+        // it was.
+        assert!(fork.roll_fork(Some(block_number + 1000), None).is_err());
+        assert!(fork.cache().parse(&key).is_some());
+
+        // Rolling the fork invalidates everything resolved on it.
+        fork.roll_fork(Some(block_number), None).unwrap();
+        assert!(fork.cache().parse(&key).is_none());
+        assert!(
+            fork.cache()
+                .dispair(local_evm.rainlang, fork.cache_fork_block())
+                .is_none()
+        );
+
+        fork.fork_eval(eval_args).await.unwrap();
+        assert!(fork.cache().parse(&key).is_some());
+
+        fork.clear_caches();
+        assert!(fork.cache().parse(&key).is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cache_shared_by_clones_at_other_blocks() {
+        let local_evm = LocalEvm::new().await;
+        let block_number = local_evm.provider.get_block_number().await.unwrap();
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: Some(block_number),
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+        let mut genesis = fork.clone();
+        genesis.roll_fork(Some(0), None).unwrap();
+
+        // The clone at the tip fills the shared cache, which must not answer
+        // for the clone at genesis, where nothing is deployed yet.
+        fork.resolve_dispair(local_evm.rainlang, false)
+            .await
+            .unwrap();
+        assert!(
+            genesis
+                .resolve_dispair(local_evm.rainlang, false)
+                .await
+                .is_err()
+        );
+        assert!(
+            fork.resolve_dispair(local_evm.rainlang, false)
+                .await
+                .is_ok()
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_fork_eval_parallel() {
         let local_evm = LocalEvm::new().await;
@@ -250,6 +1282,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let fork = Forker::new_with_fork(args, None, None).await.unwrap();
         let fork = Arc::new(fork); // Wrap in Arc for shared ownership
@@ -268,6 +1302,10 @@ mod tests {
                         decode_errors: true,
                         state_overlay: vec![],
                         inputs: vec![],
+                        bytecode: None,
+                        dispair: None,
+                        from: None,
+                        env: Default::default(),
                     })
                     .await
                     .unwrap()
@@ -283,4 +1321,44 @@ mod tests {
             );
         }
     }
+
+    // The default current thread runtime, as the EVM runs off the runtime.
+    #[tokio::test]
+    async fn test_fork_eval_current_thread() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let mut fork = Forker::new_with_fork(args, None, None).await.unwrap();
+        let args = ForkEvalArgs {
+            rainlang_string: r"_: 3;".into(),
+            source_index: 0,
+            rainlang: local_evm.rainlang,
+            namespace: FullyQualifiedNamespace::default(),
+            context: vec![],
+            decode_errors: true,
+            state_overlay: vec![],
+            inputs: vec![],
+            bytecode: None,
+            dispair: None,
+            from: None,
+            env: Default::default(),
+        };
+
+        let res = fork.fork_eval(args.clone()).await.unwrap();
+        assert_eq!(
+            res.typed_return.stack,
+            vec![FixedBytes::left_padding_from(&[3u8])]
+        );
+
+        // Nothing runs within no time at all, not even the cached parse.
+        fork.set_call_timeout(Some(Duration::ZERO));
+        assert!(matches!(
+            fork.fork_eval(args).await,
+            Err(ForkCallError::Timeout(t)) if t.is_zero()
+        ));
+    }
 }
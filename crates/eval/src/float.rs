@@ -0,0 +1,232 @@
+use alloy::primitives::{I256, U256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Number of bits of a packed float used by the exponent.
+const EXPONENT_BITS: usize = 32;
+
+/// Errors that can occur when parsing or packing decimal floats.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FloatError {
+    #[error("Invalid decimal float: {0}")]
+    Invalid(String),
+    #[error("Coefficient of {0} does not fit in 224 bits")]
+    CoefficientOverflow(String),
+}
+
+/// A Rain decimal float, `coefficient * 10^exponent`.
+///
+/// Packed into a stack word with the signed 224 bit coefficient in the low
+/// bits and the signed 32 bit exponent in the high bits. The same value has
+/// many packings, so equality compares the numeric value.
+#[derive(Debug, Clone, Copy)]
+pub struct DecimalFloat {
+    pub coefficient: I256,
+    pub exponent: i32,
+}
+
+impl DecimalFloat {
+    /// Unpacks a float from a stack word.
+    pub fn from_word(word: U256) -> Self {
+        let exponent = i32::from_be_bytes(word.to_be_bytes::<32>()[..4].try_into().unwrap());
+        let coefficient = I256::from_raw(word << EXPONENT_BITS).asr(EXPONENT_BITS);
+        DecimalFloat {
+            coefficient,
+            exponent,
+        }
+    }
+
+    /// Packs the float into a stack word.
+    pub fn to_word(&self) -> Result<U256, FloatError> {
+        if I256::from_raw(self.coefficient.into_raw() << EXPONENT_BITS).asr(EXPONENT_BITS)
+            != self.coefficient
+        {
+            return Err(FloatError::CoefficientOverflow(self.to_string()));
+        }
+        let exponent = U256::from(self.exponent as u32) << (256 - EXPONENT_BITS);
+        let coefficient = (self.coefficient.into_raw() << EXPONENT_BITS) >> EXPONENT_BITS;
+        Ok(exponent | coefficient)
+    }
+
+    /// Strips trailing zeros from the coefficient so that equal values have
+    /// equal representations.
+    fn normalized(&self) -> (I256, i64) {
+        let ten = I256::try_from(10i64).unwrap();
+        let mut coefficient = self.coefficient;
+        let mut exponent = self.exponent as i64;
+        if coefficient.is_zero() {
+            return (coefficient, 0);
+        }
+        while (coefficient % ten).is_zero() {
+            coefficient /= ten;
+            exponent += 1;
+        }
+        (coefficient, exponent)
+    }
+}
+
+impl PartialEq for DecimalFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized() == other.normalized()
+    }
+}
+
+impl Eq for DecimalFloat {}
+
+impl fmt::Display for DecimalFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (coefficient, exponent) = self.normalized();
+        let sign = if coefficient.is_negative() { "-" } else { "" };
+        let digits = coefficient.unsigned_abs().to_string();
+
+        match exponent {
+            0 => write!(f, "{sign}{digits}"),
+            1..=77 => write!(f, "{sign}{digits}{}", "0".repeat(exponent as usize)),
+            -77..=-1 => {
+                let scale = exponent.unsigned_abs() as usize;
+                let padded = format!("{digits:0>width$}", width = scale + 1);
+                let (int, frac) = padded.split_at(padded.len() - scale);
+                write!(f, "{sign}{int}.{frac}")
+            }
+            _ => write!(f, "{sign}{digits}e{exponent}"),
+        }
+    }
+}
+
+impl FromStr for DecimalFloat {
+    type Err = FloatError;
+
+    /// Parses decimal notation such as `-1.25` or `3e18`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FloatError::Invalid(s.to_string());
+
+        let (mantissa, exponent) = match s.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => {
+                (mantissa, exponent.parse::<i32>().map_err(|_| invalid())?)
+            }
+            None => (s, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let (sign, int) = match int.strip_prefix('-') {
+            Some(int) => ("-", int),
+            None => ("", int),
+        };
+        if (int.is_empty() && frac.is_empty())
+            || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let coefficient =
+            I256::from_dec_str(&format!("{sign}{int}{frac}")).map_err(|_| invalid())?;
+        let exponent = i32::try_from(frac.len())
+            .ok()
+            .and_then(|scale| exponent.checked_sub(scale))
+            .ok_or_else(invalid)?;
+
+        Ok(DecimalFloat {
+            coefficient,
+            exponent,
+        })
+    }
+}
+
+impl Serialize for DecimalFloat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DecimalFloat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float(s: &str) -> DecimalFloat {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let f = float("-1.25");
+        assert_eq!(f.coefficient, I256::try_from(-125i64).unwrap());
+        assert_eq!(f.exponent, -2);
+
+        let f = float("3e18");
+        assert_eq!(f.coefficient, I256::try_from(3i64).unwrap());
+        assert_eq!(f.exponent, 18);
+
+        let f = float("1.5e-3");
+        assert_eq!(f.coefficient, I256::try_from(15i64).unwrap());
+        assert_eq!(f.exponent, -4);
+
+        assert!("".parse::<DecimalFloat>().is_err());
+        assert!("1.2.3".parse::<DecimalFloat>().is_err());
+        assert!("abc".parse::<DecimalFloat>().is_err());
+        assert!("1e".parse::<DecimalFloat>().is_err());
+    }
+
+    #[test]
+    fn test_eq_ignores_representation() {
+        assert_eq!(float("1.50"), float("1.5"));
+        assert_eq!(float("100"), float("1e2"));
+        assert_eq!(float("0"), float("0.000"));
+        assert_ne!(float("1.5"), float("1.05"));
+        assert_ne!(float("-1"), float("1"));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(float("1.50").to_string(), "1.5");
+        assert_eq!(float("-0.05").to_string(), "-0.05");
+        assert_eq!(float("12e3").to_string(), "12000");
+        assert_eq!(float("0").to_string(), "0");
+        assert_eq!(float("1e-100").to_string(), "1e-100");
+    }
+
+    #[test]
+    fn test_word_roundtrip() {
+        // Integers with a zero exponent pack to themselves.
+        assert_eq!(float("3").to_word().unwrap(), U256::from(3));
+        assert_eq!(DecimalFloat::from_word(U256::from(3)), float("3"));
+
+        for s in ["-1.25", "1.5e-3", "0", "-7e20"] {
+            let f = float(s);
+            let word = f.to_word().unwrap();
+            let unpacked = DecimalFloat::from_word(word);
+            assert_eq!(unpacked.coefficient, f.coefficient);
+            assert_eq!(unpacked.exponent, f.exponent);
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        assert_eq!(serde_json::to_string(&float("1.50")).unwrap(), "\"1.5\"");
+        assert_eq!(
+            serde_json::from_str::<DecimalFloat>("\"-2e3\"").unwrap(),
+            float("-2000")
+        );
+        assert!(serde_json::from_str::<DecimalFloat>("\"abc\"").is_err());
+    }
+
+    #[test]
+    fn test_to_word_overflow() {
+        let f = DecimalFloat {
+            coefficient: I256::MAX,
+            exponent: 0,
+        };
+        assert!(matches!(
+            f.to_word(),
+            Err(FloatError::CoefficientOverflow(_))
+        ));
+    }
+}
@@ -1,31 +1,68 @@
-use crate::error::{ForkCallError, ReplayTransactionError};
+use crate::cache::{ForkBlock, ForkerCache};
+use crate::cancel::{self, Cancel, CancelOnDrop};
+use crate::error::{ForkCallError, ReplayTransactionError, RpcError};
+use crate::hardfork;
+use crate::rpc_cache::{OfflineState, RpcCacheFile, RpcCacheGuard, cache_path};
 use alloy::consensus::Transaction;
+use alloy::eips::Typed2718;
+use alloy::network::TransactionResponse;
 use alloy::primitives::{Address, BlockNumber, U256};
 use alloy::sol_types::SolCall;
 use foundry_evm::traces::TraceMode;
 use foundry_evm::{
     Env, EnvMut,
-    backend::{Backend, DatabaseExt, JournaledState, LocalForkId},
+    backend::{Backend, DatabaseError, DatabaseExt, JournaledState, LocalForkId},
     executors::{Executor, ExecutorBuilder, RawCallResult},
     fork::{CreateFork, ForkId, MultiFork},
     opts::EvmOpts,
+    utils::configure_tx_env,
 };
 use rain_error_decoding::AbiDecodedErrorType;
+use revm::DatabaseCommit;
 use revm::primitives::hardfork::SpecId;
 use revm::primitives::{B256, TxKind};
+use revm::state::AccountInfo;
 use revm::{
     interpreter::InstructionResult,
     primitives::{Address as Addr, Bytes},
 };
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 use std::{any::type_name, collections::HashMap};
 
 /// Forker is thin wrapper around foundry for easily forking multiple evm
 /// networks with in-memory cache that provides easy to use read/write
 /// functionalities.
+///
+/// Resolved DISPaiRs and parse results are memoized per fork and shared
+/// between clones. They are invalidated when the fork is rolled.
+///
+/// The EVM spec of each fork follows its chain's hardfork schedule at the
+/// fork block, unless pinned with [`Forker::set_spec_id`].
+///
+/// Forks with an [`RpcCache`] save the state they fetched once the last
+/// clone is dropped.
+///
+/// The async calls, such as [`Forker::alloy_call`] and `fork_eval`, run the
+/// EVM on a blocking thread so they do not stall the async runtime, and fail
+/// with [`ForkCallError::Timeout`] past [`Forker::set_call_timeout`]. The EVM
+/// stops once the call times out or its future is dropped.
 #[derive(Clone)]
 pub struct Forker {
-    pub executor: Executor,
-    forks: HashMap<ForkId, (LocalForkId, SpecId, BlockNumber)>,
+    /// Shared with clones and with the async calls in flight, so it is only
+    /// copied once mutated through [`Forker::executor_mut`].
+    pub executor: Arc<Executor>,
+    forks: HashMap<ForkId, HeldFork>,
+    next_fork_id: LocalForkId,
+    cache: Arc<Mutex<ForkerCache>>,
+    spec_id_override: Option<SpecId>,
+    rpc_caches: Vec<Arc<RpcCacheGuard>>,
+    offline: Option<Arc<OfflineState>>,
+    call_timeout: Option<Duration>,
 }
 
 /// Result of an alloy-typed call containing both the raw EVM result and the
@@ -37,6 +74,33 @@ pub struct ForkTypedReturn<C: SolCall> {
     pub typed_return: C::Return,
 }
 
+/// A fork held by a [`Forker`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForkInfo {
+    /// Id of the fork in the forker. Ids are not reused once a fork is
+    /// removed.
+    pub local_id: LocalForkId,
+    /// RPC URL the fork was created from.
+    pub url: String,
+    /// Block the fork was created at, which [`Forker::roll_fork`] resets to.
+    pub fork_block_number: BlockNumber,
+    /// Block the fork's state is currently at.
+    pub block_number: BlockNumber,
+    pub spec_id: SpecId,
+}
+
+/// A fork held by a [`Forker`], with what it was created from so that the
+/// backend can be rebuilt without another fork.
+#[derive(Debug, Clone)]
+struct HeldFork {
+    info: ForkInfo,
+    /// Id of the fork in the executor's backend, which changes when the
+    /// backend is rebuilt.
+    backend_id: LocalForkId,
+    create_fork: CreateFork,
+    rpc_cache: Option<PathBuf>,
+}
+
 /// Configuration for creating a new forked EVM instance.
 #[derive(Debug, Clone)]
 pub struct NewForkedEvm {
@@ -44,14 +108,251 @@ pub struct NewForkedEvm {
     pub fork_url: String,
     /// Optional block number to fork from. Uses latest if `None`.
     pub fork_block_number: Option<BlockNumber>,
+    /// Persists the state fetched over RPC on disk. Only forks at a pinned
+    /// block are cached, as latest moves between runs.
+    pub rpc_cache: Option<RpcCache>,
+    /// Fallback URLs, retries and rate limiting of the RPC.
+    pub rpc: RpcOptions,
+}
+
+/// On-disk cache of the state forks fetch over RPC, reused by later runs at
+/// the same block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcCache {
+    /// Directory the cache files are kept in, one per RPC URL and block.
+    pub dir: PathBuf,
+    /// Runs from the cache alone without connecting to the RPC. Fails if the
+    /// block was never cached. State that was not fetched by earlier runs
+    /// reads as empty.
+    pub offline: bool,
 }
 
+/// How a fork talks to its RPC. The default tries `fork_url` alone with the
+/// provider's own retries and no rate limit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RpcOptions {
+    /// Tried in order when the fork cannot be created from `fork_url`. The
+    /// fork stays on the URL it was created from, see [`ForkInfo::url`].
+    pub fallback_urls: Vec<String>,
+    /// Times a failed request to a URL is retried before moving on.
+    pub retries: Option<u32>,
+    /// Wait before the first retry, doubled for each retry after it.
+    pub retry_backoff: Option<Duration>,
+    /// Compute units per second the RPC allows, which requests are
+    /// throttled to.
+    pub compute_units_per_second: Option<u64>,
+}
+
+/// Block and chain environment overrides for a single call. Unset fields
+/// keep the executor's environment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvOverrides {
+    pub timestamp: Option<u64>,
+    pub number: Option<u64>,
+    pub chain_id: Option<u64>,
+    pub basefee: Option<u64>,
+    pub gas_limit: Option<u64>,
+}
+
+impl EnvOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == EnvOverrides::default()
+    }
+
+    fn apply(&self, env: &mut Env) {
+        let block_env = &mut env.evm_env.block_env;
+        if let Some(timestamp) = self.timestamp {
+            block_env.timestamp = timestamp;
+        }
+        if let Some(number) = self.number {
+            block_env.number = number;
+        }
+        if let Some(basefee) = self.basefee {
+            block_env.basefee = basefee;
+            // The caller pays no gas, so only the BASEFEE opcode sees it.
+            env.evm_env.cfg_env.disable_base_fee = true;
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            env.evm_env.block_env.gas_limit = gas_limit;
+            env.tx.gas_limit = gas_limit;
+        }
+        if let Some(chain_id) = self.chain_id {
+            env.evm_env.cfg_env.chain_id = chain_id;
+            env.tx.chain_id = Some(chain_id);
+        }
+    }
+}
+
+/// Type of the OP stack's deposit transactions.
+const DEPOSIT_TRANSACTION_TYPE: u8 = 0x7e;
+
 fn mk_journaled_state(spec_id: SpecId) -> JournaledState {
     let mut journaled_state = JournaledState::new();
     journaled_state.set_spec_id(spec_id);
     journaled_state
 }
 
+fn evm_opts() -> EvmOpts {
+    EvmOpts {
+        env: foundry_evm::opts::Env {
+            chain_id: None,
+            code_size_limit: None,
+            gas_limit: u64::MAX.into(),
+            ..Default::default()
+        },
+        memory_limit: u64::MAX,
+        ..Default::default()
+    }
+}
+
+fn fork_evm_opts(
+    fork_url: &str,
+    fork_block_number: Option<BlockNumber>,
+    rpc: &RpcOptions,
+) -> EvmOpts {
+    EvmOpts {
+        fork_url: Some(fork_url.to_string()),
+        fork_block_number,
+        fork_retries: rpc.retries,
+        fork_retry_backoff: rpc
+            .retry_backoff
+            .map(|backoff| backoff.as_millis().try_into().unwrap_or(u64::MAX)),
+        compute_units_per_second: rpc.compute_units_per_second,
+        ..evm_opts()
+    }
+}
+
+/// Fetches the fork block's environment from `fork_url`, or from the first
+/// fallback URL that answers. Each URL is retried as configured before
+/// moving on to the next.
+async fn connect(
+    fork_url: &str,
+    fork_block_number: Option<BlockNumber>,
+    rpc: &RpcOptions,
+) -> Result<CreateFork, RpcError> {
+    let urls = std::iter::once(fork_url).chain(rpc.fallback_urls.iter().map(String::as_str));
+    let mut failures = vec![];
+    for url in urls {
+        let evm_opts = fork_evm_opts(url, fork_block_number, rpc);
+        let mut backoff = rpc.retry_backoff.unwrap_or_default();
+        let mut retries = rpc.retries.unwrap_or(0);
+        loop {
+            match evm_opts.fork_evm_env(url).await {
+                Ok((env, _)) => {
+                    return Ok(CreateFork {
+                        url: url.to_owned(),
+                        enable_caching: true,
+                        env,
+                        evm_opts,
+                    });
+                }
+                Err(_) if retries > 0 => {
+                    retries -= 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    failures.push(RpcError::Connect {
+                        url: url.to_owned(),
+                        message: format!("{e:#}"),
+                    });
+                    break;
+                }
+            }
+        }
+    }
+    if failures.len() == 1 {
+        Err(failures.remove(0))
+    } else {
+        Err(RpcError::AllUrlsFailed(failures))
+    }
+}
+
+/// Maps an executor failure to [`RpcError::Fetch`] if it came from fetching
+/// fork state, and to [`ForkCallError::ExecutorError`] otherwise.
+fn executor_error(e: eyre::Report) -> ForkCallError {
+    if e.chain()
+        .any(|cause| cause.downcast_ref::<DatabaseError>().is_some())
+    {
+        RpcError::Fetch(format!("{e:#}")).into()
+    } else {
+        ForkCallError::ExecutorError(e.to_string())
+    }
+}
+
+/// Decodes the revert data of a [`ForkCallError::Failed`] call using the
+/// error selector registry when `decode_error` is set, as the async calls do
+/// once their blocking part returns. Anything else is returned as is.
+pub(crate) async fn decode_revert<R>(
+    result: Result<R, ForkCallError>,
+    decode_error: bool,
+) -> Result<R, ForkCallError> {
+    match result {
+        Err(ForkCallError::Failed(raw))
+            if decode_error && raw.exit_reason == InstructionResult::Revert =>
+        {
+            match AbiDecodedErrorType::selector_registry_abi_decode(&raw.result, None).await {
+                Ok(decoded) => Err(ForkCallError::AbiDecodedError(decoded)),
+                Err(e) => Err(e.into()),
+            }
+        }
+        result => result,
+    }
+}
+
+/// The environment of a forker without forks, with the same limits as a
+/// forked one.
+pub(crate) fn local_evm_env() -> Env {
+    evm_opts().local_evm_env()
+}
+
+/// Prefills the RPC state of the active fork from its cache file and returns
+/// a guard that saves it back, with everything fetched since, on drop.
+fn attach_rpc_cache(
+    backend: &Backend,
+    path: PathBuf,
+    env: &Env,
+) -> Result<RpcCacheGuard, ForkCallError> {
+    let data = backend
+        .active_fork_db()
+        .ok_or(ForkCallError::ExecutorError("no active fork!".to_owned()))?
+        .db
+        .data();
+    if let Some(file) = RpcCacheFile::load(&path)? {
+        data.accounts.write().extend(file.accounts);
+        let mut storage = data.storage.write();
+        for (address, slots) in file.storage {
+            storage.entry(address).or_default().extend(slots);
+        }
+    }
+
+    let chain_id = env.evm_env.cfg_env.chain_id;
+    let block_env = env.evm_env.block_env.clone();
+    Ok(RpcCacheGuard::new(move || {
+        let file = RpcCacheFile {
+            chain_id,
+            block_env: block_env.clone(),
+            accounts: data
+                .accounts
+                .read()
+                .iter()
+                .map(|(address, info)| (*address, info.clone()))
+                .collect(),
+            storage: data
+                .storage
+                .read()
+                .iter()
+                .map(|(address, slots)| {
+                    let slots = slots.iter().map(|(slot, value)| (*slot, *value));
+                    (*address, slots.collect())
+                })
+                .collect(),
+        };
+        file.save(&path)
+    }))
+}
+
 // NOTE: there is a trait for this in foundry-evm-core but it's not exposed
 // through the meta crate foundry-evm
 fn mk_env_mut(env: &mut Env) -> EnvMut<'_> {
@@ -69,8 +370,14 @@ impl Forker {
         let builder = ExecutorBuilder::default()
             .inspectors(|stack| stack.trace_mode(TraceMode::Call.with_debug(false)));
         Ok(Self {
-            executor: builder.build(Env::default(), db),
+            executor: Arc::new(builder.build(Env::default(), db)),
             forks: HashMap::new(),
+            next_fork_id: LocalForkId::ZERO,
+            cache: Arc::new(Mutex::new(ForkerCache::default())),
+            spec_id_override: None,
+            rpc_caches: vec![],
+            offline: None,
+            call_timeout: None,
         })
     }
 
@@ -94,6 +401,8 @@ impl Forker {
     /// let args = NewForkedEvm {
     ///     fork_url: "https://example.com/fork".to_owned(),
     ///     fork_block_number: Some(12345u64),
+    ///     rpc_cache: None,
+    ///     rpc: Default::default(),
     /// };
     /// let forker = Forker::new_with_fork(args, None, None).await;
     /// ```
@@ -105,51 +414,190 @@ impl Forker {
         let NewForkedEvm {
             fork_url,
             fork_block_number,
+            rpc_cache,
+            rpc,
         } = args;
+        if let Some(RpcCache { dir, offline: true }) = &rpc_cache {
+            let block_number = fork_block_number.ok_or(ForkCallError::OfflineUnpinned)?;
+            let path = cache_path(dir, &fork_url, block_number);
+            return Self::new_offline(block_number, path, env, gas_limit);
+        }
         let fork_id = ForkId::new(&fork_url, fork_block_number);
-        let evm_opts = EvmOpts {
-            fork_url: Some(fork_url.to_string()),
-            fork_block_number,
-            env: foundry_evm::opts::Env {
-                chain_id: None,
-                code_size_limit: None,
-                gas_limit: u64::MAX.into(),
-                ..Default::default()
-            },
-            memory_limit: u64::MAX,
-            ..Default::default()
-        };
-
-        let create_fork = CreateFork {
-            url: fork_url.clone(),
-            enable_caching: true,
-            env: evm_opts.fork_evm_env(&fork_url).await?.0,
-            evm_opts,
-        };
+        let create_fork = connect(&fork_url, fork_block_number, &rpc).await?;
         let block_number = if let Some(block_number) = fork_block_number {
             block_number
         } else {
             create_fork.env.evm_env.block_env.number
         };
+        let spec_id = hardfork::spec_id(
+            create_fork.env.evm_env.cfg_env.chain_id,
+            block_number,
+            create_fork.env.evm_env.block_env.timestamp,
+        );
 
-        let db = Backend::spawn(Some(create_fork.clone()))?;
+        let db = Backend::spawn(Some(create_fork.clone())).map_err(|e| RpcError::Connect {
+            url: create_fork.url.clone(),
+            message: format!("{e:#}"),
+        })?;
+        let backend_id = db.active_fork_id().unwrap_or_default();
+        let mut rpc_caches = vec![];
+        let rpc_cache_path = match (&rpc_cache, fork_block_number) {
+            (Some(rpc_cache), Some(block_number)) => {
+                Some(cache_path(&rpc_cache.dir, &fork_url, block_number))
+            }
+            _ => None,
+        };
+        if let Some(path) = &rpc_cache_path {
+            rpc_caches.push(Arc::new(attach_rpc_cache(
+                &db,
+                path.clone(),
+                &create_fork.env,
+            )?));
+        }
 
         let builder = if let Some(gas) = gas_limit {
             ExecutorBuilder::default().gas_limit(gas)
         } else {
             ExecutorBuilder::default()
         };
-        let builder =
-            builder.inspectors(|stack| stack.trace_mode(TraceMode::Call.with_debug(false)));
+        let builder = builder
+            .spec_id(spec_id)
+            .inspectors(|stack| stack.trace_mode(TraceMode::Call.with_debug(false)));
 
         let mut forks_map = HashMap::new();
-        forks_map.insert(fork_id, (U256::from(0), SpecId::default(), block_number));
+        forks_map.insert(
+            fork_id,
+            HeldFork {
+                info: ForkInfo {
+                    local_id: LocalForkId::ZERO,
+                    url: create_fork.url.clone(),
+                    fork_block_number: block_number,
+                    block_number,
+                    spec_id,
+                },
+                backend_id,
+                create_fork: create_fork.clone(),
+                rpc_cache: rpc_cache_path,
+            },
+        );
         Ok(Self {
-            executor: builder.build(env.unwrap_or(create_fork.env.clone()), db),
+            executor: Arc::new(builder.build(env.unwrap_or(create_fork.env.clone()), db)),
             forks: forks_map,
+            next_fork_id: LocalForkId::from(1),
+            cache: Arc::new(Mutex::new(ForkerCache::default())),
+            spec_id_override: None,
+            rpc_caches,
+            offline: None,
+            call_timeout: None,
         })
     }
 
+    /// A forker that runs from the cached RPC state of a fork, without a
+    /// connection to the RPC. It holds no fork, so it cannot be rolled, and
+    /// calls that load state missing from the cache fail with
+    /// [`ForkCallError::OfflineStateMiss`].
+    fn new_offline(
+        block_number: BlockNumber,
+        path: PathBuf,
+        env: Option<Env>,
+        gas_limit: Option<u64>,
+    ) -> Result<Forker, ForkCallError> {
+        let file = RpcCacheFile::load(&path)?
+            .ok_or_else(|| ForkCallError::OfflineCacheMiss(path.display().to_string()))?;
+
+        let mut fork_env = local_evm_env();
+        fork_env.evm_env.cfg_env.chain_id = file.chain_id;
+        fork_env.tx.chain_id = Some(file.chain_id);
+        fork_env.evm_env.block_env = file.block_env;
+        let spec_id = hardfork::spec_id(
+            file.chain_id,
+            block_number,
+            fork_env.evm_env.block_env.timestamp,
+        );
+
+        let offline = OfflineState::new(path, &file);
+        let storage = file.storage.into_iter().flat_map(|(address, slots)| {
+            slots
+                .into_iter()
+                .map(move |(slot, value)| (address, slot, value))
+        });
+        let mut forker = Self::new_with_state(
+            env.unwrap_or(fork_env),
+            spec_id,
+            file.accounts,
+            storage,
+            gas_limit,
+        )?;
+        forker.offline = Some(Arc::new(offline));
+        Ok(forker)
+    }
+
+    /// A forker without forks whose in-memory state holds the given accounts
+    /// and storage slots.
+    pub(crate) fn new_with_state(
+        env: Env,
+        spec_id: SpecId,
+        accounts: impl IntoIterator<Item = (Address, AccountInfo)>,
+        storage: impl IntoIterator<Item = (Address, U256, U256)>,
+        gas_limit: Option<u64>,
+    ) -> Result<Forker, ForkCallError> {
+        let mut db = Backend::new(MultiFork::new().0, None)?;
+        for (address, info) in accounts {
+            db.insert_account_info(address, info);
+        }
+        for (address, slot, value) in storage {
+            db.insert_account_storage(address, slot, value)
+                .map_err(|e| ForkCallError::ExecutorError(e.to_string()))?;
+        }
+
+        let builder = if let Some(gas) = gas_limit {
+            ExecutorBuilder::default().gas_limit(gas)
+        } else {
+            ExecutorBuilder::default()
+        };
+        let builder = builder
+            .spec_id(spec_id)
+            .inspectors(|stack| stack.trace_mode(TraceMode::Call.with_debug(false)));
+
+        Ok(Self {
+            executor: Arc::new(builder.build(env, db)),
+            forks: HashMap::new(),
+            next_fork_id: LocalForkId::ZERO,
+            cache: Arc::new(Mutex::new(ForkerCache::default())),
+            spec_id_override: None,
+            rpc_caches: vec![],
+            offline: None,
+            call_timeout: None,
+        })
+    }
+
+    /// Locks the memoized lookups. A poisoned lock only means another thread
+    /// panicked mid-update of a cache, so the contents are still usable.
+    pub(crate) fn cache(&self) -> MutexGuard<'_, ForkerCache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The active fork and the block its state is at, which memoized
+    /// lookups are keyed by.
+    pub(crate) fn cache_fork_block(&self) -> ForkBlock {
+        let active = self.active_fork();
+        ForkBlock {
+            fork_id: active.as_ref().map(|fork| fork.local_id),
+            block_number: active.map(|fork| fork.block_number).unwrap_or_default(),
+        }
+    }
+
+    /// Sets how many parse results are kept before the least recently used
+    /// ones are evicted.
+    pub fn set_parse_cache_capacity(&self, capacity: NonZeroUsize) {
+        self.cache().resize(capacity);
+    }
+
+    /// Drops all memoized DISPaiRs and parse results.
+    pub fn clear_caches(&self) {
+        self.cache().clear();
+    }
+
     /// Adds new fork and sets it as active or if the fork already exists, selects it as active.
     /// Does nothing if the fork is already the active fork.
     pub async fn add_or_select(
@@ -161,72 +609,231 @@ impl Forker {
             let forker = Self::new_with_fork(args, env, None).await?;
             self.executor = forker.executor;
             self.forks = forker.forks;
+            self.next_fork_id = forker.next_fork_id;
+            self.rpc_caches.extend(forker.rpc_caches);
+            self.offline = forker.offline;
+            if let Some(spec_id) = self.spec_id_override {
+                self.set_active_spec_id(spec_id);
+            }
             return Ok(());
         }
         let NewForkedEvm {
             fork_url,
             fork_block_number,
+            rpc_cache,
+            rpc,
         } = args;
+        if rpc_cache
+            .as_ref()
+            .is_some_and(|rpc_cache| rpc_cache.offline)
+        {
+            return Err(ForkCallError::ExecutorError(
+                "offline forks cannot be added to a forker that holds forks".to_owned(),
+            ));
+        }
         let fork_id = ForkId::new(&fork_url, fork_block_number);
-        if let Some((local_fork_id, spec_id, _)) = self.forks.get(&fork_id) {
-            if self.executor.backend().is_active_fork(*local_fork_id) {
+        if let Some(fork) = self.forks.get(&fork_id) {
+            if self.executor.backend().is_active_fork(fork.backend_id) {
                 Ok(())
             } else {
-                let mut journaled_state = mk_journaled_state(*spec_id);
-                self.executor
+                let (backend_id, spec_id) = (fork.backend_id, fork.info.spec_id);
+                let mut journaled_state = mk_journaled_state(spec_id);
+                self.executor_mut()
                     .backend_mut()
                     .select_fork(
-                        *local_fork_id,
+                        backend_id,
                         &mut mk_env_mut(&mut env.unwrap_or_default()),
                         &mut journaled_state,
                     )
-                    .map(|_| ())
-                    .map_err(|e| ForkCallError::ExecutorError(e.to_string()))
+                    .map_err(|e| ForkCallError::ExecutorError(e.to_string()))?;
+                self.executor_mut().env_mut().evm_env.cfg_env.spec = spec_id;
+                Ok(())
             }
         } else {
-            let evm_opts = EvmOpts {
-                fork_url: Some(fork_url.to_string()),
-                fork_block_number,
-                env: foundry_evm::opts::Env {
-                    chain_id: None,
-                    code_size_limit: None,
-                    gas_limit: u64::MAX.into(),
-                    ..Default::default()
-                },
-                memory_limit: u64::MAX,
-                ..Default::default()
-            };
-            let create_fork = CreateFork {
-                url: fork_url.to_string(),
-                enable_caching: true,
-                env: evm_opts.fork_evm_env(&fork_url).await?.0,
-                evm_opts,
-            };
+            let create_fork = connect(&fork_url, fork_block_number, &rpc).await?;
             let block_number = if let Some(block_number) = fork_block_number {
                 block_number
             } else {
                 create_fork.env.evm_env.block_env.number
             };
+            let spec_id = self.spec_id_override.unwrap_or_else(|| {
+                hardfork::spec_id(
+                    create_fork.env.evm_env.cfg_env.chain_id,
+                    block_number,
+                    create_fork.env.evm_env.block_env.timestamp,
+                )
+            });
+            let fork_env = create_fork.env.clone();
+            let url = create_fork.url.clone();
 
+            let backend_id = self
+                .executor_mut()
+                .backend_mut()
+                .create_select_fork(
+                    create_fork.clone(),
+                    &mut mk_env_mut(&mut env.unwrap_or_default()),
+                    &mut mk_journaled_state(spec_id),
+                )
+                .map_err(|e| RpcError::Connect {
+                    url: url.clone(),
+                    message: format!("{e:#}"),
+                })?;
+            self.executor_mut().env_mut().evm_env.cfg_env.spec = spec_id;
+            let rpc_cache_path = match (&rpc_cache, fork_block_number) {
+                (Some(rpc_cache), Some(block_number)) => {
+                    Some(cache_path(&rpc_cache.dir, &fork_url, block_number))
+                }
+                _ => None,
+            };
+            if let Some(path) = &rpc_cache_path {
+                let guard = attach_rpc_cache(self.executor.backend(), path.clone(), &fork_env)?;
+                self.rpc_caches.push(Arc::new(guard));
+            }
+            let local_id = self.next_fork_id;
+            self.next_fork_id += LocalForkId::from(1);
             self.forks.insert(
                 fork_id,
-                (
-                    U256::from(self.forks.len()),
-                    SpecId::default(),
-                    block_number,
-                ),
+                HeldFork {
+                    info: ForkInfo {
+                        local_id,
+                        url,
+                        fork_block_number: block_number,
+                        block_number,
+                        spec_id,
+                    },
+                    backend_id,
+                    create_fork,
+                    rpc_cache: rpc_cache_path,
+                },
             );
+            Ok(())
+        }
+    }
 
-            self.executor
-                .backend_mut()
+    /// Saves the state every cached fork fetched so far to its cache file.
+    /// This also happens when the last clone of the forker is dropped, but
+    /// errors are lost there.
+    pub fn save_rpc_caches(&self) -> Result<(), ForkCallError> {
+        self.rpc_caches.iter().try_for_each(|guard| guard.save())
+    }
+
+    /// The forks held by this forker, in the order they were created.
+    pub fn forks(&self) -> Vec<ForkInfo> {
+        let mut forks: Vec<ForkInfo> = self.forks.values().map(|fork| fork.info.clone()).collect();
+        forks.sort_by_key(|fork| fork.local_id);
+        forks
+    }
+
+    /// The fork calls are currently made on, if any.
+    pub fn active_fork(&self) -> Option<ForkInfo> {
+        let active_backend_id = self.executor.backend().active_fork_id()?;
+        self.forks
+            .values()
+            .find(|fork| fork.backend_id == active_backend_id)
+            .map(|fork| fork.info.clone())
+    }
+
+    /// Removes a fork that is not active, along with everything resolved on
+    /// it. Adding the same fork again creates a fresh one with a new id.
+    ///
+    /// The backend can't drop a single fork, so it is rebuilt from the
+    /// remaining ones, each at its current block with the state it holds,
+    /// and the removed fork's database is freed along with the old backend
+    /// once no clone of this forker holds it. RPC caches are saved first.
+    pub fn remove_fork(&mut self, local_id: LocalForkId) -> Result<ForkInfo, ForkCallError> {
+        let (fork_id, backend_id) = self
+            .forks
+            .iter()
+            .find(|(_, fork)| fork.info.local_id == local_id)
+            .map(|(fork_id, fork)| (fork_id.clone(), fork.backend_id))
+            .ok_or(ForkCallError::UnknownFork(local_id))?;
+        if self.executor.backend().is_active_fork(backend_id) {
+            return Err(ForkCallError::RemoveActiveFork(local_id));
+        }
+
+        self.save_rpc_caches()?;
+        let removed = self
+            .forks
+            .remove(&fork_id)
+            .ok_or(ForkCallError::UnknownFork(local_id))?;
+        if let Err(e) = self.rebuild_backend() {
+            self.forks.insert(fork_id, removed);
+            return Err(e);
+        }
+        self.cache().invalidate_fork(Some(local_id));
+        Ok(removed.info)
+    }
+
+    /// Replaces the backend with a new one holding only the forks in
+    /// `self.forks`, created in the same order, each rolled to the block it
+    /// is at and carrying over the state of its database, with the same
+    /// fork active. Nothing changes if any of them can't be recreated.
+    fn rebuild_backend(&mut self) -> Result<(), ForkCallError> {
+        let active_backend_id = self.executor.backend().active_fork_id();
+        let mut forks: Vec<(&ForkId, &HeldFork)> = self.forks.iter().collect();
+        // Stable, so the active fork is created last and stays selected.
+        forks.sort_by_key(|(_, fork)| fork.info.local_id);
+        forks.sort_by_key(|(_, fork)| Some(fork.backend_id) == active_backend_id);
+
+        let mut old = self.executor.backend().clone();
+        let mut backend =
+            Backend::spawn(None).map_err(|e| ForkCallError::ExecutorError(e.to_string()))?;
+        let mut backend_ids = vec![];
+        let mut rpc_caches = vec![];
+        for (fork_id, fork) in forks {
+            let mut env = Env::default();
+            let mut journaled_state = mk_journaled_state(fork.info.spec_id);
+            old.select_fork(
+                fork.backend_id,
+                &mut mk_env_mut(&mut env),
+                &mut journaled_state,
+            )
+            .map_err(|e| ForkCallError::ExecutorError(e.to_string()))?;
+            let state = old
+                .active_fork_db()
+                .ok_or(ForkCallError::ExecutorError("no active fork!".to_owned()))?
+                .cache
+                .clone();
+
+            let backend_id = backend
                 .create_select_fork(
-                    create_fork,
-                    &mut mk_env_mut(&mut env.unwrap_or_default()),
-                    &mut mk_journaled_state(SpecId::default()),
+                    fork.create_fork.clone(),
+                    &mut mk_env_mut(&mut env),
+                    &mut journaled_state,
                 )
-                .map(|_| ())
-                .map_err(|e| ForkCallError::ExecutorError(e.to_string()))
+                .map_err(|e| RpcError::Connect {
+                    url: fork.create_fork.url.clone(),
+                    message: format!("{e:#}"),
+                })?;
+            if fork.info.block_number != fork.info.fork_block_number {
+                backend
+                    .roll_fork(
+                        Some(backend_id),
+                        fork.info.block_number,
+                        &mut mk_env_mut(&mut env),
+                        &mut journaled_state,
+                    )
+                    .map_err(|e| ForkCallError::ExecutorError(e.to_string()))?;
+            }
+            if let Some(path) = &fork.rpc_cache {
+                let guard = attach_rpc_cache(&backend, path.clone(), &fork.create_fork.env)?;
+                rpc_caches.push(Arc::new(guard));
+            }
+            backend
+                .active_fork_db_mut()
+                .ok_or(ForkCallError::ExecutorError("no active fork!".to_owned()))?
+                .cache = state;
+            backend_ids.push((fork_id.clone(), backend_id));
+        }
+
+        *self.executor_mut().backend_mut() = backend;
+        for (fork_id, backend_id) in backend_ids {
+            if let Some(fork) = self.forks.get_mut(&fork_id) {
+                fork.backend_id = backend_id;
+            }
         }
+        self.rpc_caches = rpc_caches;
+        Ok(())
     }
 
     /// Calls the forked EVM without committing to state using alloy typed arguments.
@@ -237,26 +844,30 @@ impl Forker {
     /// * `decode_error` - Whether to decode revert data using the error selector registry.
     /// # Returns
     /// A result containing the raw call result and the typed return.
-    pub async fn alloy_call<T: SolCall>(
+    pub async fn alloy_call<T>(
         &self,
         from_address: Address,
         to_address: Address,
         call: T,
         decode_error: bool,
-    ) -> Result<ForkTypedReturn<T>, ForkCallError> {
-        let raw = self.call(
-            from_address.as_slice(),
-            to_address.as_slice(),
-            &call.abi_encode(),
-        )?;
-
-        if decode_error && raw.exit_reason == InstructionResult::Revert {
-            // decode result bytes to error selectors if it was a revert
-            return Err(ForkCallError::AbiDecodedError(
-                AbiDecodedErrorType::selector_registry_abi_decode(&raw.result, None).await?,
-            ));
-        }
+    ) -> Result<ForkTypedReturn<T>, ForkCallError>
+    where
+        T: SolCall + Send + 'static,
+        T::Return: Send,
+    {
+        let result = self
+            .run_blocking(move |forker, cancel| {
+                forker.typed_call(from_address, to_address, &call, cancel)
+            })
+            .await;
+        decode_revert(result, decode_error).await
+    }
 
+    /// Decodes the typed return of a raw call result, leaving a failed call
+    /// as [`ForkCallError::Failed`].
+    pub(crate) fn decode_return<T: SolCall>(
+        raw: RawCallResult,
+    ) -> Result<ForkTypedReturn<T>, ForkCallError> {
         if !raw.exit_reason.is_ok() {
             return Err(raw.into());
         }
@@ -272,6 +883,25 @@ impl Forker {
         Ok(ForkTypedReturn { raw, typed_return })
     }
 
+    /// Calls the forked EVM like [`Forker::alloy_call`], but on the current
+    /// thread and without decoding reverts.
+    pub(crate) fn typed_call<T: SolCall>(
+        &self,
+        from_address: Address,
+        to_address: Address,
+        call: &T,
+        cancel: &Cancel<'_>,
+    ) -> Result<ForkTypedReturn<T>, ForkCallError> {
+        let env = self.call_env(
+            from_address,
+            to_address,
+            call.abi_encode().into(),
+            U256::ZERO,
+            &EnvOverrides::default(),
+        );
+        Self::decode_return(self.call_in_env(env, None, cancel)?)
+    }
+
     /// Writes to the forked EVM using alloy typed arguments.
     /// # Arguments
     /// * `from_address` - The address to call from.
@@ -289,33 +919,10 @@ impl Forker {
         value: U256,
         decode_error: bool,
     ) -> Result<ForkTypedReturn<T>, ForkCallError> {
-        let raw: RawCallResult = self.call_committing(
-            from_address.as_slice(),
-            to_address.as_slice(),
-            &call.abi_encode(),
-            value,
-        )?;
-
-        if decode_error && raw.exit_reason == InstructionResult::Revert {
-            // decode result bytes to error selectors if it was a revert
-            return Err(ForkCallError::AbiDecodedError(
-                AbiDecodedErrorType::selector_registry_abi_decode(&raw.result, None).await?,
-            ));
-        }
-
-        if !raw.exit_reason.is_ok() {
-            return Err(raw.into());
-        }
-
-        let typed_return = T::abi_decode_returns(&raw.result.0).map_err(|e| {
-            ForkCallError::TypedError(format!(
-                "Call:{:?} Error:{:?} Raw:{:?}",
-                type_name::<T>(),
-                e,
-                raw
-            ))
-        })?;
-        Ok(ForkTypedReturn { raw, typed_return })
+        let result = self
+            .spawn_call_committing(from_address, to_address, &call.abi_encode(), value)
+            .await;
+        decode_revert(result.and_then(Self::decode_return), decode_error).await
     }
 
     /// Calls the forked EVM without committing to state.
@@ -335,14 +942,175 @@ impl Forker {
             return Err(ForkCallError::ExecutorError("invalid address!".to_owned()));
         }
 
-        self.executor
-            .call_raw(
-                Addr::from_slice(from_address),
-                Addr::from_slice(to_address),
-                Bytes::copy_from_slice(calldata),
-                U256::from(0),
-            )
-            .map_err(|e| ForkCallError::ExecutorError(e.to_string()))
+        self.call_with_env(
+            Addr::from_slice(from_address),
+            Addr::from_slice(to_address),
+            calldata,
+            &EnvOverrides::default(),
+        )
+    }
+
+    /// Reads from the forked EVM like [`Forker::call`], with the environment
+    /// overridden for this call only.
+    pub fn call_with_env(
+        &self,
+        from_address: Address,
+        to_address: Address,
+        calldata: &[u8],
+        overrides: &EnvOverrides,
+    ) -> Result<RawCallResult, ForkCallError> {
+        let env = self.call_env(
+            from_address,
+            to_address,
+            Bytes::copy_from_slice(calldata),
+            U256::ZERO,
+            overrides,
+        );
+        self.call_in_env(env, None, &Cancel::never())
+    }
+
+    /// The environment of a call from `from_address`, overridden for this
+    /// call only.
+    pub(crate) fn call_env(
+        &self,
+        from_address: Address,
+        to_address: Address,
+        calldata: Bytes,
+        value: U256,
+        overrides: &EnvOverrides,
+    ) -> Env {
+        let mut env =
+            self.executor
+                .build_test_env(from_address, TxKind::Call(to_address), calldata, value);
+        overrides.apply(&mut env);
+        env
+    }
+
+    /// Runs `env` on the current thread without committing, with the
+    /// inspectors tracing in `mode` if set, halting once `cancel` says so.
+    ///
+    /// Every call of the forker runs here: the sync calls directly, and the
+    /// async ones through [`Forker::run_blocking`].
+    pub(crate) fn call_in_env(
+        &self,
+        env: Env,
+        mode: Option<TraceMode>,
+        cancel: &Cancel<'_>,
+    ) -> Result<RawCallResult, ForkCallError> {
+        let mut inspector = self.executor.inspector().clone();
+        if let Some(mode) = mode {
+            inspector.tracing(mode);
+        }
+        let raw = cancel::call(&self.executor, inspector, env, cancel).map_err(executor_error)?;
+        self.check_offline(&raw)?;
+        Ok(raw)
+    }
+
+    /// Reads from the forked EVM like [`Forker::call_with_env`], on a blocking
+    /// thread and within the call timeout.
+    pub async fn spawn_call(
+        &self,
+        from_address: Address,
+        to_address: Address,
+        calldata: &[u8],
+        overrides: &EnvOverrides,
+    ) -> Result<RawCallResult, ForkCallError> {
+        let env = self.call_env(
+            from_address,
+            to_address,
+            Bytes::copy_from_slice(calldata),
+            U256::ZERO,
+            overrides,
+        );
+        self.run_blocking(move |forker, cancel| forker.call_in_env(env, None, cancel))
+            .await
+    }
+
+    /// Reads from the forked EVM like [`Forker::spawn_call`], recording
+    /// traces in `mode` for this call only.
+    pub(crate) async fn spawn_call_with_tracing(
+        &self,
+        from_address: Address,
+        to_address: Address,
+        calldata: &[u8],
+        overrides: &EnvOverrides,
+        mode: TraceMode,
+    ) -> Result<RawCallResult, ForkCallError> {
+        let env = self.call_env(
+            from_address,
+            to_address,
+            Bytes::copy_from_slice(calldata),
+            U256::ZERO,
+            overrides,
+        );
+        self.run_blocking(move |forker, cancel| forker.call_in_env(env, Some(mode), cancel))
+            .await
+    }
+
+    /// Writes to the forked EVM like [`Forker::call_committing`], on a
+    /// blocking thread and within the call timeout. Nothing is committed if
+    /// the call times out.
+    pub async fn spawn_call_committing(
+        &mut self,
+        from_address: Address,
+        to_address: Address,
+        calldata: &[u8],
+        value: U256,
+    ) -> Result<RawCallResult, ForkCallError> {
+        let env = self.call_env(
+            from_address,
+            to_address,
+            Bytes::copy_from_slice(calldata),
+            value,
+            &EnvOverrides::default(),
+        );
+        let result = self
+            .run_blocking(move |forker, cancel| forker.call_in_env(env, None, cancel))
+            .await;
+        self.commit(to_address, result)
+    }
+
+    /// Runs `f` with a clone of this forker on a blocking thread, within the
+    /// call timeout. The clone shares the executor, so nothing is copied.
+    ///
+    /// The calls `f` makes halt at their next step once the call timeout
+    /// passes or the returned future is dropped, and their result is then
+    /// discarded.
+    pub(crate) async fn run_blocking<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Forker, &Cancel<'_>) -> Result<R, ForkCallError> + Send + 'static,
+    ) -> Result<R, ForkCallError> {
+        let forker = self.clone();
+        let timeout = self.call_timeout;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _cancel_on_drop = CancelOnDrop(cancelled.clone());
+        let task = tokio::task::spawn_blocking(move || {
+            let cancel = Cancel::new(&cancelled, timeout);
+            cancel.check(f(&forker, &cancel))
+        });
+        // The EVM halts itself at the deadline, but the task may be held up
+        // elsewhere, such as waiting for a blocking thread.
+        let joined = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, task)
+                .await
+                .map_err(|_| ForkCallError::Timeout(timeout))?,
+            None => task.await,
+        };
+        joined.map_err(|e| match e.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            Err(e) => ForkCallError::ExecutorError(e.to_string()),
+        })?
+    }
+
+    /// The longest the async calls wait for the EVM, if limited.
+    pub fn call_timeout(&self) -> Option<Duration> {
+        self.call_timeout
+    }
+
+    /// Limits how long each async call waits for the EVM before failing
+    /// with [`ForkCallError::Timeout`]. `None` waits indefinitely.
+    pub fn set_call_timeout(&mut self, timeout: Option<Duration>) {
+        self.call_timeout = timeout;
     }
 
     /// Writes to the forked EVM.
@@ -364,22 +1132,81 @@ impl Forker {
             return Err(ForkCallError::ExecutorError("invalid address!".to_owned()));
         }
 
+        let to_address = Addr::from_slice(to_address);
+        let env = self.call_env(
+            Addr::from_slice(from_address),
+            to_address,
+            Bytes::copy_from_slice(calldata),
+            value,
+            &EnvOverrides::default(),
+        );
+        let result = self.call_in_env(env, None, &Cancel::never());
+        self.commit(to_address, result)
+    }
+
+    /// Commits the state of a call to `to_address` that ran without
+    /// committing. The executor is only copied if it is shared with a clone
+    /// of this forker.
+    fn commit(
+        &mut self,
+        to_address: Address,
+        result: Result<RawCallResult, ForkCallError>,
+    ) -> Result<RawCallResult, ForkCallError> {
+        let backend = self.executor_mut().backend_mut();
+        if let Ok(raw) = &result {
+            backend.commit(raw.state_changeset.clone());
+        }
+        // remove to_address from persisted accounts
+        backend.remove_persistent_account(&to_address);
+
+        let raw = result?;
+        self.commit_offline(&raw)?;
+        Ok(raw)
+    }
+
+    /// Deploys a contract to the forked EVM, committing it to state.
+    /// # Arguments
+    /// * `from_address` - The address to deploy from.
+    /// * `code` - The creation code, including encoded constructor arguments.
+    /// # Returns
+    /// A result containing the address of the deployed contract.
+    pub fn deploy(&mut self, from_address: Address, code: &[u8]) -> Result<Address, ForkCallError> {
         let result = self
-            .executor
-            .transact_raw(
-                Addr::from_slice(from_address),
-                Addr::from_slice(to_address),
-                Bytes::copy_from_slice(calldata),
-                value,
+            .executor_mut()
+            .deploy(
+                from_address,
+                Bytes::copy_from_slice(code),
+                U256::from(0),
+                None,
             )
-            .map_err(|e| ForkCallError::ExecutorError(e.to_string()));
+            .map_err(|e| ForkCallError::ExecutorError(e.to_string()))?;
+        self.commit_offline(&result.raw)?;
+        Ok(result.address)
+    }
 
-        // remove to_address from persisted accounts
-        self.executor
-            .backend_mut()
-            .remove_persistent_account(&Addr::from_slice(to_address));
+    /// Fails if a call of an offline forker loaded state missing from its
+    /// cache, which its database reads as empty.
+    fn check_offline(&self, raw: &RawCallResult) -> Result<(), ForkCallError> {
+        match &self.offline {
+            Some(offline) => offline.check(&raw.state_changeset),
+            None => Ok(()),
+        }
+    }
 
-        result
+    /// Like [`Forker::check_offline`] for a call that committed its state,
+    /// which is known to the offline forker from then on.
+    fn commit_offline(&mut self, raw: &RawCallResult) -> Result<(), ForkCallError> {
+        if let Some(offline) = &mut self.offline {
+            offline.check(&raw.state_changeset)?;
+            Arc::make_mut(offline).commit(&raw.state_changeset);
+        }
+        Ok(())
+    }
+
+    /// The executor, to be mutated. It is copied first if it is shared with
+    /// a clone of this forker or with an async call in flight.
+    pub fn executor_mut(&mut self) -> &mut Executor {
+        Arc::make_mut(&mut self.executor)
     }
 
     /// resets the active fork to a given block number or to original fork block number if not provided
@@ -388,35 +1215,81 @@ impl Forker {
         block_number: Option<BlockNumber>,
         env: Option<Env>,
     ) -> Result<(), ForkCallError> {
-        let active_fork_local_id = self
+        let active_backend_id = self
             .executor
             .backend()
             .active_fork_id()
             .ok_or(ForkCallError::ExecutorError("no active fork!".to_owned()))?;
-        let mut org_block_number = None;
-        let mut spec_id = SpecId::default();
-        for (local_id, sid, bnumber) in self.forks.values() {
-            if *local_id == active_fork_local_id {
-                spec_id = *sid;
-                org_block_number = Some(*bnumber);
-                break;
-            }
-        }
-        let org_block_number =
-            org_block_number.ok_or(ForkCallError::ExecutorError("no active fork!".to_owned()))?;
+        let (local_id, org_block_number, spec_id) = self
+            .active_fork()
+            .map(|fork| (fork.local_id, fork.fork_block_number, fork.spec_id))
+            .ok_or(ForkCallError::ExecutorError("no active fork!".to_owned()))?;
         let block_number = block_number.unwrap_or(org_block_number);
 
-        self.executor.env_mut().evm_env.block_env.number = block_number;
-
-        self.executor
+        let mut env = env.unwrap_or_default();
+        self.executor_mut()
             .backend_mut()
             .roll_fork(
-                Some(active_fork_local_id),
+                Some(active_backend_id),
                 block_number,
-                &mut mk_env_mut(&mut env.unwrap_or_default()),
+                &mut mk_env_mut(&mut env),
                 &mut mk_journaled_state(spec_id),
             )
-            .map_err(|v| ForkCallError::ExecutorError(v.to_string()))
+            .map_err(|v| ForkCallError::ExecutorError(v.to_string()))?;
+
+        self.executor_mut().env_mut().evm_env.block_env.number = block_number;
+
+        // Rolling discards code committed locally, so nothing resolved on
+        // this fork can be trusted anymore.
+        self.cache().invalidate_fork(Some(local_id));
+
+        // Rolling loads the new block into `env`, which may cross a hardfork.
+        let chain_id = self.executor.env().evm_env.cfg_env.chain_id;
+        let spec_id = self.spec_id_override.unwrap_or_else(|| {
+            hardfork::spec_id(chain_id, block_number, env.evm_env.block_env.timestamp)
+        });
+        self.set_active_spec_id(spec_id);
+        if let Some(fork) = self.active_fork_mut() {
+            fork.block_number = block_number;
+        }
+        Ok(())
+    }
+
+    /// The EVM spec calls run with on the active fork.
+    pub fn spec_id(&self) -> SpecId {
+        self.executor.env().evm_env.cfg_env.spec
+    }
+
+    /// Pins the EVM spec of the active fork and of forks added later,
+    /// instead of following their chain's hardfork schedule. `None` goes
+    /// back to the schedule.
+    pub fn set_spec_id(&mut self, spec_id: Option<SpecId>) {
+        self.spec_id_override = spec_id;
+        let env = self.executor.env();
+        let spec_id = spec_id.unwrap_or_else(|| {
+            hardfork::spec_id(
+                env.evm_env.cfg_env.chain_id,
+                env.evm_env.block_env.number,
+                env.evm_env.block_env.timestamp,
+            )
+        });
+        self.set_active_spec_id(spec_id);
+    }
+
+    /// Sets the spec of the executor and records it for the active fork.
+    fn set_active_spec_id(&mut self, spec_id: SpecId) {
+        self.executor_mut().env_mut().evm_env.cfg_env.spec = spec_id;
+        if let Some(fork) = self.active_fork_mut() {
+            fork.spec_id = spec_id;
+        }
+    }
+
+    fn active_fork_mut(&mut self) -> Option<&mut ForkInfo> {
+        let active_backend_id = self.executor.backend().active_fork_id()?;
+        self.forks
+            .values_mut()
+            .find(|fork| fork.backend_id == active_backend_id)
+            .map(|fork| &mut fork.info)
     }
 
     /// Replays a transaction from the forked EVM.
@@ -468,60 +1341,71 @@ impl Forker {
                         fork_url.clone(),
                     ),
                 )?),
+                rpc_cache: None,
+                rpc: Default::default(),
             },
             None,
         )
         .await?;
 
         // matching env to the env from the block the transaction is in
-        self.executor.env_mut().evm_env.block_env.number = block_number;
-        self.executor.env_mut().evm_env.block_env.timestamp = block.header.timestamp;
-        self.executor.env_mut().evm_env.block_env.beneficiary = block.header.beneficiary;
-        self.executor.env_mut().evm_env.block_env.difficulty = block.header.difficulty;
-        self.executor.env_mut().evm_env.block_env.prevrandao =
-            Some(block.header.mix_hash.unwrap_or_default());
-        self.executor.env_mut().evm_env.block_env.basefee =
-            block.header.base_fee_per_gas.unwrap_or_default();
-        self.executor.env_mut().evm_env.block_env.gas_limit = block.header.gas_limit;
-
-        let active_fork_local_id = self
-            .executor
-            .backend()
-            .active_fork_id()
-            .ok_or(ForkCallError::ExecutorError("no active fork!".to_owned()))?;
-
-        let mut journaled_state = JournaledState::new();
-
-        let env = self.executor.env().clone();
-
-        // replay all transactions that came before
-        let tx = self.executor.backend_mut().replay_until(
-            active_fork_local_id,
-            env,
-            tx_hash,
-            &mut journaled_state,
-        )?;
-
-        let res = match tx {
-            // if to field is None, it means the tx was a contract deployment, see 'revm::primitives::TxKind'
-            Some(tx) => match tx.inner.kind() {
-                TxKind::Call(to) => self.call(
-                    tx.inner.signer().as_slice(),
-                    to.as_slice(),
-                    tx.inner.input(),
-                )?,
-                TxKind::Create => {
-                    self.call(tx.inner.signer().as_slice(), &[0u8; 20], tx.inner.input())?
-                }
-            },
-            None => {
-                return Err(ForkCallError::ReplayTransactionError(
-                    ReplayTransactionError::TransactionNotFound(tx_hash.to_string(), fork_url),
-                ));
+        let block_env = &mut self.executor_mut().env_mut().evm_env.block_env;
+        block_env.number = block_number;
+        block_env.timestamp = block.header.timestamp;
+        block_env.beneficiary = block.header.beneficiary;
+        block_env.difficulty = block.header.difficulty;
+        block_env.prevrandao = Some(block.header.mix_hash.unwrap_or_default());
+        block_env.basefee = block.header.base_fee_per_gas.unwrap_or_default();
+        block_env.gas_limit = block.header.gas_limit;
+
+        // The transaction's block may be the first of a new hardfork.
+        let chain_id = self.executor.env().evm_env.cfg_env.chain_id;
+        let spec_id = self
+            .spec_id_override
+            .unwrap_or_else(|| hardfork::spec_id(chain_id, block_number, block.header.timestamp));
+        self.executor_mut().env_mut().evm_env.cfg_env.spec = spec_id;
+
+        // Replays the transactions that came before one at a time, each
+        // committed once it returns like the committing calls, so the
+        // executor is not copied and nothing is committed past a timeout.
+        for tx in block.transactions.txns() {
+            let tx = &tx.inner;
+            if tx.tx_hash() == tx_hash {
+                // if to field is None, it means the tx was a contract deployment, see 'revm::primitives::TxKind'
+                let to_address = match tx.inner.kind() {
+                    TxKind::Call(to) => to,
+                    TxKind::Create => Address::ZERO,
+                };
+                let env = self.call_env(
+                    tx.inner.signer(),
+                    to_address,
+                    tx.inner.input().clone(),
+                    U256::ZERO,
+                    &EnvOverrides::default(),
+                );
+                return self
+                    .run_blocking(move |forker, cancel| forker.call_in_env(env, None, cancel))
+                    .await;
             }
-        };
+            // Deposit transactions carry no pricing info and would revert,
+            // so they are skipped like the backend's own replay does.
+            if tx.ty() == DEPOSIT_TRANSACTION_TYPE {
+                continue;
+            }
+
+            let mut env = self.executor.env().clone();
+            configure_tx_env(&mut mk_env_mut(&mut env), tx);
+            let raw = self
+                .run_blocking(move |forker, cancel| forker.call_in_env(env, None, cancel))
+                .await?;
+            self.executor_mut()
+                .backend_mut()
+                .commit(raw.state_changeset);
+        }
 
-        Ok(res)
+        Err(ForkCallError::ReplayTransactionError(
+            ReplayTransactionError::TransactionNotFound(tx_hash.to_string(), fork_url),
+        ))
     }
 }
 
@@ -531,12 +1415,16 @@ mod tests {
     use crate::namespace::qualify_namespace;
     use alloy::eips::BlockNumberOrTag;
     use alloy::sol;
+    use alloy::sol_types::SolConstructor;
     use alloy::{
         primitives::{FixedBytes, U256},
         providers::Provider,
     };
     use rain_interpreter_bindings::IInterpreterStoreV3::{getCall, setCall};
-    use rain_interpreter_test_fixtures::LocalEvm;
+    use rain_interpreter_test_fixtures::{ERC20, LocalEvm};
+    use revm::bytecode::Bytecode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     sol! {
         interface IERC20 {
@@ -554,6 +1442,39 @@ mod tests {
         }
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_deploy() {
+        let mut forker = Forker::new().unwrap();
+        let recipient = Address::repeat_byte(0x01);
+        let code = [
+            ERC20::BYTECODE.as_ref(),
+            &ERC20::constructorCall {
+                name_: "Token".into(),
+                symbol_: "TKN".into(),
+                decimals_: 18,
+                recipient_: recipient,
+                supply_: U256::from(1000),
+            }
+            .abi_encode(),
+        ]
+        .concat();
+
+        let token = forker.deploy(recipient, &code).unwrap();
+        assert_ne!(token, Address::ZERO);
+
+        let balance = forker
+            .alloy_call(
+                recipient,
+                token,
+                IERC20::balanceOfCall { account: recipient },
+                false,
+            )
+            .await
+            .unwrap()
+            .typed_return;
+        assert_eq!(balance, U256::from(1000));
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_forker_read() {
         let local_evm = LocalEvm::new().await;
@@ -561,6 +1482,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
 
         let forker = Forker::new_with_fork(args, None, None).await.unwrap();
@@ -582,6 +1505,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let mut forker = Forker::new_with_fork(args, None, None).await.unwrap();
 
@@ -631,6 +1556,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm1.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let mut forker = Forker::new_with_fork(args, None, None).await.unwrap();
 
@@ -684,6 +1611,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm2.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         forker.add_or_select(args, None).await?;
 
@@ -732,6 +1661,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm1.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         forker.add_or_select(args, None).await?;
 
@@ -770,6 +1701,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: Some(block_number),
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let mut forker = Forker::new_with_fork(args, None, None).await.unwrap();
 
@@ -803,6 +1736,8 @@ mod tests {
             NewForkedEvm {
                 fork_url: local_evm.url(),
                 fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
             },
             None,
             None,
@@ -929,6 +1864,8 @@ mod tests {
                 NewForkedEvm {
                     fork_url: local_evm.url(),
                     fork_block_number: None,
+                    rpc_cache: None,
+                    rpc: Default::default(),
                 },
                 None,
             )
@@ -947,4 +1884,412 @@ mod tests {
             .unwrap();
         assert!(result.typed_return);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_spec_id() {
+        let local_evm = LocalEvm::new().await;
+        let mut forker = Forker::new_with_fork(
+            NewForkedEvm {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Local chains have no known schedule so they get the latest spec.
+        assert_eq!(forker.spec_id(), SpecId::default());
+
+        forker.set_spec_id(Some(SpecId::SHANGHAI));
+        assert_eq!(forker.spec_id(), SpecId::SHANGHAI);
+        assert!(
+            forker
+                .forks
+                .values()
+                .all(|fork| fork.spec_id == SpecId::SHANGHAI)
+        );
+
+        // The pinned spec survives rolling the fork.
+        let block_number = forker.executor.env().evm_env.block_env.number;
+        forker.roll_fork(Some(block_number), None).unwrap();
+        assert_eq!(forker.spec_id(), SpecId::SHANGHAI);
+
+        forker.set_spec_id(None);
+        assert_eq!(forker.spec_id(), SpecId::default());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fork_lifecycle() {
+        let a = LocalEvm::new().await;
+        let b = LocalEvm::new().await;
+        let fork_a = NewForkedEvm {
+            fork_url: a.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork_b = NewForkedEvm {
+            fork_url: b.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+
+        let mut forker = Forker::new_with_fork(fork_a.clone(), None, None)
+            .await
+            .unwrap();
+        forker.add_or_select(fork_b, None).await.unwrap();
+
+        let forks = forker.forks();
+        assert_eq!(forks.len(), 2);
+        assert_eq!(forks[0].url, a.url());
+        assert_eq!(forks[1].url, b.url());
+        assert_ne!(forks[0].local_id, forks[1].local_id);
+        let active = forker.active_fork().unwrap();
+        assert_eq!(active, forks[1]);
+
+        // Rolling moves the active fork's current block only.
+        let rolled_to = active.fork_block_number - 1;
+        forker.roll_fork(Some(rolled_to), None).unwrap();
+        let active = forker.active_fork().unwrap();
+        assert_eq!(active.block_number, rolled_to);
+        assert_eq!(active.fork_block_number, forks[1].fork_block_number);
+
+        assert!(matches!(
+            forker.remove_fork(active.local_id),
+            Err(ForkCallError::RemoveActiveFork(id)) if id == active.local_id
+        ));
+
+        // The remaining fork keeps its id, block and local state when the
+        // backend is rebuilt without the removed one.
+        let account = Address::repeat_byte(0x42);
+        forker
+            .executor_mut()
+            .set_balance(account, U256::from(5))
+            .unwrap();
+        assert_eq!(forker.remove_fork(forks[0].local_id).unwrap(), forks[0]);
+        assert!(matches!(
+            forker.remove_fork(forks[0].local_id),
+            Err(ForkCallError::UnknownFork(_))
+        ));
+        assert_eq!(forker.forks(), vec![active.clone()]);
+        assert_eq!(forker.active_fork().unwrap(), active);
+        assert_eq!(forker.executor.get_balance(account).unwrap(), U256::from(5));
+        assert_eq!(forker.executor.env().evm_env.block_env.number, rolled_to);
+
+        // Adding the removed fork back gives it a fresh id.
+        forker.add_or_select(fork_a, None).await.unwrap();
+        let readded = forker.active_fork().unwrap();
+        assert_eq!(readded.url, a.url());
+        assert_ne!(readded.local_id, forks[0].local_id);
+        assert_ne!(readded.local_id, forks[1].local_id);
+        assert_eq!(forker.forks().len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_rpc_cache() {
+        let local_evm = LocalEvm::new().await;
+        let deployer = *local_evm.deployer.address();
+        let block_number = local_evm.provider.get_block_number().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let args = |offline, fork_block_number| NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number,
+            rpc_cache: Some(RpcCache {
+                dir: dir.path().to_path_buf(),
+                offline,
+            }),
+            rpc: Default::default(),
+        };
+        let call = IERC165::supportsInterfaceCall {
+            interfaceId: FixedBytes([0x01, 0xff, 0xc9, 0xa7]),
+        };
+
+        // Nothing is cached yet.
+        assert!(matches!(
+            Forker::new_with_fork(args(true, Some(block_number)), None, None).await,
+            Err(ForkCallError::OfflineCacheMiss(_))
+        ));
+        assert!(matches!(
+            Forker::new_with_fork(args(true, None), None, None).await,
+            Err(ForkCallError::OfflineUnpinned)
+        ));
+
+        let forker = Forker::new_with_fork(args(false, Some(block_number)), None, None)
+            .await
+            .unwrap();
+        let online = forker
+            .alloy_call(Address::default(), deployer, call.clone(), false)
+            .await
+            .unwrap();
+        drop(forker);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // The offline forker has no RPC, so the deployer code comes from the
+        // cache.
+        let forker = Forker::new_with_fork(args(true, Some(block_number)), None, None)
+            .await
+            .unwrap();
+        let offline = forker
+            .alloy_call(Address::default(), deployer, call, false)
+            .await
+            .unwrap();
+        assert_eq!(offline.typed_return, online.typed_return);
+        assert!(offline.typed_return);
+        assert_eq!(forker.executor.env().evm_env.block_env.number, block_number);
+        forker.save_rpc_caches().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_rpc_cache_offline_miss() {
+        let local_evm = LocalEvm::new().await;
+        let store = *local_evm.store.address();
+        let block_number = local_evm.provider.get_block_number().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let args = |offline| NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: Some(block_number),
+            rpc_cache: Some(RpcCache {
+                dir: dir.path().to_path_buf(),
+                offline,
+            }),
+            rpc: Default::default(),
+        };
+        let get = |key: u8| getCall {
+            namespace: U256::ZERO.into(),
+            key: <FixedBytes<32>>::left_padding_from(&[key]),
+        };
+
+        let forker = Forker::new_with_fork(args(false), None, None)
+            .await
+            .unwrap();
+        forker
+            .alloy_call(Address::default(), store, get(1), false)
+            .await
+            .unwrap();
+        drop(forker);
+
+        let forker = Forker::new_with_fork(args(true), None, None).await.unwrap();
+        forker
+            .alloy_call(Address::default(), store, get(1), false)
+            .await
+            .unwrap();
+        // The slot of key 2 was never fetched, so it must not read as 0.
+        assert!(matches!(
+            forker
+                .alloy_call(Address::default(), store, get(2), false)
+                .await,
+            Err(ForkCallError::OfflineStateMiss(..))
+        ));
+        // Neither was this account.
+        assert!(matches!(
+            forker
+                .alloy_call(
+                    Address::default(),
+                    *local_evm.parser.address(),
+                    get(1),
+                    false
+                )
+                .await,
+            Err(ForkCallError::OfflineStateMiss(..))
+        ));
+    }
+
+    /// URL of a port nothing listens on.
+    async fn dead_url() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    /// A stand-in RPC that answers the first `failures` connections with a
+    /// 503 and forwards the rest to `url`. Returns its URL and the number of
+    /// failures left to inject.
+    async fn flaky_rpc(url: &str, failures: usize) -> (String, Arc<AtomicUsize>) {
+        let target = url.trim_start_matches("http://").to_owned();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let remaining = Arc::new(AtomicUsize::new(failures));
+        let failures_left = remaining.clone();
+        tokio::spawn(async move {
+            while let Ok((mut inbound, _)) = listener.accept().await {
+                let fail = failures_left
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok();
+                let target = target.clone();
+                tokio::spawn(async move {
+                    if fail {
+                        let mut request = [0u8; 4096];
+                        let _ = inbound.read(&mut request).await;
+                        let _ = inbound
+                            .write_all(
+                                b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                            )
+                            .await;
+                    } else {
+                        let mut outbound = tokio::net::TcpStream::connect(target).await.unwrap();
+                        let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    }
+                });
+            }
+        });
+        (proxy_url, remaining)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_rpc_fallback() {
+        let local_evm = LocalEvm::new().await;
+        let (dead_a, dead_b) = (dead_url().await, dead_url().await);
+        let args = |fork_url: &str, fallback_urls: Vec<String>| NewForkedEvm {
+            fork_url: fork_url.to_owned(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: RpcOptions {
+                fallback_urls,
+                ..Default::default()
+            },
+        };
+
+        let forker = Forker::new_with_fork(args(&dead_a, vec![local_evm.url()]), None, None)
+            .await
+            .unwrap();
+        assert_eq!(forker.active_fork().unwrap().url, local_evm.url());
+        let call = IERC165::supportsInterfaceCall {
+            interfaceId: FixedBytes([0x01, 0xff, 0xc9, 0xa7]),
+        };
+        let result = forker
+            .alloy_call(
+                Address::default(),
+                *local_evm.deployer.address(),
+                call,
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(result.typed_return);
+
+        match Forker::new_with_fork(args(&dead_a, vec![]), None, None).await {
+            Err(ForkCallError::Rpc(RpcError::Connect { url, .. })) => assert_eq!(url, dead_a),
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("forked from a dead url"),
+        }
+        match Forker::new_with_fork(args(&dead_a, vec![dead_b.clone()]), None, None).await {
+            Err(ForkCallError::Rpc(RpcError::AllUrlsFailed(failures))) => {
+                assert_eq!(failures.len(), 2);
+                assert!(failures[1].to_string().contains(&dead_b));
+            }
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("forked from a dead url"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_rpc_retries() {
+        let local_evm = LocalEvm::new().await;
+        let (url, failures_left) = flaky_rpc(&local_evm.url(), 2).await;
+        let args = NewForkedEvm {
+            fork_url: url.clone(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: RpcOptions {
+                retries: Some(3),
+                retry_backoff: Some(Duration::from_millis(10)),
+                compute_units_per_second: Some(10_000),
+                ..Default::default()
+            },
+        };
+
+        let forker = Forker::new_with_fork(args, None, None).await.unwrap();
+        assert_eq!(failures_left.load(Ordering::SeqCst), 0);
+        assert_eq!(forker.active_fork().unwrap().url, url);
+        let call = IERC165::supportsInterfaceCall {
+            interfaceId: FixedBytes([0x01, 0xff, 0xc9, 0xa7]),
+        };
+        let result = forker
+            .alloy_call(
+                Address::default(),
+                *local_evm.deployer.address(),
+                call,
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(result.typed_return);
+    }
+
+    // The default current thread runtime, as the EVM runs off the runtime.
+    #[tokio::test]
+    async fn test_call_timeout() {
+        // JUMPDEST PUSH1 0 JUMP, which loops until it runs out of gas.
+        let looping = Bytecode::new_raw(Bytes::from_static(&[0x5b, 0x60, 0x00, 0x56]));
+        let address = Address::repeat_byte(0x1);
+        let looping_forker = |gas_limit| {
+            Forker::new_with_state(
+                local_evm_env(),
+                SpecId::default(),
+                [(
+                    address,
+                    AccountInfo {
+                        code_hash: looping.hash_slow(),
+                        code: Some(looping.clone()),
+                        ..Default::default()
+                    },
+                )],
+                [],
+                Some(gas_limit),
+            )
+            .unwrap()
+        };
+        let overrides = EnvOverrides::default();
+        let timeout = Duration::from_millis(1);
+
+        let mut forker = looping_forker(30_000_000);
+        let raw = forker
+            .spawn_call(Address::ZERO, address, &[], &overrides)
+            .await
+            .unwrap();
+        assert!(!raw.exit_reason.is_ok());
+
+        // The sync calls are not limited.
+        forker.set_call_timeout(Some(timeout));
+        assert_eq!(forker.call_timeout(), Some(timeout));
+        let raw = forker
+            .call_with_env(Address::ZERO, address, &[], &overrides)
+            .unwrap();
+        assert!(!raw.exit_reason.is_ok());
+
+        // With gas for hours of looping only the timeout stops the call. Were
+        // it not halted, the runtime would wait for it on shutdown, so the
+        // test would hang rather than pass.
+        let mut forker = looping_forker(1 << 40);
+        forker.set_call_timeout(Some(timeout));
+        assert!(matches!(
+            forker.spawn_call(Address::ZERO, address, &[], &overrides).await,
+            Err(ForkCallError::Timeout(t)) if t == timeout
+        ));
+        assert!(matches!(
+            forker
+                .spawn_call_committing(Address::ZERO, address, &[], U256::ZERO)
+                .await,
+            Err(ForkCallError::Timeout(_))
+        ));
+
+        // A cancelled call halts at its first step instead of looping.
+        let env = forker.call_env(Address::ZERO, address, Bytes::new(), U256::ZERO, &overrides);
+        let cancelled = AtomicBool::new(true);
+        let raw = forker
+            .call_in_env(env, Some(TraceMode::Steps), &Cancel::new(&cancelled, None))
+            .unwrap();
+        assert!(!raw.exit_reason.is_ok());
+        let steps: usize = raw
+            .traces
+            .iter()
+            .flat_map(|traces| traces.arena.nodes())
+            .map(|node| node.trace.steps.len())
+            .sum();
+        assert!(steps <= 1);
+    }
 }
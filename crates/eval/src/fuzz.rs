@@ -0,0 +1,769 @@
+use crate::error::ForkCallError;
+use crate::eval::ForkEvalArgs;
+use crate::float::DecimalFloat;
+use crate::fork::Forker;
+use crate::suite::{TestCaseError, parse_word};
+use crate::trace::{RainEvalOutcome, RainEvalResult};
+use alloy::primitives::{Address, I256, U256};
+use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Upper bound on the evals spent shrinking a failing case.
+const MAX_SHRINK_EVALS: usize = 1000;
+
+/// One in this many draws is the minimum or maximum of its range, as bounds
+/// are where invariants most often break.
+const EDGE_CASE_ODDS: u64 = 8;
+
+fn default_runs() -> u32 {
+    256
+}
+
+/// An expression to fuzz and the ranges its inputs and context are drawn
+/// from. A run fails when the eval reverts, typically on an `ensure`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzSpec {
+    /// The Rainlang contract to evaluate against, unless overridden by the
+    /// caller.
+    #[serde(default)]
+    pub rainlang: Option<Address>,
+    pub expression: String,
+    #[serde(default)]
+    pub source_index: u16,
+    #[serde(default)]
+    pub inputs: Vec<FuzzRange>,
+    #[serde(default)]
+    pub context: Vec<Vec<FuzzRange>>,
+    #[serde(default = "default_runs")]
+    pub runs: u32,
+    /// Seed of the random draws. A random seed is picked and reported when
+    /// unset, so a failure can be reproduced.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// The values a single input or context item is drawn from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FuzzRange {
+    /// Integers from `min` to `max` inclusive, as decimal or 0x-prefixed hex.
+    Int {
+        min: String,
+        max: String,
+    },
+    /// Decimal floats from `min` to `max` inclusive, with up to `decimals`
+    /// fractional digits.
+    Float {
+        min: String,
+        max: String,
+        #[serde(default)]
+        decimals: u8,
+    },
+    Address,
+    /// One of a fixed list of values. With `float` decimal values are Rain
+    /// decimal floats.
+    OneOf {
+        values: Vec<String>,
+        #[serde(default)]
+        float: bool,
+    },
+}
+
+/// Errors that stop a fuzz run.
+#[derive(Error, Debug)]
+pub enum FuzzError {
+    #[error("Invalid fuzz range: {0}")]
+    InvalidRange(String),
+    #[error("No Rainlang address for the fuzz spec")]
+    MissingRainlang,
+    #[error(transparent)]
+    TestCaseError(#[from] TestCaseError),
+    #[error(transparent)]
+    ForkCallError(#[from] ForkCallError),
+}
+
+/// The smallest failing case found, after shrinking.
+#[derive(Debug, Clone)]
+pub struct FuzzFailure {
+    /// The run, counted from 1, that first failed.
+    pub run: u32,
+    /// Number of times the failing case was shrunk to a simpler one.
+    pub shrinks: usize,
+    /// Inputs of the case, written the way ranges are.
+    pub inputs: Vec<String>,
+    /// Context of the case, written the way ranges are.
+    pub context: Vec<Vec<String>>,
+    /// Result of evaluating the case, with its traces.
+    pub result: RainEvalResult,
+}
+
+/// Outcome of fuzzing an expression.
+#[derive(Debug, Clone)]
+pub struct FuzzReport {
+    pub seed: u64,
+    /// Number of runs made before finishing or finding a failure, not
+    /// counting shrinking.
+    pub runs: u32,
+    pub failure: Option<FuzzFailure>,
+}
+
+impl FuzzReport {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl fmt::Display for FuzzReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(failure) = &self.failure else {
+            return writeln!(f, "PASS {} run(s) (seed {})", self.runs, self.seed);
+        };
+        writeln!(
+            f,
+            "FAIL on run {} (seed {}), shrunk {} time(s)",
+            failure.run, self.seed, failure.shrinks
+        )?;
+        writeln!(f, "  inputs: [{}]", failure.inputs.join(", "))?;
+        let context: Vec<String> = failure
+            .context
+            .iter()
+            .map(|column| format!("[{}]", column.join(", ")))
+            .collect();
+        writeln!(f, "  context: [{}]", context.join(", "))?;
+        match &failure.result.outcome {
+            RainEvalOutcome::EnsureFailed {
+                reason,
+                source_index,
+            } => writeln!(f, "  ensure failed in source {source_index}: {reason}")?,
+            _ => match &failure.result.error {
+                Some(error) => writeln!(f, "  reverted: {error}")?,
+                None => writeln!(f, "  reverted")?,
+            },
+        }
+        for trace in &failure.result.traces {
+            let stack: Vec<String> = trace.stack.iter().map(ToString::to_string).collect();
+            writeln!(
+                f,
+                "  trace {} -> {}: [{}]",
+                trace.parent_source_index,
+                trace.source_index,
+                stack.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// SplitMix64, kept in tree so that a seed reproduces the same draws
+/// regardless of dependency versions.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_u256(&mut self) -> U256 {
+        U256::from_limbs([
+            self.next_u64(),
+            self.next_u64(),
+            self.next_u64(),
+            self.next_u64(),
+        ])
+    }
+
+    /// Draws an offset from 0 to `span` inclusive, favouring the ends.
+    fn offset(&mut self, span: U256) -> U256 {
+        match self.next_u64() % EDGE_CASE_ODDS {
+            0 => U256::ZERO,
+            1 => span,
+            _ if span == U256::MAX => self.next_u256(),
+            _ => self.next_u256() % (span + U256::from(1)),
+        }
+    }
+}
+
+/// Steps of `distance`, `distance / 2`, `distance / 4` and so on down to 1,
+/// so that shrinking tries the biggest jump first and bisects from there.
+fn halvings(distance: U256) -> Vec<U256> {
+    let mut steps = vec![];
+    let mut step = distance;
+    while !step.is_zero() {
+        steps.push(step);
+        step >>= 1;
+    }
+    steps
+}
+
+/// A fuzz range with its bounds parsed.
+#[derive(Debug, Clone)]
+enum Domain {
+    Int {
+        min: U256,
+        max: U256,
+    },
+    /// Bounds are coefficients of floats with exponent `-decimals`.
+    Float {
+        min: I256,
+        max: I256,
+        decimals: u8,
+    },
+    Address,
+    OneOf {
+        values: Vec<String>,
+        words: Vec<U256>,
+    },
+}
+
+/// A value drawn from a [`Domain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Draw {
+    Int(U256),
+    Float(I256),
+    Address(Address),
+    OneOf(usize),
+}
+
+/// Scales a decimal float to a coefficient with exponent `-decimals`.
+fn scaled_coefficient(value: &str, decimals: u8) -> Result<I256, FuzzError> {
+    let invalid = || {
+        FuzzError::InvalidRange(format!(
+            "{value} is not a float with at most {decimals} decimals"
+        ))
+    };
+    let float: DecimalFloat = value.parse().map_err(|_| invalid())?;
+    let ten = I256::try_from(10i64).unwrap();
+    let mut coefficient = float.coefficient;
+    let mut scale = float.exponent as i64 + decimals as i64;
+    while scale > 0 {
+        coefficient = coefficient.checked_mul(ten).ok_or_else(invalid)?;
+        scale -= 1;
+    }
+    while scale < 0 {
+        if !(coefficient % ten).is_zero() {
+            return Err(invalid());
+        }
+        coefficient /= ten;
+        scale += 1;
+    }
+    DecimalFloat {
+        coefficient,
+        exponent: -(decimals as i32),
+    }
+    .to_word()
+    .map_err(|_| invalid())?;
+    Ok(coefficient)
+}
+
+impl Domain {
+    fn new(range: &FuzzRange) -> Result<Self, FuzzError> {
+        let domain = match range {
+            FuzzRange::Int { min, max } => Domain::Int {
+                min: parse_word(min, false)?,
+                max: parse_word(max, false)?,
+            },
+            FuzzRange::Float { min, max, decimals } => Domain::Float {
+                min: scaled_coefficient(min, *decimals)?,
+                max: scaled_coefficient(max, *decimals)?,
+                decimals: *decimals,
+            },
+            FuzzRange::Address => Domain::Address,
+            FuzzRange::OneOf { values, float } => Domain::OneOf {
+                words: values
+                    .iter()
+                    .map(|value| parse_word(value, *float))
+                    .collect::<Result<_, _>>()?,
+                values: values.clone(),
+            },
+        };
+        let empty = match &domain {
+            Domain::Int { min, max } => min > max,
+            Domain::Float { min, max, .. } => min > max,
+            Domain::Address => false,
+            Domain::OneOf { values, .. } => values.is_empty(),
+        };
+        if empty {
+            return Err(FuzzError::InvalidRange(format!("{range:?} is empty")));
+        }
+        Ok(domain)
+    }
+
+    fn draw(&self, rng: &mut Rng) -> Draw {
+        match self {
+            Domain::Int { min, max } => Draw::Int(*min + rng.offset(*max - *min)),
+            Domain::Float { min, max, .. } => {
+                let span = max.into_raw().wrapping_sub(min.into_raw());
+                let offset = rng.offset(span);
+                Draw::Float(I256::from_raw(min.into_raw().wrapping_add(offset)))
+            }
+            Domain::Address => Draw::Address(Address::from_word(rng.next_u256().into())),
+            Domain::OneOf { values, .. } => {
+                let last = U256::from(values.len() - 1);
+                Draw::OneOf(rng.offset(last).to::<usize>())
+            }
+        }
+    }
+
+    fn word(&self, draw: Draw) -> Result<U256, FuzzError> {
+        Ok(match (self, draw) {
+            (Domain::Float { decimals, .. }, Draw::Float(coefficient)) => DecimalFloat {
+                coefficient,
+                exponent: -(*decimals as i32),
+            }
+            .to_word()
+            .map_err(|e| FuzzError::TestCaseError(e.into()))?,
+            (Domain::OneOf { words, .. }, Draw::OneOf(index)) => words[index],
+            (_, Draw::Int(value)) => value,
+            (_, Draw::Address(address)) => U256::from_be_slice(address.as_slice()),
+            _ => unreachable!("draws always come from their own domain"),
+        })
+    }
+
+    fn format(&self, draw: Draw) -> String {
+        match (self, draw) {
+            (Domain::Float { decimals, .. }, Draw::Float(coefficient)) => DecimalFloat {
+                coefficient,
+                exponent: -(*decimals as i32),
+            }
+            .to_string(),
+            (Domain::OneOf { values, .. }, Draw::OneOf(index)) => values[index].clone(),
+            (_, Draw::Int(value)) => value.to_string(),
+            (_, Draw::Address(address)) => address.to_string(),
+            _ => unreachable!("draws always come from their own domain"),
+        }
+    }
+
+    /// Simpler draws to try in place of a failing one, simplest first.
+    /// Integers shrink toward the minimum, floats toward zero or the bound
+    /// nearest it, addresses toward zero and listed values toward the first.
+    fn shrink(&self, draw: Draw) -> Vec<Draw> {
+        let mut candidates = match (self, draw) {
+            (Domain::Int { min, .. }, Draw::Int(value)) => halvings(value - *min)
+                .into_iter()
+                .map(|step| Draw::Int(value - step))
+                .collect(),
+            (Domain::Float { min, max, .. }, Draw::Float(value)) => {
+                let target = I256::ZERO.max(*min).min(*max);
+                let toward = if value > target {
+                    I256::MINUS_ONE
+                } else {
+                    I256::ONE
+                };
+                halvings((value - target).unsigned_abs())
+                    .into_iter()
+                    .map(|step| Draw::Float(value + toward * I256::from_raw(step)))
+                    .collect()
+            }
+            (Domain::Address, Draw::Address(address)) if !address.is_zero() => {
+                vec![Draw::Address(Address::ZERO)]
+            }
+            (Domain::OneOf { .. }, Draw::OneOf(index)) => halvings(U256::from(index))
+                .into_iter()
+                .map(|step| Draw::OneOf(index - step.to::<usize>()))
+                .collect(),
+            _ => vec![],
+        };
+        candidates.dedup();
+        candidates.retain(|candidate| *candidate != draw);
+        candidates
+    }
+}
+
+/// The parsed ranges of a spec, with inputs first and then the context
+/// flattened column by column.
+struct Domains {
+    domains: Vec<Domain>,
+    inputs: usize,
+    columns: Vec<usize>,
+}
+
+impl Domains {
+    fn new(spec: &FuzzSpec) -> Result<Self, FuzzError> {
+        let domains = spec
+            .inputs
+            .iter()
+            .chain(spec.context.iter().flatten())
+            .map(Domain::new)
+            .collect::<Result<_, _>>()?;
+        Ok(Domains {
+            domains,
+            inputs: spec.inputs.len(),
+            columns: spec.context.iter().map(Vec::len).collect(),
+        })
+    }
+
+    /// Splits values of every domain into inputs and context columns.
+    fn split<T>(&self, mut values: Vec<T>) -> (Vec<T>, Vec<Vec<T>>) {
+        let mut rest = values.split_off(self.inputs);
+        let context = self
+            .columns
+            .iter()
+            .map(|len| {
+                let tail = rest.split_off(*len);
+                std::mem::replace(&mut rest, tail)
+            })
+            .collect();
+        (values, context)
+    }
+
+    fn words(&self, draws: &[Draw]) -> Result<Vec<U256>, FuzzError> {
+        self.domains
+            .iter()
+            .zip(draws)
+            .map(|(domain, draw)| domain.word(*draw))
+            .collect()
+    }
+
+    fn format(&self, draws: &[Draw]) -> Vec<String> {
+        self.domains
+            .iter()
+            .zip(draws)
+            .map(|(domain, draw)| domain.format(*draw))
+            .collect()
+    }
+}
+
+impl Forker {
+    /// Evaluates the expression of the spec with drawn inputs and context
+    /// until a run reverts, then shrinks that run to a simpler failing case.
+    /// `rainlang` takes precedence over the spec's own Rainlang address.
+    pub async fn fuzz(
+        &self,
+        spec: &FuzzSpec,
+        rainlang: Option<Address>,
+    ) -> Result<FuzzReport, FuzzError> {
+        let rainlang = rainlang
+            .or(spec.rainlang)
+            .ok_or(FuzzError::MissingRainlang)?;
+        let domains = Domains::new(spec)?;
+        let seed = spec.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        let mut rng = Rng(seed);
+
+        for run in 1..=spec.runs {
+            let draws: Vec<Draw> = domains
+                .domains
+                .iter()
+                .map(|domain| domain.draw(&mut rng))
+                .collect();
+            let Some(result) = self.fuzz_run(spec, rainlang, &domains, &draws).await? else {
+                continue;
+            };
+
+            let (draws, result, shrinks) = self
+                .shrink_failure(spec, rainlang, &domains, draws, result)
+                .await?;
+            let (inputs, context) = domains.split(domains.format(&draws));
+            return Ok(FuzzReport {
+                seed,
+                runs: run,
+                failure: Some(FuzzFailure {
+                    run,
+                    shrinks,
+                    inputs,
+                    context,
+                    result,
+                }),
+            });
+        }
+
+        Ok(FuzzReport {
+            seed,
+            runs: spec.runs,
+            failure: None,
+        })
+    }
+
+    /// Evaluates one set of draws, returning the result if it reverted.
+    async fn fuzz_run(
+        &self,
+        spec: &FuzzSpec,
+        rainlang: Address,
+        domains: &Domains,
+        draws: &[Draw],
+    ) -> Result<Option<RainEvalResult>, FuzzError> {
+        let (inputs, context) = domains.split(domains.words(draws)?);
+        let result = self
+            .fork_eval_result(ForkEvalArgs {
+                rainlang_string: spec.expression.clone(),
+                source_index: spec.source_index,
+                rainlang,
+                namespace: FullyQualifiedNamespace::default(),
+                context,
+                decode_errors: false,
+                inputs,
+                state_overlay: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
+            })
+            .await?;
+        Ok(result.reverted.then_some(result))
+    }
+
+    /// Repeatedly swaps single draws for simpler ones that still fail, until
+    /// no simpler draw fails or the shrink budget runs out.
+    async fn shrink_failure(
+        &self,
+        spec: &FuzzSpec,
+        rainlang: Address,
+        domains: &Domains,
+        mut draws: Vec<Draw>,
+        mut result: RainEvalResult,
+    ) -> Result<(Vec<Draw>, RainEvalResult, usize), FuzzError> {
+        let mut shrinks = 0;
+        let mut evals = 0;
+        'shrink: loop {
+            for (position, domain) in domains.domains.iter().enumerate() {
+                for candidate in domain.shrink(draws[position]) {
+                    if evals == MAX_SHRINK_EVALS {
+                        break 'shrink;
+                    }
+                    evals += 1;
+
+                    let mut trial = draws.clone();
+                    trial[position] = candidate;
+                    if let Some(trial_result) =
+                        self.fuzz_run(spec, rainlang, domains, &trial).await?
+                    {
+                        draws = trial;
+                        result = trial_result;
+                        shrinks += 1;
+                        continue 'shrink;
+                    }
+                }
+            }
+            break;
+        }
+        Ok((draws, result, shrinks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fork::NewForkedEvm;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    fn int(min: &str, max: &str) -> FuzzRange {
+        FuzzRange::Int {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_spec() {
+        let spec: FuzzSpec = serde_json::from_str(
+            r#"{
+                "expression": "_: 1;",
+                "inputs": [{ "type": "int", "min": "0", "max": "0xff" }],
+                "context": [[
+                    { "type": "float", "min": "-1.5", "max": "2", "decimals": 2 },
+                    { "type": "address" },
+                    { "type": "oneOf", "values": ["1", "2"] }
+                ]],
+                "seed": 7
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(spec.runs, 256);
+        assert_eq!(spec.seed, Some(7));
+        assert_eq!(spec.inputs, vec![int("0", "0xff")]);
+        assert_eq!(spec.context[0][1], FuzzRange::Address);
+    }
+
+    #[test]
+    fn test_draws_are_in_range_and_seeded() {
+        let domains = [
+            Domain::new(&int("10", "20")).unwrap(),
+            Domain::new(&FuzzRange::Float {
+                min: "-1.5".into(),
+                max: "0.25".into(),
+                decimals: 2,
+            })
+            .unwrap(),
+            Domain::new(&FuzzRange::OneOf {
+                values: vec!["1".into(), "2".into(), "3".into()],
+                float: false,
+            })
+            .unwrap(),
+        ];
+
+        let draws = |seed| {
+            let mut rng = Rng(seed);
+            (0..100)
+                .flat_map(|_| domains.iter().map(|d| d.draw(&mut rng)).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(1), draws(1));
+        assert_ne!(draws(1), draws(2));
+
+        let (ten, twenty) = (U256::from(10), U256::from(20));
+        let (min, max) = (
+            I256::try_from(-150i64).unwrap(),
+            I256::try_from(25i64).unwrap(),
+        );
+        for draw in draws(3) {
+            match draw {
+                Draw::Int(value) => assert!(value >= ten && value <= twenty),
+                Draw::Float(value) => assert!(value >= min && value <= max),
+                Draw::OneOf(index) => assert!(index < 3),
+                Draw::Address(_) => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        assert!(matches!(
+            Domain::new(&int("2", "1")),
+            Err(FuzzError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            Domain::new(&FuzzRange::Float {
+                min: "0.125".into(),
+                max: "1".into(),
+                decimals: 2,
+            }),
+            Err(FuzzError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            Domain::new(&FuzzRange::OneOf {
+                values: vec![],
+                float: false,
+            }),
+            Err(FuzzError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            Domain::new(&int("x", "1")),
+            Err(FuzzError::TestCaseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_shrink() {
+        let domain = Domain::new(&int("10", "100")).unwrap();
+        assert_eq!(
+            domain.shrink(Draw::Int(U256::from(50))),
+            [10, 30, 40, 45, 48, 49]
+                .map(|value| Draw::Int(U256::from(value)))
+                .to_vec()
+        );
+        assert_eq!(domain.shrink(Draw::Int(U256::from(10))), vec![]);
+
+        let domain = Domain::new(&FuzzRange::Float {
+            min: "-1".into(),
+            max: "1".into(),
+            decimals: 1,
+        })
+        .unwrap();
+        let coefficient = |value: i64| I256::try_from(value).unwrap();
+        assert_eq!(
+            domain.shrink(Draw::Float(coefficient(-8))),
+            [0, -4, -6, -7]
+                .map(|value| Draw::Float(coefficient(value)))
+                .to_vec()
+        );
+        assert_eq!(domain.word(Draw::Float(coefficient(-8))).unwrap(), {
+            DecimalFloat {
+                coefficient: coefficient(-8),
+                exponent: -1,
+            }
+            .to_word()
+            .unwrap()
+        });
+        assert_eq!(domain.format(Draw::Float(coefficient(-8))), "-0.8");
+    }
+
+    #[test]
+    fn test_split() {
+        let spec = FuzzSpec {
+            rainlang: None,
+            expression: String::new(),
+            source_index: 0,
+            inputs: vec![int("0", "1")],
+            context: vec![vec![int("0", "1"), int("0", "1")], vec![int("0", "1")]],
+            runs: 1,
+            seed: None,
+        };
+        let domains = Domains::new(&spec).unwrap();
+        assert_eq!(
+            domains.split(vec![1, 2, 3, 4]),
+            (vec![1], vec![vec![2, 3], vec![4]])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fuzz() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let mut spec = FuzzSpec {
+            rainlang: Some(local_evm.rainlang),
+            expression: ":ensure(less-than(context<0 0>() 1000) \"too big\");".into(),
+            source_index: 0,
+            inputs: vec![],
+            context: vec![vec![int("0", "999")]],
+            runs: 20,
+            seed: Some(42),
+        };
+        let report = fork.fuzz(&spec, None).await.unwrap();
+        assert!(report.passed());
+        assert_eq!(report.runs, 20);
+        assert_eq!(report.to_string(), "PASS 20 run(s) (seed 42)\n");
+
+        spec.context = vec![vec![int("500", "5000")]];
+        let report = fork.fuzz(&spec, None).await.unwrap();
+        let failure = report.failure.as_ref().unwrap();
+        // Shrinks to the smallest value that breaks the invariant.
+        assert_eq!(failure.context, vec![vec!["1000".to_string()]]);
+        assert_eq!(
+            failure.result.outcome,
+            RainEvalOutcome::EnsureFailed {
+                reason: "too big".into(),
+                source_index: 0,
+            }
+        );
+        assert!(
+            report
+                .to_string()
+                .contains("  context: [[1000]]\n  ensure failed in source 0: too big\n")
+        );
+
+        // The same seed finds the same failure.
+        let again = fork.fuzz(&spec, None).await.unwrap();
+        assert_eq!(again.runs, report.runs);
+        assert_eq!(again.failure.unwrap().context, failure.context);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fuzz_missing_rainlang() {
+        let fork = Forker::new().unwrap();
+        let spec: FuzzSpec = serde_json::from_str(r#"{ "expression": "_: 1;" }"#).unwrap();
+        assert!(matches!(
+            fork.fuzz(&spec, None).await,
+            Err(FuzzError::MissingRainlang)
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+use revm::primitives::hardfork::SpecId;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+pub const MAINNET_CHAIN_ID: u64 = 1;
+pub const SEPOLIA_CHAIN_ID: u64 = 11155111;
+pub const POLYGON_CHAIN_ID: u64 = 137;
+pub const BASE_CHAIN_ID: u64 = 8453;
+pub const BASE_SEPOLIA_CHAIN_ID: u64 = 84532;
+/// Anvil and Hardhat.
+pub const DEV_CHAIN_ID: u64 = 31337;
+
+/// Block number or timestamp from which a hardfork is active.
+#[derive(Debug, Clone, Copy)]
+enum Activation {
+    Block(u64),
+    Timestamp(u64),
+}
+
+use Activation::{Block, Timestamp};
+
+/// Hardforks that changed EVM behaviour on mainnet, in activation order.
+const MAINNET: &[(SpecId, Activation)] = &[
+    (SpecId::FRONTIER, Block(0)),
+    (SpecId::FRONTIER_THAWING, Block(200_000)),
+    (SpecId::HOMESTEAD, Block(1_150_000)),
+    (SpecId::DAO_FORK, Block(1_920_000)),
+    (SpecId::TANGERINE, Block(2_463_000)),
+    (SpecId::SPURIOUS_DRAGON, Block(2_675_000)),
+    (SpecId::BYZANTIUM, Block(4_370_000)),
+    (SpecId::PETERSBURG, Block(7_280_000)),
+    (SpecId::ISTANBUL, Block(9_069_000)),
+    (SpecId::MUIR_GLACIER, Block(9_200_000)),
+    (SpecId::BERLIN, Block(12_244_000)),
+    (SpecId::LONDON, Block(12_965_000)),
+    (SpecId::ARROW_GLACIER, Block(13_773_000)),
+    (SpecId::GRAY_GLACIER, Block(15_050_000)),
+    (SpecId::MERGE, Block(15_537_394)),
+    (SpecId::SHANGHAI, Timestamp(1_681_338_455)),
+    (SpecId::CANCUN, Timestamp(1_710_338_135)),
+    (SpecId::PRAGUE, Timestamp(1_746_612_311)),
+];
+
+/// Sepolia started on London rules.
+const SEPOLIA: &[(SpecId, Activation)] = &[
+    (SpecId::LONDON, Block(0)),
+    (SpecId::MERGE, Block(1_450_409)),
+    (SpecId::SHANGHAI, Timestamp(1_677_557_088)),
+    (SpecId::CANCUN, Timestamp(1_706_655_072)),
+    (SpecId::PRAGUE, Timestamp(1_741_159_776)),
+];
+
+/// Polygon PoS forks by block, and had no merge.
+const POLYGON: &[(SpecId, Activation)] = &[
+    (SpecId::PETERSBURG, Block(0)),
+    (SpecId::ISTANBUL, Block(3_395_000)),
+    (SpecId::BERLIN, Block(14_750_000)),
+    (SpecId::LONDON, Block(23_850_000)),
+    (SpecId::SHANGHAI, Block(50_523_000)),
+    (SpecId::CANCUN, Block(54_876_000)),
+    (SpecId::PRAGUE, Block(73_440_256)),
+];
+
+/// Base started on Bedrock, which has the merge's rules, and took L1's
+/// hardforks with Canyon, Ecotone and Isthmus.
+const BASE: &[(SpecId, Activation)] = &[
+    (SpecId::MERGE, Block(0)),
+    (SpecId::SHANGHAI, Timestamp(1_704_992_401)),
+    (SpecId::CANCUN, Timestamp(1_710_374_401)),
+    (SpecId::PRAGUE, Timestamp(1_746_806_401)),
+];
+
+const BASE_SEPOLIA: &[(SpecId, Activation)] = &[
+    (SpecId::MERGE, Block(0)),
+    (SpecId::SHANGHAI, Timestamp(1_699_981_200)),
+    (SpecId::CANCUN, Timestamp(1_708_534_800)),
+    (SpecId::PRAGUE, Timestamp(1_744_905_600)),
+];
+
+fn schedule(chain_id: u64) -> Option<&'static [(SpecId, Activation)]> {
+    match chain_id {
+        MAINNET_CHAIN_ID => Some(MAINNET),
+        SEPOLIA_CHAIN_ID => Some(SEPOLIA),
+        POLYGON_CHAIN_ID => Some(POLYGON),
+        BASE_CHAIN_ID => Some(BASE),
+        BASE_SEPOLIA_CHAIN_ID => Some(BASE_SEPOLIA),
+        _ => None,
+    }
+}
+
+/// Chains already warned about having no schedule.
+static WARNED: Mutex<Option<HashSet<u64>>> = Mutex::new(None);
+
+/// The EVM spec active on a chain at the given block.
+///
+/// Local dev chains get the latest spec. Other chains without a known
+/// schedule get it too, with a warning, once per chain, to pin the spec with
+/// [`crate::fork::Forker::set_spec_id`], as older blocks of theirs may run
+/// on an earlier one.
+pub fn spec_id(chain_id: u64, block_number: u64, timestamp: u64) -> SpecId {
+    let Some(schedule) = schedule(chain_id) else {
+        if chain_id != DEV_CHAIN_ID {
+            warn_unscheduled(chain_id);
+        }
+        return SpecId::default();
+    };
+    schedule
+        .iter()
+        .rev()
+        .find(|(_, activation)| match activation {
+            Block(block) => block_number >= *block,
+            Timestamp(activation_timestamp) => timestamp >= *activation_timestamp,
+        })
+        .map_or(schedule[0].0, |(spec_id, _)| *spec_id)
+}
+
+fn warn_unscheduled(chain_id: u64) {
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if warned.get_or_insert_with(HashSet::new).insert(chain_id) {
+        tracing::warn!(
+            "No hardfork schedule for chain {chain_id}, running the latest EVM spec. \
+             Pin the spec when simulating blocks from before its latest hardfork."
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mainnet() {
+        assert_eq!(spec_id(MAINNET_CHAIN_ID, 0, 0), SpecId::FRONTIER);
+        assert_eq!(
+            spec_id(MAINNET_CHAIN_ID, 4_369_999, 0),
+            SpecId::SPURIOUS_DRAGON
+        );
+        assert_eq!(spec_id(MAINNET_CHAIN_ID, 4_370_000, 0), SpecId::BYZANTIUM);
+        assert_eq!(spec_id(MAINNET_CHAIN_ID, 12_965_000, 0), SpecId::LONDON);
+        assert_eq!(
+            spec_id(MAINNET_CHAIN_ID, 16_000_000, 1_681_338_454),
+            SpecId::MERGE
+        );
+        assert_eq!(
+            spec_id(MAINNET_CHAIN_ID, 17_034_870, 1_681_338_455),
+            SpecId::SHANGHAI
+        );
+        assert_eq!(
+            spec_id(MAINNET_CHAIN_ID, 19_426_587, 1_710_338_135),
+            SpecId::CANCUN
+        );
+        assert_eq!(
+            spec_id(MAINNET_CHAIN_ID, 22_431_084, 1_746_612_311),
+            SpecId::PRAGUE
+        );
+    }
+
+    #[test]
+    fn test_sepolia() {
+        assert_eq!(spec_id(SEPOLIA_CHAIN_ID, 0, 0), SpecId::LONDON);
+        assert_eq!(
+            spec_id(SEPOLIA_CHAIN_ID, 1_450_409, 1_656_000_000),
+            SpecId::MERGE
+        );
+        assert_eq!(
+            spec_id(SEPOLIA_CHAIN_ID, 5_187_023, 1_706_655_072),
+            SpecId::CANCUN
+        );
+    }
+
+    #[test]
+    fn test_polygon() {
+        assert_eq!(spec_id(POLYGON_CHAIN_ID, 0, 0), SpecId::PETERSBURG);
+        assert_eq!(spec_id(POLYGON_CHAIN_ID, 23_849_999, 0), SpecId::BERLIN);
+        assert_eq!(spec_id(POLYGON_CHAIN_ID, 23_850_000, 0), SpecId::LONDON);
+        assert_eq!(spec_id(POLYGON_CHAIN_ID, 54_876_000, 0), SpecId::CANCUN);
+    }
+
+    #[test]
+    fn test_base() {
+        assert_eq!(spec_id(BASE_CHAIN_ID, 0, 1_686_789_347), SpecId::MERGE);
+        assert_eq!(
+            spec_id(BASE_CHAIN_ID, 9_101_527, 1_704_992_401),
+            SpecId::SHANGHAI
+        );
+        assert_eq!(
+            spec_id(BASE_SEPOLIA_CHAIN_ID, 6_383_000, 1_708_534_800),
+            SpecId::CANCUN
+        );
+    }
+
+    #[test]
+    fn test_unknown_chain() {
+        assert_eq!(spec_id(DEV_CHAIN_ID, 0, 0), SpecId::default());
+        assert_eq!(spec_id(42161, 1, 1), SpecId::default());
+    }
+}
@@ -1,9 +1,35 @@
 //! Evaluation runtime for Rainlang expressions using forked EVM contexts.
 
+pub mod bytecode;
+#[cfg(not(target_family = "wasm"))]
+mod cache;
+#[cfg(not(target_family = "wasm"))]
+mod cancel;
+#[cfg(not(target_family = "wasm"))]
+pub mod compare;
+pub mod context;
+pub mod coverage;
 pub mod error;
 #[cfg(not(target_family = "wasm"))]
 pub mod eval;
+pub mod float;
 #[cfg(not(target_family = "wasm"))]
 pub mod fork;
+#[cfg(not(target_family = "wasm"))]
+pub mod fuzz;
+#[cfg(not(target_family = "wasm"))]
+pub mod hardfork;
 pub mod namespace;
+#[cfg(not(target_family = "wasm"))]
+pub mod parser;
+#[cfg(not(target_family = "wasm"))]
+pub mod pool;
+#[cfg(not(target_family = "wasm"))]
+mod rpc_cache;
+#[cfg(not(target_family = "wasm"))]
+pub mod scenario;
+#[cfg(not(target_family = "wasm"))]
+pub mod state_dump;
+#[cfg(not(target_family = "wasm"))]
+pub mod suite;
 pub mod trace;
@@ -0,0 +1,202 @@
+use crate::error::ForkCallError;
+use crate::fork::Forker;
+use alloy::primitives::Address;
+use rain_interpreter_bindings::IParserPragmaV1::{parsePragma1Call, parsePragma1Return};
+use rain_interpreter_bindings::IParserV2::{parse2Call, parse2Return};
+use rain_interpreter_bindings::RainterpreterParser::{unsafeParseCall, unsafeParseReturn};
+use rain_interpreter_parser::{Parser2, ParserError, ParserV2, UnsafeParser2};
+
+impl From<ForkCallError> for ParserError {
+    fn from(err: ForkCallError) -> Self {
+        ParserError::ClientError(Box::new(err))
+    }
+}
+
+/// Runs [`ParserV2`] against a forked EVM instead of a live RPC client. The
+/// read calls are executed locally by the fork's executor, so code written
+/// against [`Parser2`] works unchanged on a fork or a local in-memory EVM.
+impl<'a> Parser2<&'a Forker> for ParserV2 {
+    async fn parse(&self, data: Vec<u8>, client: &'a Forker) -> Result<parse2Return, ParserError> {
+        let bytecode = client
+            .alloy_call(
+                Address::default(),
+                self.deployer_address,
+                parse2Call { data: data.into() },
+                false,
+            )
+            .await?
+            .typed_return;
+
+        Ok(parse2Return { bytecode })
+    }
+
+    async fn parse_pragma(
+        &self,
+        data: Vec<u8>,
+        client: &'a Forker,
+    ) -> Result<parsePragma1Return, ParserError> {
+        let pragma = client
+            .alloy_call(
+                Address::default(),
+                self.deployer_address,
+                parsePragma1Call { data: data.into() },
+                false,
+            )
+            .await?
+            .typed_return;
+
+        Ok(parsePragma1Return { _0: pragma })
+    }
+}
+
+impl<'a> UnsafeParser2<&'a Forker> for ParserV2 {
+    async fn unsafe_parse(
+        &self,
+        data: Vec<u8>,
+        client: &'a Forker,
+    ) -> Result<unsafeParseReturn, ParserError> {
+        Ok(client
+            .alloy_call(
+                Address::default(),
+                self.unsafe_parser_address()?,
+                unsafeParseCall { data: data.into() },
+                false,
+            )
+            .await?
+            .typed_return)
+    }
+}
+
+impl Forker {
+    /// Builds a [`ParserV2`] for the deployer discovered from the given
+    /// Rainlang contract on the active fork.
+    pub async fn parser(&self, rainlang: Address) -> Result<ParserV2, ForkCallError> {
+        let dispair = self.resolve_dispair(rainlang, false).await?;
+        Ok(ParserV2::from(dispair))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::ForkParseArgs;
+    use crate::fork::NewForkedEvm;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    async fn parse_generic<C, P: Parser2<C>>(parser: &P, text: &str, client: C) -> Vec<u8> {
+        parser
+            .parse(text.as_bytes().to_vec(), client)
+            .await
+            .unwrap()
+            .bytecode
+            .to_vec()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_parse_text_matches_fork_parse() {
+        let local_evm = LocalEvm::new().await;
+        let forker = Forker::new_with_fork(
+            NewForkedEvm {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let parser = forker.parser(local_evm.rainlang).await.unwrap();
+        assert_eq!(parser.deployer_address, *local_evm.deployer.address());
+
+        let bytecode = parse_generic(&parser, "_: 1;", &forker).await;
+
+        let expected = forker
+            .fork_parse(ForkParseArgs {
+                rainlang_string: "_: 1;".to_owned(),
+                rainlang: local_evm.rainlang,
+                decode_errors: false,
+                dispair: None,
+            })
+            .await
+            .unwrap()
+            .typed_return;
+        assert_eq!(bytecode, expected.to_vec());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_parse_pragma_text() {
+        let local_evm = LocalEvm::new().await;
+        let forker = Forker::new_with_fork(
+            NewForkedEvm {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let parser = forker.parser(local_evm.rainlang).await.unwrap();
+
+        let sub_parser = Address::repeat_byte(0x11);
+        let pragmas = parser
+            .parse_pragma_text(&format!("using-words-from {sub_parser}"), &forker)
+            .await
+            .unwrap();
+        assert_eq!(pragmas, vec![sub_parser]);
+
+        let pragmas = parser.parse_pragma_text("_: 1;", &forker).await.unwrap();
+        assert!(pragmas.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_unsafe_parse_text_skips_integrity() {
+        let local_evm = LocalEvm::new().await;
+        let forker = Forker::new_with_fork(
+            NewForkedEvm {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let parser = forker.parser(local_evm.rainlang).await.unwrap();
+
+        assert!(parser.parse_text("_: add(1);", &forker).await.is_err());
+        let result = parser
+            .unsafe_parse_text("_: add(1);", &forker)
+            .await
+            .unwrap();
+        assert!(!result._0.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_parse_error() {
+        let local_evm = LocalEvm::new().await;
+        let forker = Forker::new_with_fork(
+            NewForkedEvm {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let parser = forker.parser(local_evm.rainlang).await.unwrap();
+
+        let result = parser.parse_text("_: unknown-word();", &forker).await;
+        assert!(matches!(result, Err(ParserError::ClientError(_))));
+    }
+}
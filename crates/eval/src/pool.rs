@@ -0,0 +1,229 @@
+use crate::cancel::{Cancel, CancelOnDrop};
+use crate::error::ForkCallError;
+use crate::eval::ForkEvalArgs;
+use crate::fork::{ForkTypedReturn, Forker};
+use crate::trace::RainEvalResult;
+use futures::future::join_all;
+use rain_interpreter_bindings::IInterpreterV4::eval4Call;
+use std::num::NonZeroUsize;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+/// Clones of a [`Forker`] that evaluate batches of expressions in parallel,
+/// each on its own blocking thread.
+///
+/// The clones share the executor until one of them mutates it, so state
+/// committed on one is not seen by the others. They share the backend's fork
+/// databases, so state fetched over RPC by any of them is cached for all, as
+/// are the memoized DISPaiRs and parse results.
+#[derive(Clone)]
+pub struct ForkerPool {
+    forkers: Vec<Forker>,
+}
+
+impl ForkerPool {
+    /// A pool of `size` clones of `forker`.
+    pub fn new(forker: Forker, size: NonZeroUsize) -> Self {
+        Self {
+            forkers: vec![forker; size.get()],
+        }
+    }
+
+    /// A pool with one clone of `forker` per available CPU.
+    pub fn with_available_parallelism(forker: Forker) -> Self {
+        let size = std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+        Self::new(forker, size)
+    }
+
+    /// Number of evaluations run at once.
+    pub fn size(&self) -> usize {
+        self.forkers.len()
+    }
+
+    /// Evaluates every args like [`Forker::fork_eval`], returning the results
+    /// in the order of `args`.
+    ///
+    /// Each eval fails with [`ForkCallError::Timeout`] once it runs past the
+    /// forker's `call_timeout`, counted from when a worker takes it. Dropping
+    /// the returned future stops every eval still running or queued.
+    pub async fn fork_eval(
+        &self,
+        args: Vec<ForkEvalArgs>,
+    ) -> Vec<Result<ForkTypedReturn<eval4Call>, ForkCallError>> {
+        let decode_errors: Vec<bool> = args.iter().map(|args| args.decode_errors).collect();
+        let results = self.run(args, Forker::fork_eval_blocking).await;
+
+        join_all(
+            results
+                .into_iter()
+                .zip(decode_errors)
+                .map(|(result, decode_errors)| Forker::eval_typed_return(result, decode_errors)),
+        )
+        .await
+    }
+
+    /// Evaluates every args like [`Forker::fork_eval_result`], returning the
+    /// results in the order of `args`.
+    ///
+    /// Each eval is limited to `call_timeout` as with [`ForkerPool::fork_eval`].
+    pub async fn fork_eval_result(
+        &self,
+        args: Vec<ForkEvalArgs>,
+    ) -> Vec<Result<RainEvalResult, ForkCallError>> {
+        let decode_errors: Vec<(u16, bool)> = args
+            .iter()
+            .map(|args| (args.source_index, args.decode_errors))
+            .collect();
+        let results = self.run(args, Forker::fork_eval_blocking).await;
+
+        join_all(results.into_iter().zip(decode_errors).map(
+            |(result, (source_index, decode_errors))| {
+                Forker::eval_result(result, source_index, decode_errors)
+            },
+        ))
+        .await
+    }
+
+    /// Runs `f` on every item, with one blocking thread per forker taking
+    /// the next item as soon as it is done with the last, so a few slow
+    /// items do not hold up the rest.
+    ///
+    /// Each item is cancelled once it runs past its forker's `call_timeout`,
+    /// and every item once the returned future is dropped.
+    async fn run<T, R, F>(&self, items: Vec<T>, f: F) -> Vec<Result<R, ForkCallError>>
+    where
+        T: Send + 'static,
+        R: Send + 'static,
+        F: Fn(&Forker, T, &Cancel<'_>) -> Result<R, ForkCallError> + Send + Sync + 'static,
+    {
+        let len = items.len();
+        let queue = Arc::new(Mutex::new(items.into_iter().enumerate()));
+        let f = Arc::new(f);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _cancel_on_drop = CancelOnDrop(cancelled.clone());
+
+        let workers = self.forkers.iter().take(len).cloned().map(|forker| {
+            let (queue, f, cancelled) = (queue.clone(), f.clone(), cancelled.clone());
+            tokio::task::spawn_blocking(move || {
+                let mut done = vec![];
+                loop {
+                    let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+                    let Some((i, item)) = next else {
+                        return done;
+                    };
+                    let cancel = Cancel::new(&cancelled, forker.call_timeout());
+                    // Nobody waits for the rest once the batch is dropped.
+                    if cancel.is_flagged() {
+                        return done;
+                    }
+                    done.push((i, cancel.check(f(&forker, item, &cancel))));
+                }
+            })
+        });
+
+        let mut results: Vec<Option<Result<R, ForkCallError>>> = (0..len).map(|_| None).collect();
+        for worker in join_all(workers).await {
+            let done = worker.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            for (i, result) in done {
+                results[i] = Some(result);
+            }
+        }
+        results
+            .into_iter()
+            .map(|result| result.expect("every item is taken by a worker"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fork::NewForkedEvm;
+    use alloy::primitives::Address;
+    use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
+    use rain_interpreter_test_fixtures::LocalEvm;
+    use std::time::Duration;
+
+    fn eval_args(rainlang: Address, rainlang_string: String) -> ForkEvalArgs {
+        ForkEvalArgs {
+            rainlang_string,
+            source_index: 0,
+            rainlang,
+            namespace: FullyQualifiedNamespace::default(),
+            context: vec![],
+            decode_errors: false,
+            inputs: vec![],
+            state_overlay: vec![],
+            bytecode: None,
+            dispair: None,
+            from: None,
+            env: Default::default(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_pool_fork_eval_result() {
+        let local_evm = LocalEvm::new().await;
+        let forker = Forker::new_with_fork(
+            NewForkedEvm {
+                fork_url: local_evm.url(),
+                fork_block_number: None,
+                rpc_cache: None,
+                rpc: Default::default(),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let pool = ForkerPool::new(forker.clone(), NonZeroUsize::new(4).unwrap());
+        assert_eq!(pool.size(), 4);
+
+        let mut args: Vec<ForkEvalArgs> = (0..20)
+            .map(|i| eval_args(local_evm.rainlang, format!("_ _: {i} block-number();")))
+            .collect();
+        args.push(eval_args(
+            local_evm.rainlang,
+            ":ensure(0 \"nope\");".to_owned(),
+        ));
+        args.push(eval_args(local_evm.rainlang, "_: nope();".to_owned()));
+
+        let results = pool.fork_eval_result(args.clone()).await;
+        assert_eq!(results.len(), args.len());
+        for (result, args) in results.iter().zip(args) {
+            let expected = forker.fork_eval_result(args).await;
+            match (result, expected) {
+                (Ok(result), Ok(expected)) => {
+                    assert_eq!(result.reverted, expected.reverted);
+                    let stacks = |result: &RainEvalResult| {
+                        result
+                            .traces
+                            .iter()
+                            .map(|trace| trace.stack.clone())
+                            .collect::<Vec<_>>()
+                    };
+                    assert_eq!(stacks(result), stacks(&expected));
+                }
+                (Err(_), Err(_)) => {}
+                (result, expected) => panic!("pool: {result:?}, forker: {expected:?}"),
+            }
+        }
+        assert!(results[20].as_ref().unwrap().reverted);
+        assert!(results[21].is_err());
+
+        let typed = pool
+            .fork_eval(vec![eval_args(local_evm.rainlang, "_: 1;".to_owned())])
+            .await;
+        assert!(typed[0].is_ok());
+        assert!(pool.fork_eval(vec![]).await.is_empty());
+
+        // Every eval of the batch times out on its own.
+        let mut forker = forker;
+        forker.set_call_timeout(Some(Duration::ZERO));
+        let pool = ForkerPool::new(forker, NonZeroUsize::new(2).unwrap());
+        let args = vec![eval_args(local_evm.rainlang, "_: 1;".to_owned()); 3];
+        for result in pool.fork_eval(args).await {
+            assert!(matches!(result, Err(ForkCallError::Timeout(t)) if t.is_zero()));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::error::ForkCallError;
+use alloy::hex;
+use alloy::primitives::{Address, BlockNumber, U256, keccak256};
+use revm::context::BlockEnv;
+use revm::state::{AccountInfo, EvmState};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// The state a fork fetched over RPC at a pinned block, and the environment
+/// of that block so the fork can be recreated offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RpcCacheFile {
+    pub chain_id: u64,
+    pub block_env: BlockEnv,
+    pub accounts: BTreeMap<Address, AccountInfo>,
+    pub storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+}
+
+impl RpcCacheFile {
+    /// Reads a cache file, or `None` if there is none yet.
+    pub(crate) fn load(path: &Path) -> Result<Option<Self>, ForkCallError> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(rpc_cache_error(path, e)),
+        };
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| rpc_cache_error(path, e))
+    }
+
+    /// Writes the cache file through a uniquely named temporary file in the
+    /// same directory, so concurrent writers never clobber each other's and
+    /// readers never see a partial one.
+    pub(crate) fn save(&self, path: &Path) -> Result<(), ForkCallError> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir).map_err(|e| rpc_cache_error(path, e))?;
+        let data = serde_json::to_vec(self).map_err(|e| rpc_cache_error(path, e))?;
+        let mut tmp = NamedTempFile::new_in(dir).map_err(|e| rpc_cache_error(path, e))?;
+        tmp.write_all(&data).map_err(|e| rpc_cache_error(path, e))?;
+        tmp.persist(path)
+            .map_err(|e| rpc_cache_error(path, e.error))?;
+        Ok(())
+    }
+}
+
+/// The accounts and storage slots an offline forker knows, from its cache
+/// file and from everything committed since. An offline forker has no RPC
+/// to fetch anything else from, so its database reads it as empty, and
+/// calls that loaded it must fail instead.
+#[derive(Debug, Clone)]
+pub(crate) struct OfflineState {
+    path: PathBuf,
+    accounts: HashSet<Address>,
+    storage: HashSet<(Address, U256)>,
+    /// Accounts created locally, all of whose storage is local too.
+    created: HashSet<Address>,
+}
+
+impl OfflineState {
+    pub(crate) fn new(path: PathBuf, file: &RpcCacheFile) -> Self {
+        Self {
+            path,
+            accounts: file.accounts.keys().copied().collect(),
+            storage: file
+                .storage
+                .iter()
+                .flat_map(|(address, slots)| slots.keys().map(|slot| (*address, *slot)))
+                .collect(),
+            created: HashSet::new(),
+        }
+    }
+
+    /// Fails with [`ForkCallError::OfflineStateMiss`] if the state loaded by
+    /// a call has an account or slot that is not known.
+    pub(crate) fn check(&self, state: &EvmState) -> Result<(), ForkCallError> {
+        let miss =
+            |what: String| ForkCallError::OfflineStateMiss(what, self.path.display().to_string());
+        for (address, account) in state {
+            if account.is_created() || self.created.contains(address) {
+                continue;
+            }
+            if !self.accounts.contains(address) {
+                return Err(miss(format!("account {address}")));
+            }
+            if let Some(slot) = account
+                .storage
+                .keys()
+                .find(|slot| !self.storage.contains(&(*address, **slot)))
+            {
+                return Err(miss(format!("slot {slot:#x} of {address}")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the state committed by a call as known.
+    pub(crate) fn commit(&mut self, state: &EvmState) {
+        for (address, account) in state {
+            self.accounts.insert(*address);
+            if account.is_created() {
+                self.created.insert(*address);
+            }
+            self.storage
+                .extend(account.storage.keys().map(|slot| (*address, *slot)));
+        }
+    }
+}
+
+/// Path of the cache file of a fork. Files are keyed by RPC URL rather than
+/// chain id, as the chain id is not known offline. The URL is hashed so API
+/// keys in it do not end up in file names.
+pub(crate) fn cache_path(dir: &Path, fork_url: &str, block_number: BlockNumber) -> PathBuf {
+    let url_hash = keccak256(fork_url.as_bytes());
+    dir.join(format!(
+        "{}-{block_number}.json",
+        hex::encode(&url_hash[..8])
+    ))
+}
+
+fn rpc_cache_error(path: &Path, e: impl ToString) -> ForkCallError {
+    ForkCallError::RpcCache(path.display().to_string(), e.to_string())
+}
+
+/// Saves the RPC state of a fork to its cache file when the last clone of
+/// the forker holding it is dropped.
+pub(crate) struct RpcCacheGuard {
+    save: Box<dyn Fn() -> Result<(), ForkCallError> + Send + Sync>,
+}
+
+impl RpcCacheGuard {
+    pub(crate) fn new(
+        save: impl Fn() -> Result<(), ForkCallError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            save: Box::new(save),
+        }
+    }
+
+    pub(crate) fn save(&self) -> Result<(), ForkCallError> {
+        (self.save)()
+    }
+}
+
+impl Drop for RpcCacheGuard {
+    fn drop(&mut self) {
+        // Nothing can be reported from a drop. Callers that need to know
+        // save explicitly with `Forker::save_rpc_caches`.
+        let _ = self.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::state::{Account, EvmStorageSlot};
+
+    #[test]
+    fn test_cache_path() {
+        let dir = Path::new("/cache");
+        let path = cache_path(dir, "https://rpc.example/key", 100);
+        assert_eq!(path.parent(), Some(dir));
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.ends_with("-100.json"), "got: {name}");
+        assert!(!name.contains("key"));
+        assert_ne!(path, cache_path(dir, "https://rpc.example/other", 100));
+        assert_ne!(path, cache_path(dir, "https://rpc.example/key", 101));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = cache_path(dir.path(), "http://localhost:8545", 1);
+        assert!(RpcCacheFile::load(&path).unwrap().is_none());
+
+        let file = RpcCacheFile {
+            chain_id: 1,
+            block_env: BlockEnv {
+                number: 1,
+                timestamp: 1000,
+                ..Default::default()
+            },
+            accounts: BTreeMap::from([(
+                Address::repeat_byte(0x1),
+                AccountInfo {
+                    balance: U256::from(5),
+                    nonce: 2,
+                    ..Default::default()
+                },
+            )]),
+            storage: BTreeMap::from([(
+                Address::repeat_byte(0x1),
+                BTreeMap::from([(U256::from(1), U256::from(2))]),
+            )]),
+        };
+        file.save(&path).unwrap();
+        file.save(&path).unwrap();
+        // The temporary files were persisted as the cache file.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let loaded = RpcCacheFile::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.chain_id, 1);
+        assert_eq!(loaded.block_env.timestamp, 1000);
+        assert_eq!(loaded.accounts, file.accounts);
+        assert_eq!(loaded.storage, file.storage);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            RpcCacheFile::load(&path),
+            Err(ForkCallError::RpcCache(..))
+        ));
+    }
+
+    #[test]
+    fn test_offline_state() {
+        let cached = Address::repeat_byte(0x1);
+        let file = RpcCacheFile {
+            chain_id: 1,
+            block_env: BlockEnv::default(),
+            accounts: BTreeMap::from([(cached, AccountInfo::default())]),
+            storage: BTreeMap::from([(cached, BTreeMap::from([(U256::from(1), U256::ZERO)]))]),
+        };
+        let mut offline = OfflineState::new("cache.json".into(), &file);
+        let loaded = |address: Address, slots: &[u64]| {
+            let mut account = Account::from(AccountInfo::default());
+            for slot in slots {
+                account
+                    .storage
+                    .insert(U256::from(*slot), EvmStorageSlot::new(U256::ZERO));
+            }
+            EvmState::from_iter([(address, account)])
+        };
+
+        offline.check(&loaded(cached, &[1])).unwrap();
+        assert_eq!(
+            offline
+                .check(&loaded(cached, &[2]))
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "Offline fork state cache.json has no slot 0x2 of {cached}, run once online to fill the cache"
+            )
+        );
+        let uncached = Address::repeat_byte(0x2);
+        assert!(matches!(
+            offline.check(&loaded(uncached, &[])),
+            Err(ForkCallError::OfflineStateMiss(..))
+        ));
+
+        // Committed state is known from then on, and so is all the storage
+        // of accounts created locally.
+        offline.commit(&loaded(cached, &[2]));
+        offline.check(&loaded(cached, &[2])).unwrap();
+        let mut created = loaded(uncached, &[]);
+        created.get_mut(&uncached).unwrap().mark_created();
+        offline.check(&created).unwrap();
+        offline.commit(&created);
+        offline.check(&loaded(uncached, &[7])).unwrap();
+    }
+}
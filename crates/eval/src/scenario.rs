@@ -0,0 +1,659 @@
+use crate::error::ForkCallError;
+use crate::fork::Forker;
+use crate::namespace::qualify_namespace;
+use crate::suite::{TestCase, TestCaseError, TestFailure, parse_word};
+use alloy::primitives::{Address, B256, Bytes, U256};
+use alloy::sol;
+use rain_interpreter_bindings::IInterpreterStoreV3::{getCall, setCall};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+sol! {
+    interface IERC20 {
+        function balanceOf(address account) external view returns (uint256);
+        function transfer(address to, uint256 amount) external returns (bool);
+        function approve(address spender, uint256 amount) external returns (bool);
+    }
+}
+
+/// A scripted sequence of state changes, evals and assertions that run
+/// against the same EVM, each step seeing the state left by the previous.
+///
+/// Anywhere a step takes an address it also accepts a label, either from
+/// `labels` or the name of an earlier `deploy` step. Amounts and store
+/// values are decimal or 0x-prefixed hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    /// The Rainlang contract used by `eval` and `assertStore` steps.
+    #[serde(default)]
+    pub rainlang: Option<String>,
+    #[serde(default)]
+    pub labels: HashMap<String, Address>,
+    pub steps: Vec<ScenarioStep>,
+}
+
+/// A single step of a [`Scenario`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum ScenarioStep {
+    /// Deploys creation code and labels the new contract with `name`.
+    Deploy {
+        name: String,
+        from: String,
+        code: Bytes,
+    },
+    /// Sends arbitrary calldata, failing the step if it reverts.
+    Call {
+        from: String,
+        to: String,
+        data: Bytes,
+        #[serde(default)]
+        value: Option<String>,
+    },
+    Transfer {
+        token: String,
+        from: String,
+        to: String,
+        amount: String,
+    },
+    Approve {
+        token: String,
+        owner: String,
+        spender: String,
+        amount: String,
+    },
+    /// Evaluates a test case. The case's namespace is the state namespace
+    /// of `sender`, and with `commit` the writes are set in the store as
+    /// `sender`, so later steps can read them.
+    Eval {
+        #[serde(flatten)]
+        case: TestCase,
+        #[serde(default)]
+        sender: Option<String>,
+        #[serde(default)]
+        commit: bool,
+    },
+    AdvanceTime {
+        seconds: u64,
+    },
+    AdvanceBlocks {
+        blocks: u64,
+    },
+    /// Rolls the active fork to `block`, or back to the fork block.
+    RollFork {
+        #[serde(default)]
+        block: Option<u64>,
+    },
+    AssertBalance {
+        token: String,
+        account: String,
+        expected: String,
+    },
+    /// Checks a value in the store under `sender`'s state namespace.
+    AssertStore {
+        #[serde(default)]
+        sender: Option<String>,
+        #[serde(default)]
+        namespace: Option<String>,
+        key: String,
+        expected: String,
+    },
+}
+
+impl fmt::Display for ScenarioStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioStep::Deploy { name, from, .. } => write!(f, "deploy {name} from {from}"),
+            ScenarioStep::Call { from, to, .. } => write!(f, "call {to} from {from}"),
+            ScenarioStep::Transfer {
+                token,
+                from,
+                to,
+                amount,
+            } => write!(f, "transfer {amount} {token} from {from} to {to}"),
+            ScenarioStep::Approve {
+                token,
+                owner,
+                spender,
+                amount,
+            } => write!(f, "approve {amount} {token} from {owner} to {spender}"),
+            ScenarioStep::Eval { case, commit, .. } => {
+                write!(f, "eval {}", case.name)?;
+                if *commit {
+                    write!(f, " and commit")?;
+                }
+                Ok(())
+            }
+            ScenarioStep::AdvanceTime { seconds } => write!(f, "advance time {seconds}s"),
+            ScenarioStep::AdvanceBlocks { blocks } => write!(f, "advance {blocks} blocks"),
+            ScenarioStep::RollFork { block } => match block {
+                Some(block) => write!(f, "roll fork to block {block}"),
+                None => write!(f, "roll fork to fork block"),
+            },
+            ScenarioStep::AssertBalance { token, account, .. } => {
+                write!(f, "assert {token} balance of {account}")
+            }
+            ScenarioStep::AssertStore { key, .. } => write!(f, "assert store key {key}"),
+        }
+    }
+}
+
+/// Errors that stop a scenario step from running.
+#[derive(Error, Debug)]
+pub enum ScenarioError {
+    #[error("Unknown address or label {0}")]
+    UnknownAddress(String),
+    #[error("No Rainlang address for the scenario")]
+    MissingRainlang,
+    #[error("{0} reverted")]
+    Reverted(String),
+    #[error(transparent)]
+    ForkCallError(#[from] ForkCallError),
+    #[error(transparent)]
+    TestCaseError(#[from] TestCaseError),
+}
+
+/// Result of running a single scenario step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    pub index: usize,
+    pub description: String,
+    pub failures: Vec<TestFailure>,
+}
+
+impl StepReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Result of running a scenario. Running stops at the first failed step,
+/// so steps after it are missing from the report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioReport {
+    pub steps: Vec<StepReport>,
+    pub total_steps: usize,
+}
+
+impl ScenarioReport {
+    pub fn passed(&self) -> bool {
+        self.steps.len() == self.total_steps && self.steps.iter().all(StepReport::passed)
+    }
+}
+
+impl fmt::Display for ScenarioReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            let status = if step.passed() { "PASS" } else { "FAIL" };
+            writeln!(f, "{status} step {}: {}", step.index, step.description)?;
+            for failure in &step.failures {
+                for line in failure.to_string().lines() {
+                    writeln!(f, "  {line}")?;
+                }
+            }
+        }
+        let skipped = self.total_steps - self.steps.len();
+        if skipped > 0 {
+            writeln!(f, "{skipped} step(s) skipped")?;
+        }
+        Ok(())
+    }
+}
+
+/// Addresses known to a running scenario.
+struct Labels(HashMap<String, Address>);
+
+impl Labels {
+    fn address(&self, value: &str) -> Result<Address, ScenarioError> {
+        if let Some(address) = self.0.get(value) {
+            return Ok(*address);
+        }
+        Address::from_str(value).map_err(|_| ScenarioError::UnknownAddress(value.to_string()))
+    }
+
+    fn sender(&self, value: Option<&String>) -> Result<Address, ScenarioError> {
+        value.map_or(Ok(Address::ZERO), |value| self.address(value))
+    }
+}
+
+impl Forker {
+    /// Runs the steps of a scenario in order, committing their state changes
+    /// to this forker. `rainlang` takes precedence over the scenario's own
+    /// Rainlang address.
+    pub async fn run_scenario(
+        &mut self,
+        scenario: &Scenario,
+        rainlang: Option<Address>,
+    ) -> ScenarioReport {
+        let mut labels = Labels(scenario.labels.clone());
+        let mut steps = Vec::with_capacity(scenario.steps.len());
+
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let step_rainlang = match rainlang {
+                Some(rainlang) => Ok(rainlang),
+                None => scenario
+                    .rainlang
+                    .as_deref()
+                    .ok_or(ScenarioError::MissingRainlang)
+                    .and_then(|rainlang| labels.address(rainlang)),
+            };
+            let failures = match self.run_step(step, &mut labels, step_rainlang).await {
+                Ok(failures) => failures,
+                Err(e) => vec![TestFailure::Error(e.to_string())],
+            };
+            let passed = failures.is_empty();
+            steps.push(StepReport {
+                index,
+                description: step.to_string(),
+                failures,
+            });
+            if !passed {
+                break;
+            }
+        }
+
+        ScenarioReport {
+            steps,
+            total_steps: scenario.steps.len(),
+        }
+    }
+
+    async fn run_step(
+        &mut self,
+        step: &ScenarioStep,
+        labels: &mut Labels,
+        rainlang: Result<Address, ScenarioError>,
+    ) -> Result<Vec<TestFailure>, ScenarioError> {
+        match step {
+            ScenarioStep::Deploy { name, from, code } => {
+                let address = self.deploy(labels.address(from)?, code)?;
+                labels.0.insert(name.clone(), address);
+            }
+            ScenarioStep::Call {
+                from,
+                to,
+                data,
+                value,
+            } => {
+                let value = value.as_deref().map(word).transpose()?.unwrap_or_default();
+                let raw = self
+                    .spawn_call_committing(labels.address(from)?, labels.address(to)?, data, value)
+                    .await?;
+                if !raw.exit_reason.is_ok() {
+                    return Err(ScenarioError::Reverted(step.to_string()));
+                }
+            }
+            ScenarioStep::Transfer {
+                token,
+                from,
+                to,
+                amount,
+            } => {
+                let call = IERC20::transferCall {
+                    to: labels.address(to)?,
+                    amount: parse_word(amount, false)?,
+                };
+                let res = self
+                    .alloy_call_committing(
+                        labels.address(from)?,
+                        labels.address(token)?,
+                        call,
+                        U256::ZERO,
+                        false,
+                    )
+                    .await?;
+                if !res.typed_return {
+                    return Err(ScenarioError::Reverted(step.to_string()));
+                }
+            }
+            ScenarioStep::Approve {
+                token,
+                owner,
+                spender,
+                amount,
+            } => {
+                let call = IERC20::approveCall {
+                    spender: labels.address(spender)?,
+                    amount: parse_word(amount, false)?,
+                };
+                let res = self
+                    .alloy_call_committing(
+                        labels.address(owner)?,
+                        labels.address(token)?,
+                        call,
+                        U256::ZERO,
+                        false,
+                    )
+                    .await?;
+                if !res.typed_return {
+                    return Err(ScenarioError::Reverted(step.to_string()));
+                }
+            }
+            ScenarioStep::Eval {
+                case,
+                sender,
+                commit,
+            } => {
+                let rainlang = rainlang?;
+                let sender = labels.sender(sender.as_ref())?;
+                let mut args = case.eval_args(rainlang)?;
+                let state_namespace = args.namespace.into_underlying();
+                args.namespace = qualify_namespace(state_namespace.into(), sender);
+                args.from = Some(sender);
+
+                let result = self.fork_eval_result(args).await?;
+                let failures = case.check(&result);
+
+                if *commit && !result.reverted && !result.writes.is_empty() {
+                    let store = self.resolve_dispair(rainlang, false).await?.store;
+                    let call = setCall {
+                        namespace: state_namespace,
+                        kvs: result.writes.iter().map(|word| B256::from(*word)).collect(),
+                    };
+                    self.alloy_call_committing(sender, store, call, U256::ZERO, false)
+                        .await?;
+                }
+                return Ok(failures);
+            }
+            ScenarioStep::AdvanceTime { seconds } => {
+                let block_env = &mut self.executor_mut().env_mut().evm_env.block_env;
+                block_env.timestamp = block_env.timestamp.saturating_add(*seconds);
+            }
+            ScenarioStep::AdvanceBlocks { blocks } => {
+                let block_env = &mut self.executor_mut().env_mut().evm_env.block_env;
+                block_env.number = block_env.number.saturating_add(*blocks);
+            }
+            ScenarioStep::RollFork { block } => {
+                self.roll_fork(*block, None)?;
+            }
+            ScenarioStep::AssertBalance {
+                token,
+                account,
+                expected,
+            } => {
+                let balance = self
+                    .alloy_call(
+                        Address::ZERO,
+                        labels.address(token)?,
+                        IERC20::balanceOfCall {
+                            account: labels.address(account)?,
+                        },
+                        false,
+                    )
+                    .await?
+                    .typed_return;
+                if balance != parse_word(expected, false)? {
+                    return Ok(vec![TestFailure::Mismatch {
+                        subject: format!("{token} balance of {account}"),
+                        expected: expected.clone(),
+                        actual: balance.to_string(),
+                    }]);
+                }
+            }
+            ScenarioStep::AssertStore {
+                sender,
+                namespace,
+                key,
+                expected,
+            } => {
+                let store = self.resolve_dispair(rainlang?, false).await?.store;
+                let state_namespace = namespace.as_deref().map(word).transpose()?;
+                let namespace = qualify_namespace(
+                    state_namespace.unwrap_or_default().into(),
+                    labels.sender(sender.as_ref())?,
+                );
+                let value = self
+                    .alloy_call(
+                        Address::ZERO,
+                        store,
+                        getCall {
+                            namespace: namespace.into(),
+                            key: parse_word(key, false)?.into(),
+                        },
+                        false,
+                    )
+                    .await?
+                    .typed_return;
+                let value = U256::from_be_bytes(value.0);
+                if value != parse_word(expected, false)? {
+                    return Ok(vec![TestFailure::Mismatch {
+                        subject: format!("store key {key}"),
+                        expected: expected.clone(),
+                        actual: value.to_string(),
+                    }]);
+                }
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fork::NewForkedEvm;
+    use alloy::sol_types::SolConstructor;
+    use rain_interpreter_test_fixtures::{ERC20, LocalEvm};
+
+    fn token_code(recipient: Address, supply: u64) -> Bytes {
+        [
+            ERC20::BYTECODE.as_ref(),
+            &ERC20::constructorCall {
+                name_: "Token".into(),
+                symbol_: "TKN".into(),
+                decimals_: 18,
+                recipient_: recipient,
+                supply_: U256::from(supply),
+            }
+            .abi_encode(),
+        ]
+        .concat()
+        .into()
+    }
+
+    #[test]
+    fn test_deserialize_scenario() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "rainlang": "rainlang",
+                "labels": {
+                    "rainlang": "0x0000000000000000000000000000000000000001",
+                    "alice": "0x0000000000000000000000000000000000000002"
+                },
+                "steps": [
+                    { "action": "transfer", "token": "token", "from": "alice", "to": "0x0000000000000000000000000000000000000003", "amount": "100" },
+                    { "action": "eval", "name": "set", "expression": ":set(1 2);", "sender": "alice", "commit": true, "expect": {} },
+                    { "action": "advanceTime", "seconds": 60 },
+                    { "action": "rollFork" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.labels["alice"], Address::with_last_byte(2));
+        assert_eq!(scenario.steps.len(), 4);
+        assert!(matches!(
+            &scenario.steps[1],
+            ScenarioStep::Eval { case, commit: true, .. } if case.expression == ":set(1 2);"
+        ));
+        assert_eq!(scenario.steps[2], ScenarioStep::AdvanceTime { seconds: 60 });
+        assert_eq!(scenario.steps[3], ScenarioStep::RollFork { block: None });
+        assert_eq!(
+            scenario.steps[0].to_string(),
+            "transfer 100 token from alice to 0x0000000000000000000000000000000000000003"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_scenario_in_memory() {
+        let mut forker = Forker::new().unwrap();
+        let alice = Address::repeat_byte(0x1);
+        let bob = Address::repeat_byte(0x2);
+        let scenario = Scenario {
+            rainlang: None,
+            labels: HashMap::from([("alice".to_string(), alice), ("bob".to_string(), bob)]),
+            steps: vec![
+                ScenarioStep::Deploy {
+                    name: "token".into(),
+                    from: "alice".into(),
+                    code: token_code(alice, 1000),
+                },
+                ScenarioStep::Transfer {
+                    token: "token".into(),
+                    from: "alice".into(),
+                    to: "bob".into(),
+                    amount: "250".into(),
+                },
+                ScenarioStep::Approve {
+                    token: "token".into(),
+                    owner: "bob".into(),
+                    spender: "alice".into(),
+                    amount: "0x10".into(),
+                },
+                ScenarioStep::AssertBalance {
+                    token: "token".into(),
+                    account: "alice".into(),
+                    expected: "750".into(),
+                },
+                ScenarioStep::AssertBalance {
+                    token: "token".into(),
+                    account: "bob".into(),
+                    expected: "250".into(),
+                },
+                ScenarioStep::AdvanceTime { seconds: 60 },
+            ],
+        };
+
+        let timestamp = forker.executor.env().evm_env.block_env.timestamp;
+        let report = forker.run_scenario(&scenario, None).await;
+        assert!(report.passed(), "{report}");
+        assert_eq!(
+            forker.executor.env().evm_env.block_env.timestamp,
+            timestamp + 60
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_scenario_stops_at_failure() {
+        let mut forker = Forker::new().unwrap();
+        let alice = Address::repeat_byte(0x1);
+        let scenario = Scenario {
+            rainlang: None,
+            labels: HashMap::from([("alice".to_string(), alice)]),
+            steps: vec![
+                ScenarioStep::Deploy {
+                    name: "token".into(),
+                    from: "alice".into(),
+                    code: token_code(alice, 1000),
+                },
+                ScenarioStep::AssertBalance {
+                    token: "token".into(),
+                    account: "alice".into(),
+                    expected: "1".into(),
+                },
+                ScenarioStep::AdvanceBlocks { blocks: 1 },
+            ],
+        };
+
+        let report = forker.run_scenario(&scenario, None).await;
+        assert!(!report.passed());
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(
+            report.to_string(),
+            "PASS step 0: deploy token from alice\n\
+             FAIL step 1: assert token balance of alice\n  \
+             token balance of alice: expected 1, got 1000\n\
+             1 step(s) skipped\n"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_scenario_eval_commit() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let mut forker = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "labels": { "alice": "0x0101010101010101010101010101010101010101" },
+                "steps": [
+                    {
+                        "action": "eval",
+                        "name": "set",
+                        "expression": ":set(1 42);",
+                        "sender": "alice",
+                        "commit": true,
+                        "expect": { "writes": ["1", "42"] }
+                    },
+                    { "action": "assertStore", "sender": "alice", "key": "1", "expected": "42" },
+                    {
+                        "action": "eval",
+                        "name": "get",
+                        "expression": "_: get(1);",
+                        "sender": "alice",
+                        "expect": { "stack": ["42"] }
+                    },
+                    {
+                        "action": "eval",
+                        "name": "other sender",
+                        "expression": "_: get(1);",
+                        "expect": { "stack": ["0"] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let report = forker
+            .run_scenario(&scenario, Some(local_evm.rainlang))
+            .await;
+        assert!(report.passed(), "{report}");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_scenario_missing_rainlang() {
+        let mut forker = Forker::new().unwrap();
+        let scenario: Scenario = serde_json::from_str(
+            r#"{ "steps": [{ "action": "eval", "name": "e", "expression": "_: 1;", "expect": {} }] }"#,
+        )
+        .unwrap();
+
+        let report = forker.run_scenario(&scenario, None).await;
+        assert_eq!(
+            report.steps[0].failures,
+            vec![TestFailure::Error(
+                "No Rainlang address for the scenario".into()
+            )]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_scenario_invalid_amount() {
+        let mut forker = Forker::new().unwrap();
+        let scenario = Scenario {
+            rainlang: None,
+            labels: HashMap::new(),
+            steps: vec![ScenarioStep::Transfer {
+                token: Address::ZERO.to_string(),
+                from: Address::ZERO.to_string(),
+                to: Address::ZERO.to_string(),
+                amount: "0xnope".into(),
+            }],
+        };
+
+        let report = forker.run_scenario(&scenario, None).await;
+        assert_eq!(
+            report.steps[0].failures,
+            vec![TestFailure::Error("Invalid value 0xnope".into())]
+        );
+    }
+}
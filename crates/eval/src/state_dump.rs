@@ -0,0 +1,269 @@
+use crate::error::ForkCallError;
+use crate::fork::{Forker, local_evm_env};
+use crate::hardfork;
+use alloy::primitives::{Address, Bytes, U256};
+use foundry_evm::Env;
+use revm::bytecode::Bytecode;
+use revm::state::AccountInfo;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+/// Chain state in the format of anvil's `--dump-state` and
+/// `anvil_dumpState`. Only the accounts and the block are used, the block
+/// and transaction history is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct StateDump {
+    #[serde(default)]
+    pub block: Option<DumpBlock>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub best_block_number: Option<u64>,
+    pub accounts: BTreeMap<Address, DumpAccount>,
+    /// Not part of anvil dumps, which do not record the chain. Add it to
+    /// evaluate as a specific chain, e.g. for `chain-id` or the hardfork
+    /// schedule.
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub chain_id: Option<u64>,
+}
+
+/// The block environment of a [`StateDump`]. Numbers are read both as JSON
+/// numbers and as hex or decimal strings, as anvil versions differ.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct DumpBlock {
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub number: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub timestamp: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub gas_limit: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub basefee: Option<u64>,
+    #[serde(default, alias = "coinbase")]
+    pub beneficiary: Option<Address>,
+}
+
+/// An account of a [`StateDump`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct DumpAccount {
+    #[serde(default, deserialize_with = "deserialize_u64")]
+    pub nonce: u64,
+    #[serde(default)]
+    pub balance: U256,
+    #[serde(default)]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+#[derive(Debug, Error)]
+pub enum StateDumpError {
+    #[error("Failed to read state dump {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Invalid state dump {0}: {1}")]
+    Json(String, serde_json::Error),
+}
+
+impl StateDump {
+    /// Reads a JSON state dump file.
+    pub fn from_file(path: &Path) -> Result<Self, StateDumpError> {
+        let display = path.display().to_string();
+        let data = std::fs::read(path).map_err(|e| StateDumpError::Io(display.clone(), e))?;
+        serde_json::from_slice(&data).map_err(|e| StateDumpError::Json(display, e))
+    }
+
+    /// The environment the dump's block ran in. Unset fields keep the
+    /// defaults of a forker without forks.
+    fn env(&self) -> Env {
+        let mut env = local_evm_env();
+        if let Some(chain_id) = self.chain_id {
+            env.evm_env.cfg_env.chain_id = chain_id;
+            env.tx.chain_id = Some(chain_id);
+        }
+        let block_env = &mut env.evm_env.block_env;
+        let block = self.block.clone().unwrap_or_default();
+        if let Some(number) = block.number.or(self.best_block_number) {
+            block_env.number = number;
+        }
+        if let Some(timestamp) = block.timestamp {
+            block_env.timestamp = timestamp;
+        }
+        if let Some(gas_limit) = block.gas_limit {
+            block_env.gas_limit = gas_limit;
+        }
+        if let Some(basefee) = block.basefee {
+            block_env.basefee = basefee;
+        }
+        if let Some(beneficiary) = block.beneficiary {
+            block_env.beneficiary = beneficiary;
+        }
+        env
+    }
+}
+
+impl Forker {
+    /// A forker whose state is loaded from a state dump rather than forked
+    /// over RPC. It holds no fork, so nothing is ever fetched and every eval
+    /// is deterministic. `env` overrides the environment of the dump.
+    pub fn new_from_state_dump(dump: StateDump, env: Option<Env>) -> Result<Forker, ForkCallError> {
+        let env = env.unwrap_or_else(|| dump.env());
+        let spec_id = hardfork::spec_id(
+            env.evm_env.cfg_env.chain_id,
+            env.evm_env.block_env.number,
+            env.evm_env.block_env.timestamp,
+        );
+
+        let mut accounts = vec![];
+        let mut storage = vec![];
+        for (address, account) in dump.accounts {
+            let code = Bytecode::new_raw(account.code);
+            accounts.push((
+                address,
+                AccountInfo {
+                    balance: account.balance,
+                    nonce: account.nonce,
+                    code_hash: code.hash_slow(),
+                    code: Some(code),
+                },
+            ));
+            storage.extend(
+                account
+                    .storage
+                    .into_iter()
+                    .map(|(slot, value)| (address, slot, value)),
+            );
+        }
+
+        Forker::new_with_state(env, spec_id, accounts, storage, None)
+    }
+}
+
+/// Reads a `u64` given as a JSON number or as a hex or decimal string.
+fn deserialize_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(number) => Ok(number),
+        NumberOrString::String(string) => {
+            let parsed = match string.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => string.parse(),
+            };
+            parsed.map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+fn deserialize_opt_u64<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_u64")] u64);
+
+    Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(number)| number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SLOAD(0), returned as a word.
+    const RETURN_SLOT_0: &str = "0x60005460005260206000f3";
+
+    const DUMP: &str = r#"{
+        "block": {
+            "number": "0x10",
+            "coinbase": "0x0000000000000000000000000000000000000000",
+            "timestamp": 1700000000,
+            "gas_limit": "30000000",
+            "basefee": "0x3b9aca00"
+        },
+        "accounts": {
+            "0x0101010101010101010101010101010101010101": {
+                "nonce": 1,
+                "balance": "0x0",
+                "code": "0x60005460005260206000f3",
+                "storage": { "0x0": "0x2a" }
+            },
+            "0x0202020202020202020202020202020202020202": {
+                "nonce": 0,
+                "balance": "0xde0b6b3a7640000",
+                "code": "0x",
+                "storage": {}
+            }
+        },
+        "best_block_number": "0x10",
+        "blocks": [],
+        "transactions": []
+    }"#;
+
+    #[test]
+    fn test_deserialize() {
+        let dump: StateDump = serde_json::from_str(DUMP).unwrap();
+        let block = dump.block.clone().unwrap();
+        assert_eq!(block.number, Some(16));
+        assert_eq!(block.timestamp, Some(1_700_000_000));
+        assert_eq!(block.gas_limit, Some(30_000_000));
+        assert_eq!(block.basefee, Some(1_000_000_000));
+        assert_eq!(dump.best_block_number, Some(16));
+        assert_eq!(dump.chain_id, None);
+
+        let contract = &dump.accounts[&Address::repeat_byte(0x1)];
+        assert_eq!(contract.nonce, 1);
+        assert_eq!(contract.code.to_string(), RETURN_SLOT_0);
+        assert_eq!(contract.storage[&U256::ZERO], U256::from(42));
+        assert_eq!(
+            dump.accounts[&Address::repeat_byte(0x2)].balance,
+            U256::from(10).pow(U256::from(18))
+        );
+
+        assert!(
+            serde_json::from_str::<StateDump>(r#"{ "accounts": {}, "chain_id": "x" }"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_new_from_state_dump() {
+        let mut dump: StateDump = serde_json::from_str(DUMP).unwrap();
+        dump.chain_id = Some(31337);
+        let forker = Forker::new_from_state_dump(dump, None).unwrap();
+
+        let env = &forker.executor.env().evm_env;
+        assert_eq!(env.cfg_env.chain_id, 31337);
+        assert_eq!(env.block_env.number, 16);
+        assert_eq!(env.block_env.timestamp, 1_700_000_000);
+
+        let result = forker
+            .call(
+                Address::ZERO.as_slice(),
+                Address::repeat_byte(0x1).as_slice(),
+                &[],
+            )
+            .unwrap();
+        assert_eq!(U256::from_be_slice(&result.result), U256::from(42));
+        assert!(forker.forks().is_empty());
+    }
+
+    #[test]
+    fn test_from_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), DUMP).unwrap();
+        let dump = StateDump::from_file(file.path()).unwrap();
+        assert_eq!(dump.accounts.len(), 2);
+
+        assert!(matches!(
+            StateDump::from_file(Path::new("/nonexistent/state.json")),
+            Err(StateDumpError::Io(..))
+        ));
+        std::fs::write(file.path(), "{}").unwrap();
+        assert!(matches!(
+            StateDump::from_file(file.path()),
+            Err(StateDumpError::Json(..))
+        ));
+    }
+}
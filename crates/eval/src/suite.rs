@@ -0,0 +1,580 @@
+use crate::error::ForkCallError;
+use crate::eval::ForkEvalArgs;
+use crate::float::{DecimalFloat, FloatError};
+use crate::fork::{EnvOverrides, Forker};
+use crate::trace::{RainEvalOutcome, RainEvalResult};
+use alloy::primitives::{Address, U256};
+use rain_interpreter_bindings::IInterpreterStoreV3::FullyQualifiedNamespace;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// A file of declarative Rainlang test cases.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSuite {
+    /// The Rainlang contract to evaluate against, unless overridden by the
+    /// caller.
+    #[serde(default)]
+    pub rainlang: Option<Address>,
+    pub cases: Vec<TestCase>,
+}
+
+/// A single expression evaluation and what it is expected to produce.
+///
+/// Values are decimal integers or 0x-prefixed hex words. When `float` is set
+/// decimal values are Rain decimal floats instead, such as `1.5`, and
+/// expected stack and writes compare numerically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCase {
+    pub name: String,
+    pub expression: String,
+    #[serde(default)]
+    pub source_index: u16,
+    #[serde(default)]
+    pub context: Vec<Vec<String>>,
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub block: Option<BlockOverrides>,
+    #[serde(default)]
+    pub float: bool,
+    pub expect: TestExpectation,
+}
+
+/// Block environment seen by the expression. State is still read at the
+/// fork block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockOverrides {
+    pub number: Option<u64>,
+    pub timestamp: Option<u64>,
+}
+
+/// Expected outcome of a test case. Unset fields are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestExpectation {
+    pub stack: Option<Vec<String>>,
+    pub writes: Option<Vec<String>>,
+    /// Expected revert, matched against the `ensure` reason or the decoded
+    /// error. An empty string matches any revert.
+    pub revert: Option<String>,
+}
+
+/// Errors in the values of a test case.
+#[derive(Error, Debug)]
+pub enum TestCaseError {
+    #[error("Invalid value {0}")]
+    InvalidValue(String),
+    #[error(transparent)]
+    Float(#[from] FloatError),
+}
+
+/// Errors evaluating a test case, reported as its [`TestFailure::Error`].
+#[derive(Error, Debug)]
+pub enum TestSuiteError {
+    #[error(transparent)]
+    TestCaseError(#[from] TestCaseError),
+    #[error(transparent)]
+    ForkCallError(#[from] ForkCallError),
+}
+
+/// A mismatch between the expected and actual outcome of a test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestFailure {
+    Stack {
+        expected: Vec<String>,
+        actual: Vec<String>,
+    },
+    Writes {
+        expected: Vec<String>,
+        actual: Vec<String>,
+    },
+    UnexpectedRevert(String),
+    MissingRevert(String),
+    RevertMismatch {
+        expected: String,
+        actual: String,
+    },
+    Mismatch {
+        subject: String,
+        expected: String,
+        actual: String,
+    },
+    Error(String),
+}
+
+impl fmt::Display for TestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestFailure::Stack { expected, actual } => write_diff(f, "stack", expected, actual),
+            TestFailure::Writes { expected, actual } => write_diff(f, "writes", expected, actual),
+            TestFailure::UnexpectedRevert(actual) => write!(f, "unexpected revert: {actual}"),
+            TestFailure::MissingRevert(expected) => {
+                write!(f, "expected revert \"{expected}\" but eval succeeded")
+            }
+            TestFailure::RevertMismatch { expected, actual } => {
+                write!(f, "expected revert \"{expected}\", got: {actual}")
+            }
+            TestFailure::Mismatch {
+                subject,
+                expected,
+                actual,
+            } => write!(f, "{subject}: expected {expected}, got {actual}"),
+            TestFailure::Error(error) => write!(f, "error: {error}"),
+        }
+    }
+}
+
+/// Writes a line per index, marking the expected (`-`) and actual (`+`)
+/// values that differ.
+fn write_diff(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    expected: &[String],
+    actual: &[String],
+) -> fmt::Result {
+    writeln!(f, "{name} mismatch:")?;
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => writeln!(f, "    [{i}] {e}")?,
+            (e, a) => {
+                if let Some(e) = e {
+                    writeln!(f, "  - [{i}] {e}")?;
+                }
+                if let Some(a) = a {
+                    writeln!(f, "  + [{i}] {a}")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Result of running a single test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCaseReport {
+    pub name: String,
+    pub failures: Vec<TestFailure>,
+}
+
+impl TestCaseReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for TestCaseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passed() {
+            return writeln!(f, "PASS {}", self.name);
+        }
+        writeln!(f, "FAIL {}", self.name)?;
+        for failure in &self.failures {
+            for line in failure.to_string().lines() {
+                writeln!(f, "  {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a decimal integer or 0x-prefixed hex value into a stack word.
+/// With `float` decimal values are parsed as Rain decimal floats instead.
+pub(crate) fn parse_word(value: &str, float: bool) -> Result<U256, TestCaseError> {
+    if value.starts_with("0x") || value.starts_with("0X") {
+        return U256::from_str_radix(&value[2..], 16)
+            .map_err(|_| TestCaseError::InvalidValue(value.to_string()));
+    }
+    if float {
+        return Ok(value.parse::<DecimalFloat>()?.to_word()?);
+    }
+    value
+        .parse::<U256>()
+        .map_err(|_| TestCaseError::InvalidValue(value.to_string()))
+}
+
+impl TestCase {
+    /// Parses a value of this case into a stack word.
+    fn word(&self, value: &str) -> Result<U256, TestCaseError> {
+        parse_word(value, self.float)
+    }
+
+    /// Whether an actual stack word matches an expected value.
+    fn matches(&self, expected: &str, actual: U256) -> bool {
+        let hex = expected.starts_with("0x") || expected.starts_with("0X");
+        if self.float && !hex {
+            return expected
+                .parse::<DecimalFloat>()
+                .is_ok_and(|expected| expected == DecimalFloat::from_word(actual));
+        }
+        self.word(expected).is_ok_and(|expected| expected == actual)
+    }
+
+    /// Formats an actual stack word the way expected values are written.
+    fn format(&self, actual: U256) -> String {
+        if self.float {
+            DecimalFloat::from_word(actual).to_string()
+        } else {
+            actual.to_string()
+        }
+    }
+
+    /// Builds the eval args for this case.
+    pub fn eval_args(&self, rainlang: Address) -> Result<ForkEvalArgs, TestCaseError> {
+        let words = |values: &[String]| {
+            values
+                .iter()
+                .map(|value| self.word(value))
+                .collect::<Result<Vec<U256>, _>>()
+        };
+
+        Ok(ForkEvalArgs {
+            rainlang_string: self.expression.clone(),
+            source_index: self.source_index,
+            rainlang,
+            namespace: FullyQualifiedNamespace::from(
+                self.namespace
+                    .as_deref()
+                    .map(|namespace| self.word(namespace))
+                    .transpose()?
+                    .unwrap_or_default(),
+            ),
+            context: self
+                .context
+                .iter()
+                .map(|column| words(column))
+                .collect::<Result<_, _>>()?,
+            decode_errors: false,
+            inputs: words(&self.inputs)?,
+            state_overlay: vec![],
+            bytecode: None,
+            dispair: None,
+            from: None,
+            env: EnvOverrides {
+                number: self.block.as_ref().and_then(|block| block.number),
+                timestamp: self.block.as_ref().and_then(|block| block.timestamp),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Compares the result of evaluating this case against its expectation.
+    pub fn check(&self, result: &RainEvalResult) -> Vec<TestFailure> {
+        let mut failures = vec![];
+
+        let actual_revert = result.reverted.then(|| match &result.outcome {
+            RainEvalOutcome::EnsureFailed { reason, .. } => reason.clone(),
+            _ => result
+                .error
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        });
+        match (&self.expect.revert, actual_revert) {
+            (Some(expected), Some(actual)) => {
+                if !actual.contains(expected.as_str()) {
+                    failures.push(TestFailure::RevertMismatch {
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+                return failures;
+            }
+            (Some(expected), None) => failures.push(TestFailure::MissingRevert(expected.clone())),
+            (None, Some(actual)) => {
+                failures.push(TestFailure::UnexpectedRevert(actual));
+                return failures;
+            }
+            (None, None) => {}
+        }
+
+        let compare = |expected: &Vec<String>, actual: &[U256]| {
+            let matches = expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| self.matches(expected, *actual));
+            (!matches).then(|| {
+                (
+                    expected.clone(),
+                    actual.iter().map(|word| self.format(*word)).collect(),
+                )
+            })
+        };
+        if let Some((expected, actual)) = self
+            .expect
+            .stack
+            .as_ref()
+            .and_then(|expected| compare(expected, &result.stack))
+        {
+            failures.push(TestFailure::Stack { expected, actual });
+        }
+        if let Some((expected, actual)) = self
+            .expect
+            .writes
+            .as_ref()
+            .and_then(|expected| compare(expected, &result.writes))
+        {
+            failures.push(TestFailure::Writes { expected, actual });
+        }
+
+        failures
+    }
+}
+
+impl Forker {
+    /// Evaluates a single test case and checks it against its expectation.
+    ///
+    /// Errors evaluating the case are reported as a failure of the case.
+    pub async fn run_test_case(&self, case: &TestCase, rainlang: Address) -> TestCaseReport {
+        let failures = match self.eval_test_case(case, rainlang).await {
+            Ok(result) => case.check(&result),
+            Err(e) => vec![TestFailure::Error(e.to_string())],
+        };
+        TestCaseReport {
+            name: case.name.clone(),
+            failures,
+        }
+    }
+
+    /// Runs every case of the suite in order. `rainlang` takes precedence
+    /// over the suite's own Rainlang address.
+    pub async fn run_test_suite(
+        &self,
+        suite: &TestSuite,
+        rainlang: Option<Address>,
+    ) -> Vec<TestCaseReport> {
+        let rainlang = rainlang.or(suite.rainlang).unwrap_or_default();
+        let mut reports = Vec::with_capacity(suite.cases.len());
+        for case in &suite.cases {
+            reports.push(self.run_test_case(case, rainlang).await);
+        }
+        reports
+    }
+
+    async fn eval_test_case(
+        &self,
+        case: &TestCase,
+        rainlang: Address,
+    ) -> Result<RainEvalResult, TestSuiteError> {
+        let args = case.eval_args(rainlang)?;
+        Ok(self.fork_eval_result(args).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fork::NewForkedEvm;
+    use rain_interpreter_test_fixtures::LocalEvm;
+
+    fn case(expect: TestExpectation) -> TestCase {
+        TestCase {
+            name: "case".into(),
+            expression: "_: 1;".into(),
+            source_index: 0,
+            context: vec![],
+            inputs: vec![],
+            namespace: None,
+            block: None,
+            float: false,
+            expect,
+        }
+    }
+
+    fn result(stack: Vec<U256>) -> RainEvalResult {
+        RainEvalResult {
+            reverted: false,
+            stack,
+            writes: vec![],
+            traces: vec![],
+            error: None,
+            outcome: RainEvalOutcome::Success,
+        }
+    }
+
+    #[test]
+    fn test_deserialize_suite() {
+        let suite: TestSuite = serde_json::from_str(
+            r#"{
+                "rainlang": "0x0000000000000000000000000000000000000001",
+                "cases": [{
+                    "name": "adds",
+                    "expression": "_: add(1 2);",
+                    "context": [["1", "0x02"]],
+                    "block": { "timestamp": 100 },
+                    "float": true,
+                    "expect": { "stack": ["3"] }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(suite.rainlang, Some(Address::with_last_byte(1)));
+        let case = &suite.cases[0];
+        assert_eq!(case.source_index, 0);
+        assert_eq!(case.block.as_ref().unwrap().timestamp, Some(100));
+        assert_eq!(case.expect.stack, Some(vec!["3".to_string()]));
+        assert_eq!(case.expect.revert, None);
+    }
+
+    #[test]
+    fn test_eval_args() {
+        let mut case = case(TestExpectation::default());
+        case.float = true;
+        case.context = vec![vec!["1.5".into(), "0xff".into()]];
+        case.inputs = vec!["2".into()];
+        case.namespace = Some("0x10".into());
+
+        let args = case.eval_args(Address::with_last_byte(1)).unwrap();
+        assert_eq!(
+            args.context[0][0],
+            "1.5".parse::<DecimalFloat>().unwrap().to_word().unwrap()
+        );
+        assert_eq!(args.context[0][1], U256::from(0xff));
+        assert_eq!(args.inputs, vec![U256::from(2)]);
+
+        case.inputs = vec!["nope".into()];
+        assert!(case.eval_args(Address::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_check_stack() {
+        let expected = TestExpectation {
+            stack: Some(vec!["1".into(), "0x02".into()]),
+            ..Default::default()
+        };
+        let case = case(expected);
+        assert!(
+            case.check(&result(vec![U256::from(1), U256::from(2)]))
+                .is_empty()
+        );
+
+        let failures = case.check(&result(vec![U256::from(1), U256::from(3)]));
+        assert_eq!(
+            failures,
+            vec![TestFailure::Stack {
+                expected: vec!["1".into(), "0x02".into()],
+                actual: vec!["1".into(), "3".into()],
+            }]
+        );
+        assert_eq!(
+            failures[0].to_string(),
+            "stack mismatch:\n    [0] 1\n  - [1] 0x02\n  + [1] 3\n"
+        );
+    }
+
+    #[test]
+    fn test_check_float() {
+        let mut case = case(TestExpectation {
+            stack: Some(vec!["1.50".into()]),
+            ..Default::default()
+        });
+        case.float = true;
+        let word = "15e-1".parse::<DecimalFloat>().unwrap().to_word().unwrap();
+        assert!(case.check(&result(vec![word])).is_empty());
+        assert_eq!(
+            case.check(&result(vec![U256::from(2)])),
+            vec![TestFailure::Stack {
+                expected: vec!["1.50".into()],
+                actual: vec!["2".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_revert() {
+        let mut reverted = result(vec![]);
+        reverted.reverted = true;
+        reverted.outcome = RainEvalOutcome::EnsureFailed {
+            reason: "too low".into(),
+            source_index: 0,
+        };
+
+        let expects_revert = case(TestExpectation {
+            revert: Some("too low".into()),
+            ..Default::default()
+        });
+        assert!(expects_revert.check(&reverted).is_empty());
+        assert_eq!(
+            expects_revert.check(&result(vec![])),
+            vec![TestFailure::MissingRevert("too low".into())]
+        );
+
+        let expects_other = case(TestExpectation {
+            revert: Some("too high".into()),
+            ..Default::default()
+        });
+        assert_eq!(
+            expects_other.check(&reverted),
+            vec![TestFailure::RevertMismatch {
+                expected: "too high".into(),
+                actual: "too low".into(),
+            }]
+        );
+
+        let expects_success = case(TestExpectation::default());
+        assert_eq!(
+            expects_success.check(&reverted),
+            vec![TestFailure::UnexpectedRevert("too low".into())]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_test_suite() {
+        let local_evm = LocalEvm::new().await;
+        let args = NewForkedEvm {
+            fork_url: local_evm.url(),
+            fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
+        };
+        let fork = Forker::new_with_fork(args, None, None).await.unwrap();
+
+        let suite: TestSuite = serde_json::from_str(
+            r#"{
+                "cases": [
+                    {
+                        "name": "constant",
+                        "expression": "_: 3;",
+                        "expect": { "stack": ["3"] }
+                    },
+                    {
+                        "name": "context",
+                        "expression": "_: context<0 0>();",
+                        "context": [["7"]],
+                        "expect": { "stack": ["8"] }
+                    },
+                    {
+                        "name": "ensure",
+                        "expression": ":ensure(0 \"always fails\");",
+                        "expect": { "revert": "always fails" }
+                    },
+                    {
+                        "name": "timestamp",
+                        "expression": "_: block-timestamp();",
+                        "block": { "timestamp": 12345 },
+                        "expect": { "stack": ["12345"] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let reports = fork.run_test_suite(&suite, Some(local_evm.rainlang)).await;
+        assert_eq!(reports.len(), 4);
+        assert!(reports[0].passed());
+        assert!(!reports[1].passed());
+        assert_eq!(
+            reports[1].to_string(),
+            "FAIL context\n  stack mismatch:\n    - [0] 8\n    + [0] 7\n"
+        );
+        assert!(reports[2].passed(), "{}", reports[2]);
+        assert!(reports[3].passed(), "{}", reports[3]);
+    }
+}
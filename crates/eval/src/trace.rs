@@ -1,12 +1,16 @@
 #[cfg(not(target_family = "wasm"))]
 use crate::fork::ForkTypedReturn;
 use alloy::primitives::{Address, U256};
+use alloy::sol_types::{Revert, SolError};
 #[cfg(not(target_family = "wasm"))]
 use foundry_evm::executors::RawCallResult;
 #[cfg(not(target_family = "wasm"))]
+use foundry_evm::traces::CallTraceNode;
+use rain_error_decoding::AbiDecodedErrorType;
+#[cfg(not(target_family = "wasm"))]
 use rain_interpreter_bindings::IInterpreterV4::{eval4Call, eval4Return};
 use revm::primitives::address;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use thiserror::Error;
 #[cfg(target_family = "wasm")]
 use wasm_bindgen_utils::{impl_wasm_traits, prelude::*};
@@ -19,7 +23,8 @@ pub const RAIN_TRACER_ADDRESS: Address = address!("F06Cd48c98d7321649dB7D8b2C396
 /// A struct representing a single trace from a Rain source. Intended to be decoded
 /// from the calldata sent as part of a noop call by the Interpreter to the
 /// non-existent tracer contract.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RainSourceTrace {
     pub parent_source_index: u16,
     pub source_index: u16,
@@ -57,14 +62,107 @@ impl RainSourceTrace {
     }
 }
 
+/// How an eval concluded.
+///
+/// In JSON the variant is named by `type`, e.g. `{ "type": "success" }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RainEvalOutcome {
+    /// The eval returned normally.
+    #[default]
+    Success,
+    /// An `ensure` condition was zero. `source_index` is the innermost source
+    /// the traces show was running when it reverted, see
+    /// [`RainEvalOutcome::from_revert`].
+    EnsureFailed {
+        reason: String,
+        #[serde(rename = "sourceIndex")]
+        source_index: u16,
+    },
+    /// The eval reverted for any other reason.
+    Reverted,
+}
+
+impl RainEvalOutcome {
+    /// Classifies the revert data of a reverted eval.
+    ///
+    /// `ensure` reverts with a plain `Error(string)`, which the interpreter
+    /// never uses for its own errors. Contracts called by ops may revert with
+    /// one too, so it is only an ensure failure when `raised_by_interpreter`,
+    /// see [`revert_raised_by`].
+    ///
+    /// Traces are only emitted when a source completes, so the parent of the
+    /// most recently completed source was still running at the revert. With
+    /// no traces the entry source is the only candidate. A source that is
+    /// entered after a sibling completed and fails before any source it
+    /// calls completes leaves no trace, so its caller is reported instead.
+    pub fn from_revert(
+        revert_data: &[u8],
+        traces: &[RainSourceTrace],
+        entry_source_index: u16,
+        raised_by_interpreter: bool,
+    ) -> Self {
+        match Revert::abi_decode(revert_data) {
+            Ok(revert) if raised_by_interpreter => RainEvalOutcome::EnsureFailed {
+                reason: revert.reason,
+                source_index: traces
+                    .first()
+                    .map_or(entry_source_index, |trace| trace.parent_source_index),
+            },
+            _ => RainEvalOutcome::Reverted,
+        }
+    }
+}
+
+/// Whether the revert of a call to `address` was raised in that call's own
+/// frame, rather than bubbled up unchanged from a call the frame made last.
+/// Calls to the tracer are ignored, as they never revert.
+#[cfg(not(target_family = "wasm"))]
+pub fn revert_raised_by(nodes: &[CallTraceNode], address: Address) -> bool {
+    let Some(frame) = nodes
+        .iter()
+        .find(|node| Address::from(node.trace.address.into_array()) == address)
+    else {
+        return false;
+    };
+    let last_call = frame
+        .children
+        .iter()
+        .rev()
+        .map(|&i| &nodes[i])
+        .find(|child| Address::from(child.trace.address.into_array()) != RAIN_TRACER_ADDRESS);
+    !last_call.is_some_and(|call| !call.trace.success && call.trace.output == frame.trace.output)
+}
+
 /// A struct representing the result of a Rain eval call. Contains the stack,
 /// writes, and traces. Can be constructed from a `ForkTypedReturn<eval4Call>`.
-#[derive(Debug, Clone)]
+///
+/// If the eval reverted, `traces` holds the sources that completed before the
+/// revert, `error` holds the decoded revert data and `outcome` says whether
+/// it was an `ensure` failure.
+///
+/// In JSON the error is its message.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RainEvalResult {
     pub reverted: bool,
     pub stack: Vec<U256>,
     pub writes: Vec<U256>,
     pub traces: Vec<RainSourceTrace>,
+    #[serde(serialize_with = "serialize_error")]
+    pub error: Option<AbiDecodedErrorType>,
+    pub outcome: RainEvalOutcome,
+}
+
+/// Serializes a decoded revert as its message.
+fn serialize_error<S: Serializer>(
+    error: &Option<AbiDecodedErrorType>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match error {
+        Some(error) => serializer.serialize_some(&error.to_string()),
+        None => serializer.serialize_none(),
+    }
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -96,6 +194,8 @@ impl TryFrom<ForkTypedReturn<eval4Call>> for RainEvalResult {
             stack: stack.into_iter().map(Into::into).collect(),
             writes: writes.into_iter().map(Into::into).collect(),
             traces,
+            error: None,
+            outcome: RainEvalOutcome::Success,
         })
     }
 }
@@ -138,6 +238,8 @@ impl TryFrom<RawCallResult> for RainEvalResult {
             stack: vec![],
             writes: vec![],
             traces,
+            error: None,
+            outcome: RainEvalOutcome::Success,
         })
     }
 }
@@ -333,6 +435,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
 
         let fork = Forker::new_with_fork(args, None, None).await.unwrap();
@@ -360,6 +464,10 @@ mod tests {
                 decode_errors: true,
                 state_overlay: vec![],
                 inputs: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
             })
             .await
             .unwrap();
@@ -407,6 +515,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let fork = Forker::new_with_fork(args, None, None).await.unwrap();
 
@@ -431,6 +541,10 @@ mod tests {
                 decode_errors: true,
                 state_overlay: vec![],
                 inputs: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
             })
             .await
             .unwrap();
@@ -472,6 +586,8 @@ mod tests {
         let args = NewForkedEvm {
             fork_url: local_evm.url(),
             fork_block_number: None,
+            rpc_cache: None,
+            rpc: Default::default(),
         };
         let fork = Forker::new_with_fork(args, None, None).await.unwrap();
 
@@ -498,6 +614,10 @@ mod tests {
                 decode_errors: true,
                 state_overlay: vec![],
                 inputs: vec![],
+                bytecode: None,
+                dispair: None,
+                from: None,
+                env: Default::default(),
             })
             .await
             .unwrap();
@@ -628,6 +748,94 @@ mod tests {
         assert_eq!(trace.stack[1], U256::from(0x0B));
     }
 
+    #[test]
+    fn test_outcome_from_revert() {
+        let ensure = Revert::from("always fails").abi_encode();
+        assert_eq!(
+            RainEvalOutcome::from_revert(&ensure, &[], 2, true),
+            RainEvalOutcome::EnsureFailed {
+                reason: "always fails".into(),
+                source_index: 2,
+            }
+        );
+
+        let traces = vec![
+            RainSourceTrace {
+                parent_source_index: 1,
+                source_index: 3,
+                stack: vec![],
+            },
+            RainSourceTrace {
+                parent_source_index: 0,
+                source_index: 1,
+                stack: vec![],
+            },
+        ];
+        assert_eq!(
+            RainEvalOutcome::from_revert(&ensure, &traces, 0, true),
+            RainEvalOutcome::EnsureFailed {
+                reason: "always fails".into(),
+                source_index: 1,
+            }
+        );
+
+        // Custom errors are not ensure failures.
+        assert_eq!(
+            RainEvalOutcome::from_revert(&[0xde, 0xad, 0xbe, 0xef], &traces, 0, true),
+            RainEvalOutcome::Reverted
+        );
+
+        // Nor are reasons bubbled up from contracts called by ops.
+        assert_eq!(
+            RainEvalOutcome::from_revert(&ensure, &traces, 0, false),
+            RainEvalOutcome::Reverted
+        );
+    }
+
+    fn call_node(
+        idx: usize,
+        parent: Option<usize>,
+        address: Address,
+        success: bool,
+        output: &[u8],
+    ) -> CallTraceNode {
+        let mut node = CallTraceNode {
+            idx,
+            parent,
+            ..Default::default()
+        };
+        node.trace.address = address;
+        node.trace.success = success;
+        node.trace.output = output.to_vec().into();
+        node
+    }
+
+    #[test]
+    fn test_revert_raised_by() {
+        let interpreter = Address::repeat_byte(0x1);
+        let token = Address::repeat_byte(0x2);
+        let reason = Revert::from("insufficient balance").abi_encode();
+
+        // The interpreter reverts on its own after tracing a source.
+        let mut nodes = vec![
+            call_node(0, None, interpreter, false, &reason),
+            call_node(1, Some(0), RAIN_TRACER_ADDRESS, true, &[]),
+        ];
+        nodes[0].children = vec![1];
+        assert!(revert_raised_by(&nodes, interpreter));
+
+        // A token call made by an op reverts and the interpreter bubbles it.
+        nodes.push(call_node(2, Some(0), token, false, &reason));
+        nodes[0].children.push(2);
+        assert!(!revert_raised_by(&nodes, interpreter));
+
+        // A failed call whose revert was handled is not the cause.
+        nodes[2].trace.output = vec![0xde, 0xad].into();
+        assert!(revert_raised_by(&nodes, interpreter));
+
+        assert!(!revert_raised_by(&nodes, Address::repeat_byte(0x3)));
+    }
+
     #[test]
     fn test_rain_eval_results_into_flattened_table_empty() {
         let rain_eval_results = RainEvalResults { results: vec![] };
@@ -654,6 +862,8 @@ mod tests {
             stack: vec![],
             writes: vec![],
             traces: vec![trace1, trace2],
+            error: None,
+            outcome: RainEvalOutcome::Success,
         };
 
         let rain_eval_results = RainEvalResults {
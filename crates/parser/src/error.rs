@@ -1,3 +1,6 @@
+use alloy::primitives::Bytes;
+use alloy::providers::MulticallError;
+use alloy::transports::{RpcError, TransportErrorKind};
 use alloy_ethers_typecast::{ReadContractParametersBuilderError, ReadableClientError};
 use thiserror::Error;
 
@@ -8,4 +11,19 @@ pub enum ParserError {
     ReadableClientError(#[from] ReadableClientError),
     #[error(transparent)]
     ReadContractParametersBuilderError(#[from] ReadContractParametersBuilderError),
+    /// Error raised by a non-RPC client, e.g. a forked EVM.
+    #[error("Client error: {0}")]
+    ClientError(Box<dyn std::error::Error + Send + Sync>),
+    #[error(transparent)]
+    RpcError(#[from] RpcError<TransportErrorKind>),
+    #[error(transparent)]
+    AbiDecodeError(#[from] alloy::sol_types::Error),
+    #[error(transparent)]
+    MulticallError(#[from] MulticallError),
+    /// `unsafe_parse` was called on a parser without a parser address.
+    #[error("No parser address provided")]
+    MissingParserAddress,
+    /// A single call within a multicall batch reverted.
+    #[error("Multicall call {index} failed with return data {return_data}")]
+    MulticallCallFailed { index: usize, return_data: Bytes },
 }
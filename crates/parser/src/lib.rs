@@ -1,7 +1,9 @@
 //! Rust client for the on-chain Rainlang parser contract.
 
 pub mod error;
+pub mod provider;
 pub mod v2;
 
 pub use crate::error::*;
+pub use crate::provider::*;
 pub use crate::v2::*;
@@ -0,0 +1,279 @@
+use crate::error::ParserError;
+use crate::v2::{Parser2, ParserV2, UnsafeParser2};
+use alloy::eips::BlockId;
+use alloy::network::{Ethereum, Network, TransactionBuilder};
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::{CallItem, Provider};
+use alloy::sol_types::SolCall;
+use rain_interpreter_bindings::IParserPragmaV1::*;
+use rain_interpreter_bindings::IParserV2::*;
+use rain_interpreter_bindings::RainterpreterParser::{unsafeParseCall, unsafeParseReturn};
+use std::marker::PhantomData;
+
+/// Any alloy [`Provider`] paired with an optional block to pin reads to.
+///
+/// When `block` is `None` reads go to the provider's default block
+/// (latest). Pinning a block makes repeated parses consistent with the
+/// parser that was deployed at that height.
+#[derive(Debug, Clone)]
+pub struct ProviderClient<P, N = Ethereum> {
+    /// The alloy provider used to make `eth_call` requests.
+    pub provider: P,
+    /// Optional block to make the calls at.
+    pub block: Option<BlockId>,
+    _network: PhantomData<fn() -> N>,
+}
+
+impl<P, N> ProviderClient<P, N> {
+    /// Creates a new `ProviderClient` that reads at the latest block.
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            block: None,
+            _network: PhantomData,
+        }
+    }
+
+    /// Pins all reads made through this client to the given block.
+    pub fn at_block(mut self, block: impl Into<BlockId>) -> Self {
+        self.block = Some(block.into());
+        self
+    }
+}
+
+impl<P: Provider<N>, N: Network> ProviderClient<P, N> {
+    /// Makes an `eth_call` to `to` and decodes the return of `T`.
+    async fn read<T: SolCall>(&self, to: Address, call: T) -> Result<T::Return, ParserError> {
+        let tx = N::TransactionRequest::default()
+            .with_to(to)
+            .with_input(call.abi_encode());
+        let mut eth_call = self.provider.call(tx);
+        if let Some(block) = self.block {
+            eth_call = eth_call.block(block);
+        }
+        let result = eth_call.await?;
+        Ok(T::abi_decode_returns(&result)?)
+    }
+}
+
+impl<P: Provider<N>, N: Network> Parser2<ProviderClient<P, N>> for ParserV2 {
+    async fn parse(
+        &self,
+        data: Vec<u8>,
+        client: ProviderClient<P, N>,
+    ) -> Result<parse2Return, ParserError> {
+        let bytecode = client
+            .read(self.deployer_address, parse2Call { data: data.into() })
+            .await?;
+
+        Ok(parse2Return { bytecode })
+    }
+
+    async fn parse_pragma(
+        &self,
+        data: Vec<u8>,
+        client: ProviderClient<P, N>,
+    ) -> Result<parsePragma1Return, ParserError> {
+        let pragma = client
+            .read(
+                self.deployer_address,
+                parsePragma1Call { data: data.into() },
+            )
+            .await?;
+
+        Ok(parsePragma1Return { _0: pragma })
+    }
+}
+
+impl<P: Provider<N>, N: Network> UnsafeParser2<ProviderClient<P, N>> for ParserV2 {
+    async fn unsafe_parse(
+        &self,
+        data: Vec<u8>,
+        client: ProviderClient<P, N>,
+    ) -> Result<unsafeParseReturn, ParserError> {
+        client
+            .read(
+                self.unsafe_parser_address()?,
+                unsafeParseCall { data: data.into() },
+            )
+            .await
+    }
+}
+
+impl ParserV2 {
+    /// Parses many rainlang texts in a single RPC round-trip by batching the
+    /// `parse2` calls through Multicall3.
+    ///
+    /// Results are returned in the same order as `texts`. A text that fails
+    /// to parse yields an error in its own slot without failing the batch.
+    pub async fn parse_text_multicall<P: Provider<N>, N: Network>(
+        &self,
+        texts: &[&str],
+        client: &ProviderClient<P, N>,
+    ) -> Result<Vec<Result<parse2Return, ParserError>>, ParserError> {
+        let mut multicall = client.provider.multicall().dynamic::<parse2Call>();
+        if let Some(block) = client.block {
+            multicall = multicall.block(block);
+        }
+        for text in texts {
+            let call = parse2Call {
+                data: text.as_bytes().to_vec().into(),
+            };
+            multicall = multicall.add_call_dynamic(
+                CallItem::<parse2Call>::new(self.deployer_address, call.abi_encode().into())
+                    .allow_failure(true),
+            );
+        }
+
+        let results = multicall.aggregate3().await?;
+
+        Ok(results
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| {
+                result
+                    .map(|bytecode| parse2Return { bytecode })
+                    .map_err(|failure| ParserError::MulticallCallFailed {
+                        index,
+                        return_data: failure.return_data,
+                    })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        hex,
+        providers::{ProviderBuilder, mock::Asserter},
+        sol,
+    };
+
+    sol! {
+        struct Aggregate3Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3Returns() returns (Aggregate3Result[] memory);
+    }
+
+    fn mocked_client(asserter: Asserter) -> ProviderClient<impl Provider> {
+        ProviderClient::new(ProviderBuilder::default().connect_mocked_client(asserter))
+    }
+
+    #[tokio::test]
+    async fn test_parse_text() {
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(parse2Call::abi_encode_returns(&Bytes::from(
+            hex!("1234").to_vec(),
+        ))));
+
+        let parser = ParserV2::new(Address::repeat_byte(0x1));
+        let result = parser
+            .parse_text("my rainlang", mocked_client(asserter))
+            .await
+            .unwrap();
+
+        assert_eq!(**result.bytecode, hex!("1234"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_text_at_block() {
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(parse2Call::abi_encode_returns(&Bytes::from(
+            hex!("5678").to_vec(),
+        ))));
+
+        let client = mocked_client(asserter).at_block(123u64);
+        assert_eq!(client.block, Some(BlockId::number(123)));
+
+        let parser = ParserV2::new(Address::repeat_byte(0x1));
+        let result = parser.parse_text("my rainlang", client).await.unwrap();
+
+        assert_eq!(**result.bytecode, hex!("5678"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_pragma_text() {
+        let pragma1 = Address::repeat_byte(0x11);
+        let pragma2 = Address::repeat_byte(0x22);
+
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(parsePragma1Call::abi_encode_returns(
+            &PragmaV1 {
+                usingWordsFrom: vec![pragma1, pragma2],
+            },
+        )));
+
+        let parser = ParserV2::new(Address::repeat_byte(0x1));
+        let result = parser
+            .parse_pragma_text("my rainlang", mocked_client(asserter))
+            .await
+            .unwrap();
+
+        assert_eq!(result, vec![pragma1, pragma2]);
+    }
+
+    #[tokio::test]
+    async fn test_unsafe_parse_text() {
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(unsafeParseCall::abi_encode_returns(
+            &unsafeParseReturn {
+                _0: hex!("1234").to_vec().into(),
+                _1: vec![],
+            },
+        )));
+
+        let parser =
+            ParserV2::new(Address::repeat_byte(0x1)).with_parser(Address::repeat_byte(0x2));
+        let result = parser
+            .unsafe_parse_text("my rainlang", mocked_client(asserter))
+            .await
+            .unwrap();
+
+        assert_eq!(**result._0, hex!("1234"));
+        assert!(result._1.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_parse_text_multicall() {
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(aggregate3ReturnsCall::abi_encode_returns(
+            &vec![
+                Aggregate3Result {
+                    success: true,
+                    returnData: parse2Call::abi_encode_returns(&Bytes::from(hex!("1234").to_vec()))
+                        .into(),
+                },
+                Aggregate3Result {
+                    success: false,
+                    returnData: hex!("deadbeef").to_vec().into(),
+                },
+                Aggregate3Result {
+                    success: true,
+                    returnData: parse2Call::abi_encode_returns(&Bytes::from(hex!("5678").to_vec()))
+                        .into(),
+                },
+            ],
+        )));
+
+        let parser = ParserV2::new(Address::repeat_byte(0x1));
+        let client = mocked_client(asserter);
+        let results = parser
+            .parse_text_multicall(&["a", "b", "c"], &client)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(**results[0].as_ref().unwrap().bytecode, hex!("1234"));
+        assert!(matches!(
+            &results[1],
+            Err(ParserError::MulticallCallFailed { index: 1, return_data })
+                if **return_data == hex!("deadbeef")
+        ));
+        assert_eq!(**results[2].as_ref().unwrap().bytecode, hex!("5678"));
+    }
+}
@@ -3,16 +3,20 @@ use alloy::primitives::Address;
 use alloy_ethers_typecast::{ReadContractParametersBuilder, ReadableClient};
 use rain_interpreter_bindings::IParserPragmaV1::*;
 use rain_interpreter_bindings::IParserV2::*;
+use rain_interpreter_bindings::RainterpreterParser::{unsafeParseCall, unsafeParseReturn};
 use rain_interpreter_dispair::DISPaiR;
 
 /// Trait for interacting with the on-chain Rainlang parser contract.
+///
+/// Generic over the client `C` that executes the read calls, so the same
+/// parser can be driven by a live RPC client or by a forked/in-memory EVM.
 #[cfg(not(target_family = "wasm"))]
-pub trait Parser2 {
+pub trait Parser2<C = ReadableClient> {
     /// Call Parser contract to parse the provided rainlang text.
     fn parse_text(
         &self,
         text: &str,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<parse2Return, ParserError>> + Send
     where
         Self: Sync,
@@ -25,7 +29,7 @@ pub trait Parser2 {
     fn parse(
         &self,
         data: Vec<u8>,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<parse2Return, ParserError>> + Send;
 
     /// Call Parser contract to parse the provided rainlang text and provide the pragma.
@@ -33,17 +37,18 @@ pub trait Parser2 {
     fn parse_pragma(
         &self,
         data: Vec<u8>,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<parsePragma1Return, ParserError>> + Send;
 
     /// Call Parser contract to parse the provided rainlang text and return the pragma addresses.
     fn parse_pragma_text(
         &self,
         text: &str,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<Vec<Address>, ParserError>> + Send
     where
         Self: Sync,
+        C: Send,
     {
         async {
             let res = self.parse_pragma(text.as_bytes().to_vec(), client).await?;
@@ -53,13 +58,16 @@ pub trait Parser2 {
 }
 
 /// Trait for interacting with the on-chain Rainlang parser contract.
+///
+/// Generic over the client `C` that executes the read calls, so the same
+/// parser can be driven by a live RPC client or by a forked/in-memory EVM.
 #[cfg(target_family = "wasm")]
-pub trait Parser2 {
+pub trait Parser2<C = ReadableClient> {
     /// Call Parser contract to parse the provided rainlang text.
     fn parse_text(
         &self,
         text: &str,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<parse2Return, ParserError>>
     where
         Self: Sync,
@@ -72,7 +80,7 @@ pub trait Parser2 {
     fn parse(
         &self,
         data: Vec<u8>,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<parse2Return, ParserError>>;
 
     /// Call Parser contract to parse the provided rainlang text and provide the pragma.
@@ -80,14 +88,14 @@ pub trait Parser2 {
     fn parse_pragma(
         &self,
         data: Vec<u8>,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<parsePragma1Return, ParserError>>;
 
     /// Call Parser contract to parse the provided rainlang text and return the pragma addresses.
     fn parse_pragma_text(
         &self,
         text: &str,
-        client: ReadableClient,
+        client: C,
     ) -> impl std::future::Future<Output = Result<Vec<Address>, ParserError>>
     where
         Self: Sync,
@@ -99,6 +107,60 @@ pub trait Parser2 {
     }
 }
 
+/// Parsers that can also call the parser contract directly, skipping the
+/// deployer, for clients that know the parser's address.
+#[cfg(not(target_family = "wasm"))]
+pub trait UnsafeParser2<C = ReadableClient>: Parser2<C> {
+    /// Call the parser contract's `unsafeParse` directly, skipping the
+    /// deployer's integrity check. The bytecode and constants are returned
+    /// exactly as the parser produced them, even if integrity would reject
+    /// them.
+    fn unsafe_parse(
+        &self,
+        data: Vec<u8>,
+        client: C,
+    ) -> impl std::future::Future<Output = Result<unsafeParseReturn, ParserError>> + Send;
+
+    /// Call the parser contract's `unsafeParse` on the provided rainlang text.
+    fn unsafe_parse_text(
+        &self,
+        text: &str,
+        client: C,
+    ) -> impl std::future::Future<Output = Result<unsafeParseReturn, ParserError>> + Send
+    where
+        Self: Sync,
+    {
+        self.unsafe_parse(text.as_bytes().to_vec(), client)
+    }
+}
+
+/// Parsers that can also call the parser contract directly, skipping the
+/// deployer, for clients that know the parser's address.
+#[cfg(target_family = "wasm")]
+pub trait UnsafeParser2<C = ReadableClient>: Parser2<C> {
+    /// Call the parser contract's `unsafeParse` directly, skipping the
+    /// deployer's integrity check. The bytecode and constants are returned
+    /// exactly as the parser produced them, even if integrity would reject
+    /// them.
+    fn unsafe_parse(
+        &self,
+        data: Vec<u8>,
+        client: C,
+    ) -> impl std::future::Future<Output = Result<unsafeParseReturn, ParserError>>;
+
+    /// Call the parser contract's `unsafeParse` on the provided rainlang text.
+    fn unsafe_parse_text(
+        &self,
+        text: &str,
+        client: C,
+    ) -> impl std::future::Future<Output = Result<unsafeParseReturn, ParserError>>
+    where
+        Self: Sync,
+    {
+        self.unsafe_parse(text.as_bytes().to_vec(), client)
+    }
+}
+
 /// Client-side wrapper around a deployer address that implements [`Parser2`]
 /// by making read calls to the on-chain deployer contract (which implements
 /// `IParserV2`).
@@ -108,28 +170,45 @@ pub trait Parser2 {
 pub struct ParserV2 {
     /// The address of the expression deployer (implements `IParserV2`).
     pub deployer_address: Address,
+    /// The address of the parser the deployer uses, needed only by
+    /// [`UnsafeParser2::unsafe_parse`]. `None` if it is unknown.
+    pub parser_address: Option<Address>,
 }
 
 impl From<DISPaiR> for ParserV2 {
     fn from(val: DISPaiR) -> Self {
         Self {
             deployer_address: val.deployer,
+            // A DISPaiR without a parser has the zero address.
+            parser_address: (val.parser != Address::ZERO).then_some(val.parser),
         }
     }
 }
 
 impl From<Address> for ParserV2 {
     fn from(val: Address) -> Self {
-        Self {
-            deployer_address: val,
-        }
+        Self::new(val)
     }
 }
 
 impl ParserV2 {
     /// Creates a new `ParserV2` for the given deployer address.
     pub fn new(deployer_address: Address) -> Self {
-        Self { deployer_address }
+        Self {
+            deployer_address,
+            parser_address: None,
+        }
+    }
+
+    /// Sets the parser address used by [`UnsafeParser2::unsafe_parse`].
+    pub fn with_parser(mut self, parser_address: Address) -> Self {
+        self.parser_address = Some(parser_address);
+        self
+    }
+
+    /// The parser address, or an error if it is unknown.
+    pub fn unsafe_parser_address(&self) -> Result<Address, ParserError> {
+        self.parser_address.ok_or(ParserError::MissingParserAddress)
     }
 }
 
@@ -173,10 +252,34 @@ impl Parser2 for ParserV2 {
     }
 }
 
+impl UnsafeParser2 for ParserV2 {
+    async fn unsafe_parse(
+        &self,
+        data: Vec<u8>,
+        client: ReadableClient,
+    ) -> Result<unsafeParseReturn, ParserError> {
+        client
+            .read(
+                ReadContractParametersBuilder::default()
+                    .address(self.unsafe_parser_address()?)
+                    .call(unsafeParseCall { data: data.into() })
+                    .build()
+                    .map_err(ParserError::ReadContractParametersBuilderError)?,
+            )
+            .await
+            .map_err(ParserError::ReadableClientError)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy::{hex, primitives::Address, providers::mock::Asserter};
+    use alloy::sol_types::SolCall;
+    use alloy::{
+        hex,
+        primitives::{Address, B256, Bytes},
+        providers::mock::Asserter,
+    };
 
     #[tokio::test]
     async fn test_from_dispair() {
@@ -193,6 +296,46 @@ mod tests {
 
         assert_eq!(parser.deployer_address, dispair.deployer);
         assert_eq!(parser.deployer_address, deployer_address);
+        assert_eq!(parser.parser_address, Some(dispair.parser));
+
+        let parser = ParserV2::from(DISPaiR {
+            parser: Address::ZERO,
+            ..dispair
+        });
+        assert_eq!(parser.parser_address, None);
+    }
+
+    #[tokio::test]
+    async fn test_unsafe_parse_text() {
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(unsafeParseCall::abi_encode_returns(
+            &unsafeParseReturn {
+                _0: hex!("1234").to_vec().into(),
+                _1: vec![B256::repeat_byte(0x5)],
+            },
+        )));
+
+        let client = ReadableClient::new_mocked(asserter);
+        let parser =
+            ParserV2::new(Address::repeat_byte(0x1)).with_parser(Address::repeat_byte(0x2));
+
+        let result = parser
+            .unsafe_parse_text("my rainlang", client)
+            .await
+            .unwrap();
+
+        assert_eq!(**result._0, hex!("1234"));
+        assert_eq!(result._1, vec![B256::repeat_byte(0x5)]);
+    }
+
+    #[tokio::test]
+    async fn test_unsafe_parse_without_parser() {
+        let client = ReadableClient::new_mocked(Asserter::new());
+        let parser = ParserV2::new(Address::repeat_byte(0x1));
+
+        let result = parser.unsafe_parse_text("my rainlang", client).await;
+
+        assert!(matches!(result, Err(ParserError::MissingParserAddress)));
     }
 
     #[tokio::test]
@@ -210,6 +353,7 @@ mod tests {
         let client = ReadableClient::new_mocked(asserter);
         let parser = ParserV2 {
             deployer_address: Address::repeat_byte(0x1),
+            ..Default::default()
         };
 
         let result = parser.parse_text("my rainlang", client).await.unwrap();
@@ -234,6 +378,7 @@ mod tests {
         let client = ReadableClient::new_mocked(asserter);
         let parser = ParserV2 {
             deployer_address: Address::repeat_byte(0x1),
+            ..Default::default()
         };
 
         let result = parser.parse_text(rainlang, client).await.unwrap();
@@ -263,6 +408,7 @@ mod tests {
         let client = ReadableClient::new_mocked(asserter);
         let parser = ParserV2 {
             deployer_address: Address::repeat_byte(0x1),
+            ..Default::default()
         };
 
         let result = parser.parse_pragma_text(rainlang, client).await.unwrap();